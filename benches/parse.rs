@@ -0,0 +1,58 @@
+//! Parsing throughput over a large synthetic module: `cargo bench --bench parse`.
+
+use std::hint::black_box;
+use std::io::Cursor;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use learning_llvm_tools::parser::{parse_ll_str, stream_ll_file};
+
+const FUNCTIONS: usize = 5000;
+
+/// A module of `count` small functions, each a loop around a two-way branch, like the bulk of
+/// a real module after optimization.
+fn synthetic_module(count: usize) -> String {
+    let mut text = String::from("source_filename = \"bench.c\"\ntarget triple = \"x86_64-pc-linux-gnu\"\n\n");
+    for n in 0..count {
+        text.push_str(&format!("\
+define i32 @f{n}(i32 %n, i1 %c) {{
+entry:
+  br label %loop
+
+loop:                                             ; preds = %latch, %entry
+  %i = phi i32 [ 0, %entry ], [ %next, %latch ]
+  br i1 %c, label %then, label %else
+
+then:                                             ; preds = %loop
+  %a = add i32 %i, 1
+  br label %latch
+
+else:                                             ; preds = %loop
+  %b = mul i32 %i, 3
+  br label %latch
+
+latch:                                            ; preds = %else, %then
+  %v = phi i32 [ %a, %then ], [ %b, %else ]
+  %next = add i32 %v, 1
+  %done = icmp sge i32 %next, %n
+  br i1 %done, label %exit, label %loop
+
+exit:                                             ; preds = %latch
+  ret i32 %next
+}}
+
+"));
+    }
+    text
+}
+
+fn parse(c: &mut Criterion) {
+    let text = synthetic_module(FUNCTIONS);
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(FUNCTIONS as u64));
+    group.sample_size(20);
+    group.bench_function("parse_ll_str", |b| b.iter(|| parse_ll_str(black_box(&text))));
+    group.bench_function("stream_ll_file", |b| b.iter(|| stream_ll_file(Cursor::new(black_box(text.as_bytes()))).into_module().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,437 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::cfg::Cfg;
+use crate::dom::{reverse_postorder, Dominators};
+use crate::ir::{opcode, Function, Module, Terminator};
+
+/// In/out degree summary of a function's CFG.
+#[derive(Clone, Debug)]
+pub struct GraphStats {
+    pub blocks: usize,
+    pub edges: usize,
+    /// The block with the most predecessors, and that count.
+    pub max_fan_in: Option<(String, usize)>,
+    /// The block with the most successors, and that count.
+    pub max_fan_out: Option<(String, usize)>,
+    /// Average of in-degree plus out-degree over all blocks.
+    pub average_degree: f64,
+    /// Blocks without predecessors (the entry block included).
+    pub sources: usize,
+    /// Blocks without successors.
+    pub leaves: usize,
+}
+
+pub fn graph_stats(function: &Function) -> GraphStats {
+    let cfg = Cfg::new(function);
+    let name = |index: usize| format!("%{}", function.blocks[index].name);
+    // max_by_key keeps the last maximum; prefer the first block in source order
+    let max_by = |degrees: &Vec<Vec<usize>>| (0..cfg.len())
+        .rev()
+        .max_by_key(|&i| degrees[i].len())
+        .map(|i| (name(i), degrees[i].len()));
+
+    let edges = cfg.edge_count();
+    GraphStats {
+        blocks: cfg.len(),
+        edges,
+        max_fan_in: max_by(&cfg.preds),
+        max_fan_out: max_by(&cfg.succs),
+        average_degree: if cfg.is_empty() { 0.0 } else { 2.0 * edges as f64 / cfg.len() as f64 },
+        sources: cfg.preds.iter().filter(|p| p.is_empty()).count(),
+        leaves: cfg.succs.iter().filter(|s| s.is_empty()).count(),
+    }
+}
+
+/// Counts the opcodes of `functions`, keeping only those matching `filter` when given.
+pub fn opcode_histogram(functions: &[&Function], filter: Option<&Regex>) -> BTreeMap<String, usize> {
+    let mut histogram = BTreeMap::new();
+    functions.iter()
+        .flat_map(|f| f.blocks.iter())
+        .flat_map(|b| b.instructions.iter())
+        .filter_map(|inst| opcode(inst))
+        .filter(|op| filter.is_none_or(|re| re.is_match(op)))
+        .for_each(|op| *histogram.entry(op.to_string()).or_insert(0) += 1);
+    histogram
+}
+
+/// Counts the blocks of `function` by terminator kind; blocks without a terminator are not counted.
+pub fn terminator_counts(function: &Function) -> BTreeMap<Terminator, usize> {
+    let mut counts = BTreeMap::new();
+    function.blocks.iter()
+        .filter_map(|b| b.terminator())
+        .for_each(|t| *counts.entry(t).or_insert(0) += 1);
+    counts
+}
+
+/// Marks the blocks reachable from `entry`.
+pub fn reachable_blocks(cfg: &Cfg, entry: usize) -> Vec<bool> {
+    let mut reachable = vec![false; cfg.len()];
+    if cfg.is_empty() {
+        return reachable;
+    }
+    let mut queue = VecDeque::from([entry]);
+    reachable[entry] = true;
+    while let Some(block) = queue.pop_front() {
+        for &succ in &cfg.succs[block] {
+            if !reachable[succ] {
+                reachable[succ] = true;
+                queue.push_back(succ);
+            }
+        }
+    }
+    reachable
+}
+
+/// Marks the blocks at most `hops` edges away from `center`, following edges in both directions.
+pub fn neighborhood(cfg: &Cfg, center: usize, hops: usize) -> Vec<bool> {
+    let mut distance = vec![usize::MAX; cfg.len()];
+    let mut queue = VecDeque::from([center]);
+    distance[center] = 0;
+    while let Some(block) = queue.pop_front() {
+        if distance[block] == hops {
+            continue;
+        }
+        for &next in cfg.succs[block].iter().chain(&cfg.preds[block]) {
+            if distance[next] == usize::MAX {
+                distance[next] = distance[block] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    distance.iter().map(|&d| d != usize::MAX).collect()
+}
+
+/// Counts the blocks outside `visible` reachable from `start` without entering `visible`,
+/// following successors when `forward` and predecessors otherwise.
+pub fn hidden_behind(cfg: &Cfg, start: usize, visible: &[bool], forward: bool) -> usize {
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(block) = queue.pop_front() {
+        let next = if forward { &cfg.succs[block] } else { &cfg.preds[block] };
+        for &n in next {
+            if !visible[n] && seen.insert(n) {
+                queue.push_back(n);
+            }
+        }
+    }
+    seen.len()
+}
+
+static BLOCKADDRESS_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"blockaddress\(\s*@("[^"]*"|[-\w.$]+)\s*,\s*%("[^"]*"|[-\w.$]+)\s*\)"#).unwrap());
+
+/// The `(function, block)` pairs referenced by a `blockaddress(@function, %block)` anywhere in the module.
+pub fn address_taken_blocks(module: &Module) -> HashSet<(String, String)> {
+    let globals = module.globals.iter().map(|g| &g.line);
+    let instructions = module.functions.iter().flat_map(|f| f.blocks.iter()).flat_map(|b| b.instructions.iter());
+    globals.chain(instructions)
+        .flat_map(|line| BLOCKADDRESS_RE.captures_iter(line).map(|c| (c[1].to_string(), c[2].to_string())).collect::<Vec<_>>())
+        .collect()
+}
+
+/// Why a block cannot be reached from the entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnreachableReason {
+    /// Nothing branches to the block.
+    NoPredecessors,
+    /// The block's address is taken by `blockaddress`, but the function has no `indirectbr` to jump there.
+    AddressTakenWithoutIndirectbr,
+    /// The block has predecessors, but all of them are unreachable themselves.
+    UnreachablePredecessors,
+}
+
+impl UnreachableReason {
+    /// Stable identifier used in reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnreachableReason::NoPredecessors => "no-predecessors",
+            UnreachableReason::AddressTakenWithoutIndirectbr => "address-taken-without-indirectbr",
+            UnreachableReason::UnreachablePredecessors => "unreachable-predecessors",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UnreachableBlock {
+    pub index: usize,
+    pub reason: UnreachableReason,
+}
+
+/// Lists the blocks of `function` that cannot be reached from `entry`, with the reason of each.
+/// `address_taken` is the result of [`address_taken_blocks`].
+pub fn unreachable_blocks(function: &Function, entry: usize, address_taken: &HashSet<(String, String)>) -> Vec<UnreachableBlock> {
+    let cfg = Cfg::new(function);
+    let reachable = reachable_blocks(&cfg, entry);
+    let has_indirectbr = function.blocks.iter()
+        .flat_map(|b| b.instructions.iter())
+        .any(|inst| opcode(inst) == Some("indirectbr"));
+
+    (0..cfg.len())
+        .filter(|&index| !reachable[index])
+        .map(|index| {
+            let name = (function.name.to_string(), function.blocks[index].name.to_string());
+            let reason = if address_taken.contains(&name) && !has_indirectbr {
+                UnreachableReason::AddressTakenWithoutIndirectbr
+            } else if cfg.preds[index].is_empty() {
+                UnreachableReason::NoPredecessors
+            } else {
+                UnreachableReason::UnreachablePredecessors
+            };
+            UnreachableBlock { index, reason }
+        })
+        .collect()
+}
+
+/// A natural loop: a header plus every block that reaches one of its back edges without passing through it.
+#[derive(Clone, Debug)]
+pub struct Loop {
+    pub header: usize,
+    /// The blocks of the loop in ascending order, the header included.
+    pub blocks: Vec<usize>,
+}
+
+/// Natural loops of the CFG, one per header (back edges sharing a header are merged).
+pub fn natural_loops(cfg: &Cfg, doms: &Dominators) -> Vec<Loop> {
+    let mut loops: Vec<Loop> = vec![];
+    for (tail, succs) in cfg.succs.iter().enumerate() {
+        for &header in succs.iter().filter(|&&h| doms.dominates(h, tail)) {
+            let mut body = vec![false; cfg.len()];
+            body[header] = true;
+            let mut stack = vec![tail];
+            while let Some(block) = stack.pop() {
+                if !body[block] {
+                    body[block] = true;
+                    stack.extend(cfg.preds[block].iter().copied());
+                }
+            }
+            let blocks: Vec<usize> = (0..cfg.len()).filter(|&b| body[b]).collect();
+            match loops.iter_mut().find(|l| l.header == header) {
+                Some(existing) => {
+                    existing.blocks.extend(blocks);
+                    existing.blocks.sort_unstable();
+                    existing.blocks.dedup();
+                }
+                None => loops.push(Loop { header, blocks }),
+            }
+        }
+    }
+    loops
+}
+
+/// Number of natural loops containing each block.
+pub fn loop_depths(cfg: &Cfg, loops: &[Loop]) -> Vec<usize> {
+    let mut depths = vec![0; cfg.len()];
+    loops.iter().flat_map(|l| l.blocks.iter()).for_each(|&b| depths[b] += 1);
+    depths
+}
+
+/// The number of blocks on the longest path from `entry` that follows no back edge (an edge
+/// to a block not after its source in reverse postorder), 0 for an empty CFG.
+pub fn longest_path(cfg: &Cfg, entry: usize) -> usize {
+    if cfg.is_empty() {
+        return 0;
+    }
+    let rpo = reverse_postorder(&cfg.succs, entry);
+    let mut position = vec![usize::MAX; cfg.len()];
+    rpo.iter().enumerate().for_each(|(at, &block)| position[block] = at);
+    let mut length = vec![0; cfg.len()];
+    length[entry] = 1;
+    for &block in &rpo {
+        for &succ in cfg.succs[block].iter().filter(|&&s| position[s] > position[block]) {
+            length[succ] = length[succ].max(length[block] + 1);
+        }
+    }
+    length.into_iter().max().unwrap_or(0)
+}
+
+/// A cycle of blocks that no single block dominates, so that it is no natural loop: control can
+/// enter it at several blocks, as after a `goto` into a loop body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IrreducibleRegion {
+    /// The blocks of the cycle, sorted.
+    pub blocks: Vec<usize>,
+    /// The blocks of the cycle with a predecessor outside it, or that are the entry, sorted.
+    pub entries: Vec<usize>,
+}
+
+/// The irreducible regions of the CFG: once the back edges of the natural loops (to a block
+/// dominating their source) are removed, a reducible CFG has no cycle left, and each strongly
+/// connected component of more than one block is an irreducible region.
+pub fn irreducible_regions(cfg: &Cfg, doms: &Dominators) -> Vec<IrreducibleRegion> {
+    if cfg.is_empty() {
+        return vec![];
+    }
+    let keep = |from: usize, to: usize| doms.is_reachable(from) && !doms.dominates(to, from);
+    let succs: Vec<Vec<usize>> = cfg.succs.iter().enumerate()
+        .map(|(from, succs)| succs.iter().copied().filter(|&to| keep(from, to)).collect())
+        .collect();
+    // Kosaraju: components of the reversed graph, taken in reverse postorder of the forward one
+    let mut component = vec![usize::MAX; cfg.len()];
+    let mut regions = vec![];
+    for root in reverse_postorder(&succs, doms.root) {
+        if component[root] != usize::MAX {
+            continue;
+        }
+        component[root] = root;
+        let mut blocks = vec![];
+        let mut stack = vec![root];
+        while let Some(block) = stack.pop() {
+            blocks.push(block);
+            for &pred in cfg.preds[block].iter().filter(|&&p| keep(p, block)) {
+                if component[pred] == usize::MAX {
+                    component[pred] = root;
+                    stack.push(pred);
+                }
+            }
+        }
+        if blocks.len() > 1 {
+            blocks.sort_unstable();
+            regions.push(blocks);
+        }
+    }
+    regions.into_iter().map(|blocks| {
+        let entries = blocks.iter().copied()
+            .filter(|&b| b == doms.root || cfg.preds[b].iter().any(|&p| doms.is_reachable(p) && blocks.binary_search(&p).is_err()))
+            .collect();
+        IrreducibleRegion { blocks, entries }
+    }).collect()
+}
+
+/// Whether a cycle is reachable from the entry, natural loop or not.
+pub fn has_cycle(cfg: &Cfg, doms: &Dominators) -> bool {
+    cfg.succs.iter().enumerate().any(|(from, succs)| {
+        succs.iter().any(|&to| match (doms.rpo_position(from), doms.rpo_position(to)) {
+            (Some(f), Some(t)) => t <= f,
+            _ => false,
+        })
+    })
+}
+
+/// Number of instructions in `function` (switch case lines and comments excluded).
+pub fn instruction_count(function: &Function) -> usize {
+    function.blocks.iter().flat_map(|b| b.instructions.iter()).filter(|inst| opcode(inst).is_some()).count()
+}
+
+/// Whether `inst` calls a debug info intrinsic: `@llvm.dbg.declare`, `@llvm.dbg.value`,
+/// `@llvm.dbg.assign` or `@llvm.dbg.label`. They only describe variables and labels to the
+/// debugger and generate no code, but fill `-O0 -g` IR.
+pub fn is_debug_intrinsic(inst: &str) -> bool {
+    opcode(inst) == Some("call")
+        && ["@llvm.dbg.declare(", "@llvm.dbg.value(", "@llvm.dbg.assign(", "@llvm.dbg.label("].iter().any(|name| inst.contains(name))
+}
+
+/// A copy of `function` without its debug intrinsic calls, for counts that reflect the generated code.
+pub fn without_debug_intrinsics<'a>(function: &Function<'a>) -> Function<'a> {
+    let mut function = function.clone();
+    for block in &mut function.blocks {
+        block.instructions.retain(|inst| !is_debug_intrinsic(inst));
+    }
+    function
+}
+
+/// Where each SSA value of `function` comes from, keyed by its name with the `%`:
+/// `add in %bb1` for instructions, `argument` for the function arguments.
+pub fn value_definitions(function: &Function) -> HashMap<String, String> {
+    let mut definitions: HashMap<String, String> = function.arguments().into_iter()
+        .map(|name| (format!("%{name}"), "argument".to_string()))
+        .collect();
+    for block in &function.blocks {
+        for inst in &block.instructions {
+            let Some((lhs, _)) = inst.trim().split_once(" = ") else { continue };
+            if lhs.starts_with('%') && !lhs.contains(' ') {
+                let opcode = opcode(inst).unwrap_or("?");
+                definitions.insert(lhs.to_string(), format!("{opcode} in %{}", block.name));
+            }
+        }
+    }
+    definitions
+}
+
+/// Size and complexity figures of one function.
+#[derive(Clone, Debug, Default)]
+pub struct FunctionMetrics {
+    pub blocks: usize,
+    pub instructions: usize,
+    pub edges: usize,
+    /// Cyclomatic complexity, `edges - blocks + 2`.
+    pub complexity: usize,
+    pub loops: usize,
+    /// Whether the CFG has a cycle, even one that is not a natural loop.
+    pub has_cycle: bool,
+    pub unreachable_blocks: usize,
+    /// Blocks per terminator kind, see [`terminator_counts`].
+    pub terminators: BTreeMap<Terminator, usize>,
+    /// The most predecessors of a block, as at a merge point.
+    pub max_fan_in: usize,
+    /// The most successors of a block, as at a big switch.
+    pub max_fan_out: usize,
+    /// The instructions with each block's counted `trip_count^depth` times, see [`loop_weighted_instructions`].
+    pub weighted_instructions: u64,
+}
+
+pub fn function_metrics(function: &Function, entry: usize) -> FunctionMetrics {
+    function_metrics_with_trip_count(function, entry, 1)
+}
+
+/// [`function_metrics`] with loops assumed to iterate `trip_count` times for `weighted_instructions`.
+pub fn function_metrics_with_trip_count(function: &Function, entry: usize, trip_count: u64) -> FunctionMetrics {
+    let cfg = Cfg::new(function);
+    if cfg.is_empty() {
+        return FunctionMetrics::default();
+    }
+    let doms = Dominators::new(&cfg, entry);
+    FunctionMetrics {
+        blocks: cfg.len(),
+        instructions: instruction_count(function),
+        edges: cfg.edge_count(),
+        complexity: (cfg.edge_count() + 2).saturating_sub(cfg.len()).max(1),
+        loops: natural_loops(&cfg, &doms).len(),
+        has_cycle: has_cycle(&cfg, &doms),
+        unreachable_blocks: (0..cfg.len()).filter(|&b| !doms.is_reachable(b)).count(),
+        terminators: terminator_counts(function),
+        max_fan_in: cfg.preds.iter().map(Vec::len).max().unwrap_or(0),
+        max_fan_out: cfg.succs.iter().map(Vec::len).max().unwrap_or(0),
+        weighted_instructions: loop_weighted_instructions(function, &cfg, &doms, trip_count),
+    }
+}
+
+/// A crude cost estimate: the instruction count with the instructions of a block nested `depth`
+/// natural loops deep counted `trip_count^depth` times, as if every loop ran `trip_count` times.
+/// Branch probabilities and the real trip counts are ignored; only the ranking means something.
+pub fn loop_weighted_instructions(function: &Function, cfg: &Cfg, doms: &Dominators, trip_count: u64) -> u64 {
+    let depths = loop_depths(cfg, &natural_loops(cfg, doms));
+    function.blocks.iter().zip(depths).map(|(block, depth)| {
+        let count = block.instructions.iter().filter(|i| opcode(i).is_some()).count() as u64;
+        count.saturating_mul(trip_count.saturating_pow(depth as u32))
+    }).fold(0, u64::saturating_add)
+}
+
+/// Module-wide roll-up of [`FunctionMetrics`].
+#[derive(Clone, Debug, Default)]
+pub struct ModuleMetrics {
+    pub functions: usize,
+    pub blocks: usize,
+    pub instructions: usize,
+    pub edges: usize,
+    pub total_complexity: usize,
+    pub max_complexity: usize,
+    pub functions_with_loops: usize,
+    pub functions_with_unreachable: usize,
+    pub terminators: BTreeMap<Terminator, usize>,
+}
+
+impl ModuleMetrics {
+    pub fn add(&mut self, metrics: &FunctionMetrics) {
+        self.functions += 1;
+        self.blocks += metrics.blocks;
+        self.instructions += metrics.instructions;
+        self.edges += metrics.edges;
+        self.total_complexity += metrics.complexity;
+        self.max_complexity = self.max_complexity.max(metrics.complexity);
+        self.functions_with_loops += metrics.has_cycle as usize;
+        self.functions_with_unreachable += (metrics.unreachable_blocks > 0) as usize;
+        for (&kind, &count) in &metrics.terminators {
+            *self.terminators.entry(kind).or_insert(0) += count;
+        }
+    }
+}
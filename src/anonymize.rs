@@ -0,0 +1,165 @@
+//! Consistent renaming of the identifiers of a module, for sharing CFGs outside the project.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::LazyLock;
+use regex::{Captures, Regex};
+use serde_json::{json, Value};
+use crate::parser::{function_end, parse_ll_str};
+
+/// Symbols kept by default besides the `llvm.*` intrinsics: well-known C library functions.
+pub const DEFAULT_ALLOWLIST: &[&str] = &[
+    "main", "malloc", "calloc", "realloc", "free", "memcpy", "memmove", "memset", "memcmp",
+    "strlen", "strcmp", "strncmp", "strcpy", "strncpy", "strchr", "strdup", "printf", "fprintf",
+    "sprintf", "snprintf", "puts", "putchar", "fputs", "fwrite", "fread", "fopen", "fclose",
+    "exit", "abort", "atexit", "__assert_fail", "__cxa_throw", "__cxa_begin_catch", "__cxa_end_catch",
+    "__gxx_personality_v0", "_Znwm", "_ZdlPv",
+];
+
+/// Maps every renamed identifier to its original name.
+#[derive(Clone, Debug, Default)]
+pub struct Mapping {
+    pub functions: BTreeMap<String, String>,
+    pub globals: BTreeMap<String, String>,
+    pub types: BTreeMap<String, String>,
+    /// Block labels, per anonymized function name.
+    pub blocks: BTreeMap<String, BTreeMap<String, String>>,
+    /// Arguments and other local values, per anonymized function name.
+    pub values: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Mapping {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "functions": self.functions,
+            "globals": self.globals,
+            "types": self.types,
+            "blocks": self.blocks,
+            "values": self.values,
+        })
+    }
+}
+
+/// Renames symbols in order of first appearance, with a fixed prefix.
+struct Renamer {
+    prefix: &'static str,
+    names: HashMap<String, String>,
+}
+
+impl Renamer {
+    fn new(prefix: &'static str) -> Self {
+        Renamer { prefix, names: HashMap::new() }
+    }
+
+    fn rename(&mut self, name: &str) -> String {
+        let next = self.names.len() + 1;
+        self.names.entry(name.to_string()).or_insert_with(|| format!("{}{next}", self.prefix)).clone()
+    }
+
+    fn reverse(&self) -> BTreeMap<String, String> {
+        self.names.iter().map(|(original, renamed)| (renamed.clone(), original.clone())).collect()
+    }
+}
+
+static GLOBAL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"@("[^"]*"|[-\w.$]+)"#).unwrap());
+static LOCAL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"%("[^"]*"|[-\w.$]+)"#).unwrap());
+static TYPE_DEF_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^%("[^"]*"|[-\w.$]+)\s*=\s*type\b"#).unwrap());
+static LABEL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([-\w.$]+):").unwrap());
+/// String constants, metadata strings and the string fields of metadata nodes, e.g. `name: "x"`.
+static STRING_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(c|!|:\s*)"[^"]*""#).unwrap());
+
+fn unquote(name: &str) -> &str {
+    name.strip_prefix('"').and_then(|n| n.strip_suffix('"')).unwrap_or(name)
+}
+
+/// Rewrites the module text with functions renamed to `f1, f2, ...`, other globals to `g1, ...`,
+/// named types to `T1, ...`, and block labels to `bb1, ...` and arguments and other named values to
+/// `v1, ...` within each function (numbered ones stay). String constants, metadata strings and the
+/// source file name are redacted. Symbols in `allowlist` and `llvm.*` intrinsics keep their names.
+pub fn anonymize(text: &str, allowlist: &HashSet<String>) -> (String, Mapping) {
+    let module = parse_ll_str(text);
+    let functions: HashSet<&str> = module.functions.iter().map(|f| f.name.as_ref())
+        .chain(module.declarations.iter().map(|d| d.name.as_ref()))
+        .collect();
+    let local_blocks: Vec<HashSet<&str>> = module.functions.iter()
+        .map(|f| f.blocks.iter().map(|b| b.name.as_ref()).filter(|n| !n.chars().all(|c| c.is_ascii_digit())).collect())
+        .collect();
+
+    let type_names: HashSet<String> = text.lines()
+        .filter_map(|line| TYPE_DEF_RE.captures(line).map(|c| c[1].to_string()))
+        .collect();
+
+    let (mut fn_names, mut global_names, mut types) = (Renamer::new("f"), Renamer::new("g"), Renamer::new("T"));
+    let mut mapping = Mapping::default();
+    let mut output = String::with_capacity(text.len());
+    let mut function_index = None; // index into module.functions while inside a body
+    let mut blocks = Renamer::new("bb");
+    let mut values = Renamer::new("v");
+    let mut next_function = 0;
+
+    for line in text.lines() {
+        if line.starts_with("define ") {
+            function_index = Some(next_function);
+            next_function += 1;
+            blocks = Renamer::new("bb");
+        }
+        let declaration = line.starts_with("declare ");
+        if line.starts_with("define ") || declaration {
+            values = Renamer::new("v");
+        }
+        let line = if line.starts_with("; ModuleID") || line.starts_with("source_filename") {
+            format!("{} = \"redacted\"", line.split('=').next().unwrap_or_default().trim_end())
+        } else {
+            STRING_RE.replace_all(line, "$1\"redacted\"").into_owned()
+        };
+        let line = GLOBAL_RE.replace_all(&line, |caps: &Captures| {
+            let name = unquote(&caps[1]);
+            if name.starts_with("llvm.") || allowlist.contains(name) {
+                caps[0].to_string()
+            } else if functions.contains(name) {
+                format!("@{}", fn_names.rename(name))
+            } else {
+                format!("@{}", global_names.rename(name))
+            }
+        });
+        let block_names = function_index.and_then(|i| local_blocks.get(i));
+        let in_function = block_names.is_some() || declaration;
+        let line = LOCAL_RE.replace_all(&line, |caps: &Captures| {
+            let name = unquote(&caps[1]);
+            if type_names.contains(&caps[1]) {
+                format!("%{}", types.rename(&caps[1]))
+            } else if block_names.is_some_and(|b| b.contains(name)) {
+                format!("%{}", blocks.rename(name))
+            } else if in_function && !name.chars().all(|c| c.is_ascii_digit()) {
+                format!("%{}", values.rename(name))
+            } else {
+                caps[0].to_string()
+            }
+        });
+        let line = match (block_names, LABEL_RE.captures(&line)) {
+            (Some(names), Some(caps)) if names.contains(&caps[1]) => {
+                let renamed = blocks.rename(&caps[1]);
+                format!("{renamed}:{}", &line[caps[0].len()..])
+            }
+            _ => line.into_owned(),
+        };
+        output.push_str(&line);
+        output.push('\n');
+        if declaration && !values.names.is_empty() {
+            if let Some(caps) = GLOBAL_RE.captures(&line) {
+                mapping.values.insert(unquote(&caps[1]).to_string(), values.reverse());
+            }
+        }
+        if function_end(&line).is_some() {
+            if let Some(index) = function_index.take() {
+                let name = module.functions[index].name.as_ref();
+                let renamed = if allowlist.contains(name) { name.to_string() } else { fn_names.rename(name) };
+                mapping.blocks.insert(renamed.clone(), blocks.reverse());
+                mapping.values.insert(renamed, values.reverse());
+            }
+        }
+    }
+    mapping.functions = fn_names.reverse();
+    mapping.globals = global_names.reverse();
+    mapping.types = types.reverse();
+    (output, mapping)
+}
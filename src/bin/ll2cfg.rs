@@ -1,211 +1,1584 @@
-use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read, Write};
+use std::io::{self, Read, Write};
 use std::path::Path;
 use clap::Parser;
+use regex::Regex;
+use serde_json::json;
+use learning_llvm_tools::anonymize::{anonymize, DEFAULT_ALLOWLIST};
+use learning_llvm_tools::analysis::{address_taken_blocks, function_metrics_with_trip_count, graph_stats, instruction_count, irreducible_regions, opcode_histogram, terminator_counts, unreachable_blocks, without_debug_intrinsics, FunctionMetrics, ModuleMetrics};
+use learning_llvm_tools::canvas::canvas_json;
+use learning_llvm_tools::callgraph::{dump_call_graph, dump_tail_chain, tail_chain, CallGraph, DEFAULT_CALL_PATTERN};
+use learning_llvm_tools::cfg::{cfg_json, Cfg};
+use learning_llvm_tools::condense::{collapse_allocas, condense_small_blocks, merge_identical_blocks, Condensed};
+use learning_llvm_tools::dedupe::{function_hash, group_duplicates, identical_functions, odr_conflicts, HashContent};
+use learning_llvm_tools::diff::{diff_functions, dump_cfg_diff};
+use learning_llvm_tools::dom::{dominator_chain, Dominators};
+use learning_llvm_tools::dot::{dump_dot, render_with_graphviz};
+use learning_llvm_tools::dot_cfg::parse_dot_cfg;
+use learning_llvm_tools::graphml::dump_graphml;
+use learning_llvm_tools::inline::{inline_preview, stitch_calls};
+use learning_llvm_tools::input::{open_input, uncompressed_name};
+use learning_llvm_tools::ir::{opcode, Function, Module, Terminator};
+use learning_llvm_tools::objdump::parse_objdump;
+use learning_llvm_tools::metrics::{compare_metrics, deltas_json, metrics_deltas, metrics_json, write_metrics_csv, Thresholds, DELTA_METRICS};
+use learning_llvm_tools::patterns::{find_patterns, self_loops, PatternKind};
+use learning_llvm_tools::progress::Progress;
+use learning_llvm_tools::pass_dump::function_changes;
+use learning_llvm_tools::parser::{parse_ll_file, parse_ll_str, parse_problems, stream_ll_file};
+use learning_llvm_tools::region::collapse_regions;
+use learning_llvm_tools::remarks::{debug_lines, parse_remarks, place_remarks};
+use learning_llvm_tools::render::{dump_cfg, fill_template, function_anchors, function_heading, write_function_ir, write_toc, DominatorNotes, EdgeBundling, MermaidKind, RenderOptions, DEFAULT_TEMPLATE};
+use learning_llvm_tools::split_output::{nested_file_names, split_file_names, SplitOutput, INDEX};
+use learning_llvm_tools::suggest::did_you_mean;
+use learning_llvm_tools::verify::{verify, verify_terminators};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    /// Markdown with a mermaid flowchart per function.
+    Mermaid,
+    /// A graphviz digraph with a cluster per function.
+    Dot,
+    /// The blocks and edges of each function.
+    Json,
+    /// Newline-delimited JSON: the object --format json gives each function, one per line. IR inputs
+    /// are streamed, so each function is written as soon as it is parsed.
+    Jsonl,
+    /// The dot output rendered by graphviz.
+    Svg,
+    Png,
+    /// Per-function metrics, one row each.
+    Csv,
+    /// One Obsidian .canvas file per function.
+    Canvas,
+    /// A GraphML document with a graph per function, for yEd and other graph editors.
+    Graphml,
+}
+
+impl Format {
+    fn from_extension(path: &str) -> Option<Format> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "md" | "mmd" => Format::Mermaid,
+            "dot" | "gv" => Format::Dot,
+            "json" => Format::Json,
+            "jsonl" | "ndjson" => Format::Jsonl,
+            "svg" => Format::Svg,
+            "png" => Format::Png,
+            "csv" => Format::Csv,
+            "canvas" => Format::Canvas,
+            "graphml" => Format::Graphml,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum InputFormat {
+    /// LLVM IR text.
+    Ll,
+    /// The .dot files written by `opt -dot-cfg` or `-dot-cfg-only`.
+    Dot,
+    /// `llvm-objdump -d` disassembly (x86-64 or AArch64), ideally with `-r --symbolize-operands`.
+    Objdump,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum SortFunctions {
+    /// The order of the input file.
+    Source,
+    Name,
+    /// Most blocks first.
+    Blocks,
+    /// Most instructions first.
+    Instructions,
+}
 
 #[derive(Parser, Debug)]
 #[command(about, version, author)]
 struct Args {
-    /// The input LLVM IR file.
-    input: String,
+    /// The input LLVM IR files. With several, their functions are combined as if in one module.
+    #[arg(required_unless_present_any = ["git_compare", "metrics_compare", "metrics_delta"])]
+    input: Vec<String>,
+
+    /// The kind of the input files. Inferred from their extension when not given: .dot and .gv files are
+    /// read as `opt -dot-cfg` output, .objdump files as disassembly, anything else as IR.
+    #[arg(long, value_enum)]
+    input_format: Option<InputFormat>,
 
     /// whether to include instructions inside basic blocks.
     #[arg(long, default_value = "false")]
     abbr: bool,
 
-    /// The function to generate the CFG for. if not specified, all functions are considered.
+    /// A function to generate the CFG for; repeat to select several. if not specified, all functions are considered.
     #[arg(short, long)]
-    function: Option<String>,
+    function: Vec<String>,
 
-    /// The output file(markdown) to write the CFG to. If not specified, the CFG is written to stdout.
+    /// The output file to write the CFG to; its extension selects the format. If not specified, the CFG is written to stdout.
+    /// With --format canvas, the path of the .canvas file, suffixed with the function name when there are several.
     #[arg(short, long)]
     output: Option<String>,
-}
 
-type BlockName = String;
+    /// Where the reports of the analysis modes (--aggregate, --graph-stats, --list-functions, ...) and the notes of
+    /// the transformations go: `stderr` or a file. The CFG is then rendered as well, keeping the output to the
+    /// chosen format only. Without it an analysis report is the whole output.
+    #[arg(long, value_name = "STDERR|FILE", alias = "output-stats-to")]
+    stats_output: Option<String>,
 
-#[derive(Clone, Debug)]
-struct BasicBlock {
-    name: BlockName,  // entry has name ""
-    instructions: Vec<String>,
-    predecessors: Vec<BlockName>,
-    successors: Vec<BlockName>,
-}
+    /// Write one document per function, named after the function, into the directory PATH, or into
+    /// a single zip archive when PATH ends in .zip. An index.md listing the functions comes along.
+    /// Works with the mermaid, dot, graphml and json formats.
+    #[arg(long, value_name = "PATH")]
+    split_output: Option<String>,
 
-#[derive(Debug)]
-#[allow(dead_code)]
-struct Function {
-    name: String,
-    define: String, // the define line
-    blocks: Vec<BasicBlock>,
-}
+    /// How --split-output names the documents: `flat`, all in PATH, or `nested`, in a subdirectory per namespace
+    /// of the (mangled or demangled) function name, mirroring the structure of the source.
+    #[arg(long, value_name = "LAYOUT", default_value = "flat", value_parser = ["flat", "nested"], alias = "split-output-flat", requires = "split_output")]
+    split_layout: String,
 
-#[allow(dead_code)]
-impl Function {
-    fn to_string(&self) -> String {
-        use std::fmt::Write;
-        let mut buffer = String::new();
+    /// The output format of the CFG. Inferred from the --output extension when not given, mermaid for stdout.
+    #[arg(long, value_enum)]
+    format: Option<Format>,
 
-        _ = writeln!(buffer, "Function: {}", self.name);
-        for block in &self.blocks {
-            _ = writeln!(buffer, "\tBlock: {}\t; preds = {}", block.name, block.predecessors.join(", "));
-            for instr in &block.instructions {
-                _ = writeln!(buffer, "\t\t  {}", instr);
-            }
-            _ = writeln!(buffer, "\t; successors = {}", block.successors.join(", "));
-        }
+    /// Write one graphviz digraph with a cluster per function. The dot format always does, so this only selects
+    /// it when neither --format nor the --output extension gives another.
+    #[arg(long, alias = "emit-graphviz-subgraph-per-function")]
+    combined_dot: bool,
 
-        buffer
-    }
+    /// Truncate each block label to at most N instruction lines.
+    #[arg(long, value_name = "N")]
+    max_lines: Option<usize>,
+
+    /// Warn about blocks whose label text exceeds N characters.
+    #[arg(long, value_name = "N")]
+    warn_on_large_node: Option<usize>,
+
+    /// The block to use as the entry, for IR whose entry block is named (e.g. `start`). Defaults to the first block.
+    #[arg(long, value_name = "NAME")]
+    entry_name: Option<String>,
+
+    /// Print per-function in/out degree statistics instead of the CFG.
+    #[arg(long)]
+    graph_stats: bool,
+
+    /// Print one line per function: name, blocks, instructions and flags (L: has loops,
+    /// U: has unreachable blocks, R: recursive), instead of the CFG.
+    #[arg(long)]
+    list_functions: bool,
+
+    /// Print the groups of functions with identical blocks, edges and instructions up to renaming (what
+    /// identical code folding would merge) instead of the CFG.
+    #[arg(long)]
+    find_duplicates: bool,
+
+    /// Overlay the optimization remarks of FILE (from `opt -pass-remarks-output=FILE`) on the diagrams: each remark
+    /// is added to the block holding an instruction whose `!dbg` line is the remark's line. Remarks of the selected
+    /// functions that match no block are listed after the diagrams.
+    #[arg(long, value_name = "FILE")]
+    remarks: Option<String>,
+
+    /// Print one `HASH NAME` line per function instead of the CFG, to diff between builds. The hash is a 64-bit
+    /// FNV-1a that ignores value and block names, comments and metadata attachments. `structure` (the default)
+    /// covers the blocks with their terminators and successors, `full` the instructions too.
+    #[arg(long, value_name = "WHAT", num_args = 0..=1, require_equals = true, default_missing_value = "structure", value_parser = ["structure", "full"])]
+    function_hash: Option<String>,
+
+    /// Leave the debug info intrinsics (`@llvm.dbg.declare`, `@llvm.dbg.value`, `@llvm.dbg.assign` and
+    /// `@llvm.dbg.label`) out of instruction counts, opcode histograms, metrics, the table of contents, --aggregate,
+    /// --list-functions, --find-self-loops, --find-duplicates and --sort-functions instructions. The diagrams still
+    /// show them.
+    #[arg(long, alias = "ignore-debug-intrinsics-in-counts")]
+    ignore_debug: bool,
+
+    /// The trip count assumed for every loop by `weighted_instructions` in --metrics and csv output, which counts
+    /// the instructions of a block N^(loop depth) times. A crude heuristic for ranking functions by cost.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    assume_trip_count: u64,
+
+    /// Warn on stderr (or --stats-output) about every irreducible region: a cycle that can be entered at several
+    /// blocks, so that it is no natural loop, as left by `goto` into a loop, asm goto or obfuscation.
+    #[arg(long)]
+    warn_irreducible: bool,
+
+    /// Print a table of the terminator kinds of each function (`ret`, `br`, conditional `condbr`, `switch`,
+    /// `invoke`, `unreachable`, ...) with a total row, instead of the CFG. Kinds no function uses are left out.
+    #[arg(long, alias = "count-terminators")]
+    terminator_stats: bool,
+
+    /// Print a table of opcode counts over the selected functions instead of the CFG.
+    #[arg(long)]
+    opcode_histogram: bool,
+
+    /// With --opcode-histogram, only count opcodes matching this regex, e.g. `^(load|store|alloca)$`.
+    #[arg(long, value_name = "REGEX", requires = "opcode_histogram")]
+    opcode_filter: Option<Regex>,
+
+    /// With --opcode-histogram, print one table per function instead of a module-wide one.
+    #[arg(long, requires = "opcode_histogram")]
+    per_function: bool,
+
+    /// List the diamonds (if/else that rejoin), triangles (if without else) and self-loops of each
+    /// function, optionally only the given kinds, instead of the CFG.
+    #[arg(long, value_name = "KIND", num_args = 0.., value_delimiter = ',', value_parser = ["diamond", "triangle", "self-loop"])]
+    find_patterns: Option<Vec<String>>,
+
+    /// List the blocks that branch to themselves (single-block loops such as spin loops and simple counters)
+    /// with their instruction count and other successors, instead of the CFG.
+    #[arg(long)]
+    find_self_loops: bool,
+
+    /// Print the blocks that cannot be reached from the entry, with the reason, instead of the CFG.
+    #[arg(long)]
+    unreachable: bool,
+
+    /// Also write the unreachable-block report as JSON to FILE.
+    #[arg(long, value_name = "FILE")]
+    unreachable_json: Option<String>,
+
+    /// Print module-wide totals (functions, blocks, instructions, edges, complexity, loops, unreachable code) instead of the CFG.
+    #[arg(long)]
+    aggregate: bool,
+
+    /// Also write the summary of --aggregate, or the deltas of --metrics-delta, as JSON to FILE.
+    #[arg(long, value_name = "FILE")]
+    summary_json: Option<String>,
+
+    /// Render the call graph of the module instead of the CFGs.
+    #[arg(long)]
+    call_graph: bool,
+
+    /// The regex finding the callee of a call instruction, for the call graph and the recursion flag of
+    /// --list-functions. The callee is the first capture group that matched. The default handles `call`,
+    /// `tail call`, `musttail call`, `invoke`, `callbr` and bitcast-wrapped callees.
+    #[arg(long, value_name = "REGEX", alias = "call-instruction-pattern", default_value = DEFAULT_CALL_PATTERN)]
+    call_pattern: Regex,
+
+    /// Render the chain of `musttail` calls starting at FUNCTION, as trampolined code runs it, instead of the
+    /// CFGs. The chain stops at a function without `musttail` calls, an external one, a cycle, or a function
+    /// tail-calling several others.
+    #[arg(long, value_name = "FUNCTION")]
+    tail_chain: Option<String>,
+
+    /// Give each function a distinct, stable fill color, in the call graph and in CFG nodes.
+    #[arg(long)]
+    color_by_function: bool,
+
+    /// The order in which functions are rendered and listed.
+    #[arg(long, value_enum, default_value_t = SortFunctions::Source)]
+    sort_functions: SortFunctions,
+
+    /// A markdown file laying out the document, with {title} (the module's source_filename, or else the input
+    /// file name), {toc}, {diagrams} and {metrics} placeholders filled in. The default is "{toc}{diagrams}".
+    #[arg(long, value_name = "FILE")]
+    output_template: Option<String>,
+
+    /// Do not write the table of contents that precedes the diagrams of several functions.
+    #[arg(long)]
+    no_toc: bool,
+
+    /// Write an HTML anchor, `<a id="fn-NAME"></a>`, before the diagram of each function, and point the table of
+    /// contents at them instead of at the headings, whose generated ids vary between markdown renderers.
+    #[arg(long, alias = "function-boundary-markers")]
+    anchors: bool,
+
+    /// After each diagram, append the function's verbatim IR in a collapsed <details> section.
+    #[arg(long)]
+    include_ir: bool,
+
+    /// Rename functions, globals, types, block labels and local values and redact strings, for sharing the output.
+    /// The mapping back to the original names is written to --anonymize-map, never to the output.
+    #[arg(long)]
+    anonymize: bool,
+
+    /// Where --anonymize writes its mapping. Defaults to <INPUT>.anonymize.json.
+    #[arg(long, value_name = "FILE", requires = "anonymize")]
+    anonymize_map: Option<String>,
+
+    /// A symbol --anonymize keeps, in addition to llvm.* intrinsics and well-known libc names.
+    #[arg(long, value_name = "NAME", requires = "anonymize")]
+    anonymize_keep: Vec<String>,
+
+    /// Only render the blocks within --hops CFG edges of BLOCK, in either direction.
+    #[arg(long, value_name = "BLOCK")]
+    around: Option<String>,
+
+    /// The radius of the --around neighborhood.
+    #[arg(long, value_name = "N", default_value_t = 2, requires = "around")]
+    hops: usize,
+
+    /// Only render the chain of immediate dominators from the entry down to BLOCK, as a line: the blocks
+    /// that must have executed whenever BLOCK does, unlike all the paths --around shows.
+    #[arg(long, value_name = "BLOCK")]
+    dom_path_to: Option<String>,
+
+    /// Collapse the out-edges of blocks with more than N successors (giant switches) into one note; 0 disables.
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    max_successors: usize,
+
+    /// Leave out the blocks of fewer than N instructions that fall through to a single block, appending
+    /// them to their predecessor or letting their predecessors branch past them.
+    #[arg(long, value_name = "N")]
+    min_block_size: Option<usize>,
+
+    /// Show each set of blocks with identical instructions (as tail duplication and unrolling leave) as one node,
+    /// labeled with the blocks it stands for and entered by all their predecessors. Display only: identical
+    /// blocks have the same successors, so the drawn paths are unchanged. The merged blocks are listed on stderr.
+    #[arg(long, alias = "merge-by-instruction-hash")]
+    merge_identical_blocks: bool,
+
+    /// Show each run of consecutive `alloca`s in the entry block as one `; N allocas` line, so that the entry of
+    /// `-O0` code does not dwarf the rest of the diagram. The number collapsed is reported on stderr.
+    #[arg(long, alias = "trim-entry-allocas")]
+    collapse_allocas: bool,
+
+    /// Collapse each single-entry single-exit region nested DEPTH deep into one node showing its entry, exit
+    /// and block count: 0 shows only the top-level structure, each level more reveals the next one.
+    #[arg(long, value_name = "DEPTH")]
+    collapse_by_region: Option<usize>,
+
+    /// With several inputs, render each distinct function body once, listing the files and linkage
+    /// it occurs with, and call out names that have different bodies (ODR violations).
+    #[arg(long)]
+    dedupe: bool,
+
+    /// Append the number of predecessors of each block to its label, as in `[2→]`, to spot merge points.
+    #[arg(long)]
+    annotate_predecessor_count: bool,
+
+    /// Append the number of successors of each block to its label, as in `[→3]`, to spot branch points;
+    /// with --annotate-predecessor-count both counts share one badge, `[2→3]`.
+    #[arg(long)]
+    annotate_successor_count: bool,
+
+    /// Label each block with its immediate dominator (`idom: none` for the entry), and with `full`
+    /// also with all its dominators.
+    #[arg(long, value_name = "WHAT", num_args = 0..=1, default_missing_value = "idom", value_parser = ["idom", "full"])]
+    annotate_dominators: Option<String>,
+
+    /// For the dot, svg and png formats, bundle the edges of dense CFGs: `concentrate` (the default) merges
+    /// parallel edges, `ortho` routes them axis-parallel with the labels beside them. Edges into blocks with
+    /// many predecessors share a head port, and out of blocks with many successors a tail port. Only the
+    /// `dot` engine honors all of these; `neato` and `fdp` honor `ortho` alone.
+    #[arg(long, value_name = "HOW", num_args = 0..=1, require_equals = true, default_missing_value = "concentrate", value_parser = ["concentrate", "ortho"])]
+    edge_bundling: Option<String>,
+
+    /// For the dot format, give every node the attributes `blockname`, `insts` (instruction count), `term` (the
+    /// terminator), `loopdepth` and `reachable` (from the entry), for downstream tools to style or filter on.
+    #[arg(long, alias = "export-dot-with-attrs")]
+    dot_attrs: bool,
+
+    /// The kind of mermaid diagram: a `flowchart`, or a `stateDiagram-v2` whose entry is entered from `[*]`, whose
+    /// returns lead to `[*]` and whose transitions are labeled with the branch conditions.
+    #[arg(long, value_name = "KIND", default_value = "flowchart", value_parser = ["flowchart", "state"])]
+    mermaid_kind: String,
+
+    /// Lay out each mermaid flowchart left to right when the most successors of a block outnumber the blocks on
+    /// the longest acyclic path from the entry (wide CFGs such as big switches), top down otherwise.
+    #[arg(long)]
+    auto_direction: bool,
+
+    /// Give each block the node shape of its terminator: conditional branches diamonds, switches hexagons,
+    /// returns rounded, unreachable asymmetric, invokes subroutines, indirectbr parallelograms, EH stadiums.
+    #[arg(long)]
+    shape_by_terminator: bool,
+
+    /// Follow each instruction in the block labels with a comment telling where the values it uses
+    /// are defined, e.g. `; %3 = add in %bb1, %a = argument`.
+    #[arg(long)]
+    resolve_values: bool,
+
+    /// Show the types of the operands instead of the SSA values, e.g. `store i32, ptr` for `store i32 %x, ptr %p`,
+    /// to compare functions that only differ in value numbering. A heuristic: constants, globals and labels stay.
+    #[arg(long, alias = "replace-values-with-types")]
+    show_types: bool,
+
+    /// Show a progress line on stderr while parsing (a spinner counting the functions) and rendering (a bar
+    /// over the selected functions) big modules. Only drawn when stderr is a terminal.
+    #[arg(long)]
+    progress: bool,
+
+    /// Never draw the progress line, even with --progress (e.g. from a shell alias).
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Cut edge labels (target blocks, branch conditions) longer than N characters with an ellipsis. The full label
+    /// stays in a `%%` comment before the edge (mermaid) or a `comment` attribute (dot). 0 keeps them whole.
+    #[arg(long, value_name = "N", default_value_t = 40)]
+    max_edge_label_length: usize,
+
+    /// Show the `; Function Attrs:` of each function in a note next to its entry block.
+    #[arg(long)]
+    show_attrs: bool,
+
+    /// Write per-function metrics and module totals as a versioned JSON document to FILE.
+    #[arg(long, value_name = "FILE")]
+    metrics: Option<String>,
+
+    /// Compare two --metrics documents and list the functions that regressed, exiting with status 1 if any did.
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    metrics_compare: Option<Vec<String>>,
+
+    /// Compute the metrics of the functions of two IR files, matched by name, and print a table of their changes in
+    /// blocks, instructions, complexity and edges, largest instruction change first. Functions in one file only are
+    /// listed as added or removed. With --summary-json the deltas are also written as JSON.
+    #[arg(long, num_args = 2, value_names = ["A.ll", "B.ll"], alias = "compare-metrics")]
+    metrics_delta: Option<Vec<String>>,
+
+    /// With --metrics-compare, the allowed increase of a function's cyclomatic complexity.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    complexity_threshold: u64,
+
+    /// With --metrics-compare, the allowed growth of a function's instruction count, in percent.
+    #[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+    size_threshold: f64,
+
+    /// Check that every block ends with exactly one terminator, listing violations and exiting with status 1 if any.
+    #[arg(long)]
+    check_terminators: bool,
 
+    /// Run every IR check: terminators, and phi incoming blocks against the computed predecessors.
+    /// Lists violations and exits with status 1 if any.
+    #[arg(long)]
+    validate: bool,
+
+    /// Only parse the inputs: report the first construct the parser skips or misreads in each file,
+    /// and print how many functions and blocks were read.
+    #[arg(long)]
+    parse_only: bool,
+
+    /// With --parse-only, exit with status 1 when a problem is found instead of only warning about it.
+    #[arg(long, requires = "parse_only")]
+    strict: bool,
+
+    /// For the CI gates (--check-terminators, --validate, --metrics-compare, --git-compare): print nothing when
+    /// the check passes, and only the violations, regressions or differences when it fails.
+    #[arg(long)]
+    summary_only_on_error: bool,
+
+    /// Render FUNCTION as one interprocedural CFG: every call to a function defined in the module becomes an
+    /// edge into a copy of the callee's blocks (`<callee>.<n>.<block>`) whose returns lead back to the rest of
+    /// the calling block (`<block>.ret<k>`), see --inline-depth.
+    #[arg(long, value_name = "FUNCTION")]
+    stitch: Option<String>,
+
+    /// With --stitch, how many levels of calls to expand. Recursion stops at this depth, where a recursive
+    /// call gets an edge back to the entry of the enclosing copy of its callee.
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "stitch")]
+    inline_depth: usize,
+
+    /// Render CALLER with the CFG of CALLEE spliced in at one of its call sites, to judge an inlining decision.
+    #[arg(long, value_name = "CALLER:CALLEE")]
+    inline_preview: Option<String>,
+
+    /// With --inline-preview, which call site (1-based, in program order) to splice at.
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "inline_preview")]
+    call_site: usize,
+
+    /// Compare the input (before) with this IR file (after), matching functions by name.
+    #[arg(long, value_name = "AFTER")]
+    diff_cfg: Option<String>,
+
+    /// Read the input as an `opt -print-after-all` log and show how each pass transformed the one --function:
+    /// a section per pass that changed it, with its CFG before and after side by side and a summary of the changes.
+    #[arg(long, requires = "function")]
+    diff_passes: bool,
+
+    /// With --diff-cfg or --git-compare, render the before and after diagrams separately instead of one merged graph.
+    #[arg(long)]
+    side_by_side: bool,
+
+    /// Compare an IR file between two git revisions, each given as REV:PATH (e.g. HEAD~1:build/foo.ll HEAD:build/foo.ll).
+    /// Exits with status 1 when the CFGs differ.
+    #[arg(long, num_args = 2, value_names = ["BEFORE", "AFTER"])]
+    git_compare: Option<Vec<String>>,
+
+    /// The repository used by --git-compare.
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    git_repo: String,
 }
 
+impl Args {
+    /// Whether `function` is named by one of the --function flags, or there are none.
+    fn selects(&self, function: &Function) -> bool {
+        self.function.is_empty() || self.function.iter().any(|name| function.name == *name)
+    }
 
-fn main() -> io::Result<()> {
+    /// The first input file.
+    fn input(&self) -> &str {
+        self.input.first().map(String::as_str).unwrap_or_default()
+    }
 
-    let args = Args::parse();
+    /// The explicit --input-format, else the one implied by the extension of `input`.
+    fn input_format(&self, input: &str) -> InputFormat {
+        let extension = Path::new(uncompressed_name(input)).extension().and_then(|e| e.to_str());
+        self.input_format.unwrap_or(match extension {
+            Some("dot" | "gv") => InputFormat::Dot,
+            Some("objdump") => InputFormat::Objdump,
+            _ => InputFormat::Ll,
+        })
+    }
 
-    if !Path::new(&args.input).exists() {
-        eprintln!("Input file does not exist: {}", args.input);
-        std::process::exit(1);
+    /// Whether the output is plain JSON lines of IR inputs, which can be written while parsing: no analysis mode,
+    /// transformation or other option needing the whole module is given.
+    fn streams_json_lines(&self) -> bool {
+        let analyses = self.check_terminators || self.validate || self.graph_stats || self.list_functions
+            || self.function_hash.is_some() || self.find_duplicates || self.terminator_stats || self.opcode_histogram
+            || self.find_patterns.is_some() || self.find_self_loops || self.unreachable || self.aggregate
+            || self.metrics.is_some() || self.warn_irreducible || self.call_graph || self.tail_chain.is_some();
+        let transforms = self.min_block_size.is_some() || self.merge_identical_blocks
+            || self.collapse_allocas || self.collapse_by_region.is_some() || self.dom_path_to.is_some()
+            || self.stitch.is_some() || self.inline_preview.is_some() || self.diff_cfg.is_some() || self.diff_passes
+            || self.dedupe || self.anonymize || self.remarks.is_some() || self.split_output.is_some();
+        self.format() == Ok(Format::Jsonl) && !analyses && !transforms && self.sort_functions == SortFunctions::Source
+            && self.input.iter().all(|input| self.input_format(input) == InputFormat::Ll)
+    }
+
+    /// Whether --progress asks for a progress line and --quiet does not forbid it.
+    fn shows_progress(&self) -> bool {
+        self.progress && !self.quiet
+    }
+
+    /// The explicit --format, else the one implied by the --output extension.
+    fn format(&self) -> Result<Format, String> {
+        match (self.format, &self.output) {
+            (Some(format), _) => Ok(format),
+            (None, Some(output)) => Format::from_extension(output)
+                .ok_or_else(|| format!("cannot infer the output format of {output}; pass --format")),
+            (None, None) if self.combined_dot => Ok(Format::Dot),
+            (None, None) => Ok(Format::Mermaid),
+        }
+    }
+
+    /// The rendering options given on the command line, shared by every mode drawing CFGs.
+    fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            abbr: self.abbr,
+            max_lines: self.max_lines,
+            entry_name: self.entry_name.clone(),
+            color_by_function: self.color_by_function,
+            show_attrs: self.show_attrs,
+            max_successors: (self.max_successors > 0).then_some(self.max_successors),
+            around: self.around.clone().map(|block| (block, self.hops)),
+            shape_by_terminator: self.shape_by_terminator,
+            resolve_values: self.resolve_values,
+            value_types: self.show_types,
+            auto_direction: self.auto_direction,
+            max_edge_label: (self.max_edge_label_length > 0).then_some(self.max_edge_label_length),
+            dot_attrs: self.dot_attrs,
+            predecessor_count: self.annotate_predecessor_count,
+            successor_count: self.annotate_successor_count,
+            mermaid_kind: if self.mermaid_kind == "state" { MermaidKind::State } else { MermaidKind::Flowchart },
+            edge_bundling: self.edge_bundling.as_deref().map(|how| if how == "ortho" { EdgeBundling::Ortho } else { EdgeBundling::Concentrate }),
+            dominators: self.annotate_dominators.as_deref().map(|what| if what == "full" { DominatorNotes::Full } else { DominatorNotes::Idom }),
+            ..Default::default()
+        }
     }
+}
+
+fn main() -> io::Result<()> {
 
-    let mut reader = io::BufReader::new( File::open(&args.input)? );
-    let result = parse_ll_file(&mut reader)?;
+    let mut args = Args::parse();
 
-    let output: &mut dyn Write = if let Some(output) = &args.output {
+    // canvas files are named after --output, the listing of written files goes to stdout
+    let output: &mut dyn Write = if let Some(output) = args.output.as_ref().filter(|_| args.format() != Ok(Format::Canvas)) {
         &mut File::create(output)?
     }
     else {
         &mut io::stdout()
     };
+    let mut stats: Option<Box<dyn Write>> = match args.stats_output.as_deref() {
+        None => None,
+        Some(to) if to.eq_ignore_ascii_case("stderr") => Some(Box::new(io::stderr())),
+        Some(file) => Some(Box::new(File::create(file)?)),
+    };
+
+    if let Some(specs) = &args.git_compare {
+        return git_compare(output, &args, &specs[0], &specs[1]);
+    }
+    if let Some(files) = &args.metrics_compare {
+        return metrics_compare(output, &args, &files[0], &files[1]);
+    }
+    if let Some(files) = &args.metrics_delta {
+        return metrics_delta(output, &args, &files[0], &files[1]);
+    }
 
-    if let Some(func_name) = &args.function {
-        result.iter().filter(|f| f.name == *func_name).for_each(|f| {
-            dump_cfg(output, f, args.abbr);
+    if let Some(missing) = args.input.iter().find(|input| !Path::new(input).exists()) {
+        eprintln!("Input file does not exist: {missing}");
+        std::process::exit(1);
+    }
+    if args.parse_only {
+        return parse_only(output, &args);
+    }
+    if args.streams_json_lines() {
+        return stream_json_lines(output, &args);
+    }
+    if args.diff_passes {
+        return diff_passes(output, &args);
+    }
+    if args.call_pattern.captures_len() < 2 {
+        eprintln!("--call-pattern needs a capture group for the callee name");
+        std::process::exit(2);
+    }
+    if args.anonymize && args.input.len() > 1 {
+        eprintln!("--anonymize takes a single input file");
+        std::process::exit(2);
+    }
+    if args.anonymize && args.input_format(args.input()) != InputFormat::Ll {
+        eprintln!("--anonymize takes an IR input file");
+        std::process::exit(2);
+    }
+
+    let module = if args.anonymize {
+        let mut reader = open_input(args.input())?;
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let allowlist: HashSet<String> = DEFAULT_ALLOWLIST.iter().map(|s| s.to_string())
+            .chain(args.anonymize_keep.iter().cloned())
+            .collect();
+        let (text, mapping) = anonymize(&text, &allowlist);
+        let map_file = args.anonymize_map.clone().unwrap_or_else(|| format!("{}.anonymize.json", args.input()));
+        std::fs::write(&map_file, serde_json::to_string_pretty(&mapping.to_json()).unwrap() + "\n")?;
+        eprintln!("anonymization mapping written to {map_file}");
+        // -f still takes the original name
+        for name in args.function.iter_mut() {
+            if let Some((renamed, _)) = mapping.functions.iter().find(|(_, original)| *original == name) {
+                *name = renamed.clone();
+            }
+        }
+        stream_ll_file(io::Cursor::new(text)).keep_raw(args.include_ir).into_module()?
+    } else {
+        let mut module = Module::default();
+        let mut parsing = Progress::new("parsing", None, args.shows_progress());
+        for (index, input) in args.input.iter().enumerate() {
+            let mut part = match args.input_format(input) {
+                InputFormat::Ll => {
+                    let mut stream = stream_ll_file(open_input(input)?).keep_raw(args.include_ir);
+                    let mut functions = vec![];
+                    for function in stream.by_ref() {
+                        functions.push(function?);
+                        parsing.inc();
+                    }
+                    stream.module.functions = functions;
+                    stream.module
+                }
+                format => {
+                    let mut text = String::new();
+                    open_input(input)?.read_to_string(&mut text)?;
+                    if format == InputFormat::Dot { parse_dot_cfg(&text) } else { parse_objdump(&text) }
+                }
+            };
+            part.set_index(index);
+            module.merge(part);
+        }
+        parsing.finish();
+        module
+    };
+
+    let mut functions: Vec<&Function> = module.functions.iter()
+        .filter(|f| args.selects(f))
+        .collect();
+    match args.sort_functions {
+        SortFunctions::Source => {}
+        SortFunctions::Name => functions.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortFunctions::Blocks => functions.sort_by_key(|f| std::cmp::Reverse(f.blocks.len())),
+        SortFunctions::Instructions => functions.sort_by_cached_key(|f| std::cmp::Reverse(metrics_of(&args, f).instructions)),
+    }
+    // with --ignore-debug the counting modes below see the functions without their debug intrinsics
+    let stripped = counted_functions(&args, &functions);
+    let counted: Vec<&Function> = stripped.iter().map(|f| &**f).collect();
+
+    if args.warn_irreducible {
+        for function in &functions {
+            let cfg = Cfg::new(function);
+            if cfg.is_empty() {
+                continue;
+            }
+            let doms = Dominators::new(&cfg, function.entry_index(args.entry_name.as_deref()));
+            for region in irreducible_regions(&cfg, &doms) {
+                let names = |blocks: &[usize]| blocks.iter().map(|&b| format!("%{}", function.blocks[b].name)).collect::<Vec<_>>().join(", ");
+                note(&mut stats, &format!("warning: @{}: irreducible control flow through {}, entered at {}",
+                    function.name, names(&region.blocks), names(&region.entries)))?;
+            }
+        }
+    }
+
+    // with --stats-output the analyses go there and the CFG is still rendered to the output
+    let analysed = match stats.as_mut() {
+        Some(stats) => print_analyses(stats, &args, &module, &functions, &counted)?,
+        None => print_analyses(output, &args, &module, &functions, &counted)?,
+    };
+    if analysed && stats.is_none() {
+        return Ok(());
+    }
+
+    if let Some(start) = &args.tail_chain {
+        let start = start.strip_prefix('@').unwrap_or(start);
+        if module.function(start).is_none() {
+            let hint = did_you_mean(start, module.functions.iter().map(|f| f.name.as_ref())).map(|c| format!("; did you mean @{c}?")).unwrap_or_default();
+            eprintln!("error: no function @{start}{hint}");
+            std::process::exit(2);
+        }
+        return dump_tail_chain(output, &tail_chain(&module, start));
+    }
+
+    if args.call_graph {
+        return dump_call_graph(output, &CallGraph::with_call_pattern(&module, &args.call_pattern), args.color_by_function);
+    }
+
+    let mut options = args.render_options();
+
+    let mut unlocated_remarks = vec![];
+    if let Some(file) = &args.remarks {
+        let remarks = parse_remarks(&std::fs::read_to_string(file)?);
+        let mut lines = vec![];
+        for input in &args.input {
+            lines.push(if args.input_format(input) == InputFormat::Ll { debug_lines(open_input(input)?)? } else { HashMap::new() });
+        }
+        let placement = place_remarks(&remarks, &functions, &lines);
+        for (key, indices) in placement.blocks {
+            let mut notes: Vec<String> = vec![];
+            for summary in indices.iter().map(|&i| remarks[i].summary()) {
+                if !notes.contains(&summary) {
+                    notes.push(summary);
+                }
+            }
+            options.block_notes.insert(key, notes);
+        }
+        unlocated_remarks = placement.unlocated.iter().map(|&i| &remarks[i])
+            .filter(|r| functions.iter().any(|f| f.name == r.function))
+            .map(|r| format!("- `{}`: {}{}\n", r.function, r.summary(), r.line.map(|l| format!(" (line {l})")).unwrap_or_default()))
+            .collect();
+        unlocated_remarks.dedup();
+    }
+
+    if let Some(spec) = &args.inline_preview {
+        let Some((caller, callee)) = spec.split_once(':') else {
+            eprintln!("--inline-preview expects CALLER:CALLEE, got {spec}");
+            std::process::exit(2);
+        };
+        let (caller, callee) = (caller.trim_start_matches('@'), callee.trim_start_matches('@'));
+        let preview = inline_preview(&module, caller, callee, args.call_site).unwrap_or_else(|err| {
+            eprintln!("error: {err}");
+            std::process::exit(2);
         });
+        if preview.call_sites > 1 {
+            eprintln!("note: @{caller} calls @{callee} {} times; showing call site {} (see --call-site)", preview.call_sites, args.call_site);
+        }
+        writeln!(output, "## inline preview: @{callee} into @{caller}, call site {} of {}\n", args.call_site, preview.call_sites)?;
+        let options = RenderOptions { cluster: Some((format!("inlined @{callee}"), preview.spliced)), ..options };
+        dump_cfg(output, &preview.function, &options);
+        return Ok(());
     }
-    else {
-        result.iter().for_each(|f| {
-            dump_cfg(output, f, args.abbr);
+
+    if let Some(root) = &args.stitch {
+        let stitched = stitch_calls(&module, root.trim_start_matches('@'), args.inline_depth).unwrap_or_else(|err| {
+            eprintln!("error: {err}");
+            std::process::exit(2);
         });
+        note(&mut stats, &format!("--stitch: expanded {} call site(s), cut {} recursive call(s) at depth {}", stitched.expanded, stitched.recursion_cut, args.inline_depth))?;
+        dump_cfg(output, &stitched.function, &options);
+        return Ok(());
     }
 
-    Ok(())
-}
+    if let Some(other) = &args.diff_cfg {
+        let after = parse_ll_file(&mut open_input(other)?)?;
+        diff_cfgs(output, &args, (args.input(), &module), (other, &after), &options)?;
+        return Ok(());
+    }
 
-fn dump_cfg(output: &mut dyn Write, function: &Function, abbr: bool)  {
-    _ = writeln!(output, "```mermaid");
-    _ = writeln!(output, "flowchart TD");
-    _ = writeln!(output, "%% function {}", function.name);
-    function.blocks.iter().for_each(|block| {
-        let block_name = if block.name == "" { "%1" } else { &format!("%{}", &block.name) };
-        block.predecessors.iter().for_each(|src_name|
-            _ = writeln!(output, "\t{} -->|{}| {}", src_name, block_name, block_name)
-        );
-        if abbr == false {
-            let block_label = block.instructions.join("\n");
-            _ = writeln!(output, "{}[\"{}\"]", block_name, block_label);
+    let condensed: Vec<Function>;
+    if let Some(min_size) = args.min_block_size {
+        let mut total = Condensed::default();
+        condensed = functions.iter().map(|f| {
+            let (function, count) = condense_small_blocks(f, min_size, f.entry_index(args.entry_name.as_deref()));
+            total.merged += count.merged;
+            total.bypassed += count.bypassed;
+            function
+        }).collect();
+        functions = condensed.iter().collect();
+        note(&mut stats, &format!("--min-block-size {min_size}: merged {} block(s) into their predecessor, bypassed {}", total.merged, total.bypassed))?;
+    }
+    let trimmed: Vec<Function>;
+    if args.collapse_allocas {
+        let mut total = 0;
+        trimmed = functions.iter().map(|f| {
+            let (function, count) = collapse_allocas(f, f.entry_index(args.entry_name.as_deref()));
+            total += count;
+            function
+        }).collect();
+        functions = trimmed.iter().collect();
+        note(&mut stats, &format!("--collapse-allocas: collapsed {total} alloca(s)"))?;
+    }
+    let merged: Vec<Function>;
+    if args.merge_identical_blocks {
+        let mut lines = vec![];
+        let mut total = 0;
+        merged = functions.iter().map(|f| {
+            let (function, groups) = merge_identical_blocks(f, f.entry_index(args.entry_name.as_deref()));
+            for group in &groups {
+                lines.push(format!("--merge-identical-blocks: @{}: {} shown as {}", f.name, group[1..].join(", "), group[0]));
+                total += group.len() - 1;
+            }
+            function
+        }).collect();
+        functions = merged.iter().collect();
+        lines.push(format!("--merge-identical-blocks: merged {total} block(s)"));
+        for line in &lines {
+            note(&mut stats, line)?;
         }
-        let is_return = block.instructions.last().iter().any(|s| s.trim().starts_with("ret "));
-        let is_unreachable = block.instructions.last().iter().any(|s| s.trim().starts_with("unreachable"));
-        if is_return {
-            _ = writeln!(output, "style {block_name} stroke:#0f0");
+    }
+    let collapsed: Vec<Function>;
+    if let Some(depth) = args.collapse_by_region {
+        let mut total = 0;
+        collapsed = functions.iter().map(|f| {
+            let (function, count) = collapse_regions(f, depth, f.entry_index(args.entry_name.as_deref()));
+            total += count;
+            function
+        }).collect();
+        functions = collapsed.iter().collect();
+        note(&mut stats, &format!("--collapse-by-region {depth}: collapsed {total} region(s)"))?;
+    }
+    let chains: Vec<Function>;
+    if let Some(block) = &args.dom_path_to {
+        let name = block.strip_prefix('%').unwrap_or(block);
+        if !functions.iter().any(|f| f.block_index(name).is_some()) {
+            let candidates = functions.iter().flat_map(|f| f.blocks.iter().map(|b| b.name.as_ref()));
+            let hint = did_you_mean(name, candidates).map(|c| format!("; did you mean %{c}?")).unwrap_or_default();
+            eprintln!("error: no block %{name} in the selected functions{hint}");
+            std::process::exit(2);
         }
-        if is_unreachable {
-            _ = writeln!(output, "style {block_name} stroke:#f00");
+        chains = functions.iter().filter_map(|f| {
+            let chain = dominator_chain(f, f.entry_index(args.entry_name.as_deref()), f.block_index(name)?);
+            if chain.is_none() {
+                eprintln!("@{}: %{name} is unreachable from the entry, no dominator path exists", f.name);
+            }
+            chain
+        }).collect();
+        if chains.is_empty() {
+            std::process::exit(1);
         }
+        functions = chains.iter().collect();
+    }
+
+    let format = args.format().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
     });
-    _ = writeln!(output, "```").unwrap();
+    if let Some(path) = &args.split_output {
+        return write_split_output(output, &args, &source_name(&args, &module), &functions, &options, format, Path::new(path));
+    }
+    match format {
+        Format::Mermaid => {}
+        Format::Canvas => return write_canvases(output, &args, &functions, &options),
+        Format::Dot => return dump_dot(output, &functions, &options),
+        Format::Graphml => return dump_graphml(output, &functions),
+        Format::Svg | Format::Png => {
+            let mut source = vec![];
+            dump_dot(&mut source, &functions, &options)?;
+            let graphviz_format = if format == Format::Svg { "svg" } else { "png" };
+            return output.write_all(&render_with_graphviz(&source, graphviz_format)?);
+        }
+        Format::Json => {
+            let functions: Vec<serde_json::Value> = functions.iter()
+                .map(|f| cfg_json(f, f.entry_index(args.entry_name.as_deref())))
+                .collect();
+            return writeln!(output, "{}", serde_json::to_string_pretty(&json!({ "functions": functions })).unwrap());
+        }
+        Format::Jsonl => {
+            for function in &functions {
+                writeln!(output, "{}", cfg_json(function, function.entry_index(args.entry_name.as_deref())))?;
+            }
+            return Ok(());
+        }
+        Format::Csv => {
+            let metrics: Vec<(&str, FunctionMetrics)> = functions.iter()
+                .map(|f| (f.name.as_ref(), metrics_of(&args, f)))
+                .collect();
+            return write_metrics_csv(output, &metrics);
+        }
+    }
+
+    if let Some(block) = &args.around {
+        let name = block.strip_prefix('%').unwrap_or(block);
+        functions.retain(|f| f.block_index(name).is_some());
+        if functions.is_empty() {
+            let candidates = module.functions.iter()
+                .filter(|f| args.selects(f))
+                .flat_map(|f| f.blocks.iter().map(|b| b.name.as_ref()));
+            let hint = did_you_mean(name, candidates).map(|c| format!("; did you mean %{c}?")).unwrap_or_default();
+            eprintln!("error: no block %{name} in the selected functions{hint}");
+            std::process::exit(2);
+        }
+    }
+
+    // text written under each function heading, then before all diagrams
+    let mut notes = vec![String::new(); functions.len()];
+    let mut preamble = String::new();
+    if args.dedupe {
+        let groups = group_duplicates(&functions);
+        let occurrence = |f: &Function| format!("`{}` ({})", args.input.get(f.module).map(String::as_str).unwrap_or_default(), f.linkage());
+        preamble = format!("Deduplicated {} functions from {} input(s) into {} unique bodies (ratio {:.2}).\n\n",
+            functions.len(), args.input.len(), groups.len(), functions.len() as f64 / groups.len().max(1) as f64);
+        let conflicts = odr_conflicts(&functions, &groups);
+        if !conflicts.is_empty() {
+            preamble.push_str("## ODR conflicts\n\nThese names have different bodies in different inputs:\n\n");
+            for (name, conflicting) in &conflicts {
+                eprintln!("warning: @{name} has {} different bodies across the inputs", conflicting.len());
+                let bodies: Vec<String> = conflicting.iter().enumerate().map(|(n, &g)| {
+                    let places: Vec<String> = groups[g].members.iter().map(|&m| occurrence(functions[m])).collect();
+                    format!("body {}: {}", n + 1, places.join(", "))
+                }).collect();
+                preamble.push_str(&format!("- `@{name}`: {}\n", bodies.join("; ")));
+            }
+            preamble.push('\n');
+        }
+        notes = groups.iter().map(|g| {
+            let places: Vec<String> = g.members.iter().map(|&m| occurrence(functions[m])).collect();
+            format!("{} occurrence(s): {}\n\n", g.members.len(), places.join(", "))
+        }).collect();
+        functions = groups.iter().map(|g| functions[g.members[0]]).collect();
+    }
+
+    let template = match &args.output_template {
+        Some(file) => std::fs::read_to_string(file)?,
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+    let sections = functions.len() > 1 || args.dedupe;
+    let mut toc = vec![];
+    if sections && !args.no_toc {
+        // the transformations above may have changed the functions since `counted` was built
+        let stripped = counted_functions(&args, &functions);
+        write_toc(&mut toc, &stripped.iter().map(|f| &**f).collect::<Vec<_>>(), args.anchors)?;
+    }
+    let mut diagrams = preamble.into_bytes();
+    let mut large_nodes: Vec<(String, String, usize)> = vec![];
+    let mut rendering = Progress::new("rendering", Some(functions.len()), args.shows_progress());
+    let anchors = if args.anchors { function_anchors(&functions) } else { vec![String::new(); functions.len()] };
+    functions.iter().zip(&notes).zip(&anchors)
+        .for_each(|((f, note), anchor)| {
+            if !anchor.is_empty() {
+                _ = write!(diagrams, "<a id=\"{anchor}\"></a>\n\n");
+            }
+            if sections {
+                _ = write!(diagrams, "## {}\n\n{note}", function_heading(f));
+            }
+            if let Some(entry_name) = &args.entry_name {
+                check_entry_name(f, entry_name);
+            }
+            let label_sizes = dump_cfg(&mut diagrams, f, &options);
+            if args.include_ir {
+                _ = writeln!(diagrams);
+                _ = write_function_ir(&mut diagrams, f);
+            }
+            if sections {
+                _ = writeln!(diagrams);
+            }
+            if let Some(limit) = args.warn_on_large_node {
+                label_sizes.into_iter().filter(|(_, size)| *size > limit).for_each(|(block, size)| {
+                    large_nodes.push((f.name.to_string(), block, size));
+                });
+            }
+            rendering.inc();
+        });
+    rendering.finish();
+    if !unlocated_remarks.is_empty() {
+        _ = write!(diagrams, "## Remarks not placed on a block\n\n{}\n", unlocated_remarks.concat());
+    }
+    let mut metrics = vec![];
+    writeln!(metrics, "| function | blocks | edges | instructions | loops | complexity | max fan-in | max fan-out |")?;
+    writeln!(metrics, "|---|---:|---:|---:|---:|---:|---:|---:|")?;
+    for f in &functions {
+        let m = metrics_of(&args, f);
+        writeln!(metrics, "| {} | {} | {} | {} | {} | {} | {} | {} |",
+            f.name, m.blocks, m.edges, m.instructions, m.loops, m.complexity, m.max_fan_in, m.max_fan_out)?;
+    }
+    let title = source_name(&args, &module);
+    let values = [
+        ("title", title.as_str()),
+        ("toc", &String::from_utf8_lossy(&toc)),
+        ("diagrams", &String::from_utf8_lossy(&diagrams)),
+        ("metrics", &String::from_utf8_lossy(&metrics)),
+    ];
+    match fill_template(&template, &values) {
+        Ok(document) => output.write_all(document.as_bytes())?,
+        Err(err) => {
+            eprintln!("{}: {err}", args.output_template.as_deref().unwrap_or_default());
+            std::process::exit(2);
+        }
+    }
+
+    if let Some(limit) = args.warn_on_large_node {
+        for (func, block, size) in &large_nodes {
+            eprintln!("warning: block {block} in function {func} has a {size}-character label (limit {limit})");
+        }
+        if !large_nodes.is_empty() {
+            eprintln!("warning: {} oversized block(s); consider --abbr or --max-lines", large_nodes.len());
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Warns when the `--entry-name` block is missing from `function` or has predecessors.
+fn check_entry_name(function: &Function, entry_name: &str) {
+    match function.block_index(entry_name) {
+        None => eprintln!("warning: entry block {entry_name} not found in function {}, using the first block", function.name),
+        Some(index) if function.has_predecessors(index) =>
+            eprintln!("warning: entry block {entry_name} in function {} has predecessors", function.name),
+        Some(_) => {}
+    }
+}
+
+/// The `source_filename` of the module, or failing that the name of the first input file.
+fn source_name(args: &Args, module: &Module) -> String {
+    match &module.source_filename {
+        Some(name) => name.to_string(),
+        None => Path::new(args.input()).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+    }
+}
+
+/// The metrics of `function`, not counting its debug intrinsics with --ignore-debug.
+fn metrics_of(args: &Args, function: &Function) -> FunctionMetrics {
+    let entry = function.entry_index(args.entry_name.as_deref());
+    if args.ignore_debug {
+        function_metrics_with_trip_count(&without_debug_intrinsics(function), entry, args.assume_trip_count)
+    } else {
+        function_metrics_with_trip_count(function, entry, args.assume_trip_count)
+    }
 }
 
-fn parse_ll_file<R: Read>(reader: &mut io::BufReader<R>) -> io::Result<Vec<Function>>{
+/// `functions` as the counting modes see them: copies without their debug intrinsics with --ignore-debug,
+/// the functions themselves otherwise.
+fn counted_functions<'f, 'a>(args: &Args, functions: &[&'f Function<'a>]) -> Vec<Cow<'f, Function<'a>>> {
+    functions.iter()
+        .map(|&f| if args.ignore_debug { Cow::Owned(without_debug_intrinsics(f)) } else { Cow::Borrowed(f) })
+        .collect()
+}
 
-    let define_re = Regex::new(r"^define\s+.*@([a-zA-Z0-9_\.]+)\s*\(.*\)\s*(.*)\s*\{$").unwrap();
+/// Writes a note on what a transformation did to --stats-output, or else to stderr.
+fn note(stats: &mut Option<Box<dyn Write>>, text: &str) -> io::Result<()> {
+    match stats {
+        Some(stats) => writeln!(stats, "{text}"),
+        None => {
+            eprintln!("{text}");
+            Ok(())
+        }
+    }
+}
 
-    let mut functions: Vec<Function> = vec![];
+/// Runs the first of the analysis modes selected, writing its report to `output`. Returns whether one ran.
+fn print_analyses(output: &mut dyn Write, args: &Args, module: &Module, functions: &[&Function], counted: &[&Function]) -> io::Result<bool> {
+    if args.check_terminators || args.validate {
+        let check = if args.validate { verify } else { verify_terminators };
+        let mut count = 0;
+        for function in functions {
+            for violation in check(function) {
+                writeln!(output, "{}: {violation}", function.name)?;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            output.flush()?;
+            eprintln!("{count} violation(s)");
+            std::process::exit(1);
+        }
+        if !args.summary_only_on_error {
+            let blocks: usize = functions.iter().map(|f| f.blocks.len()).sum();
+            writeln!(output, "ok: {} functions, {blocks} blocks", functions.len())?;
+        }
+        return Ok(true);
+    }
+
+    if args.graph_stats {
+        print_graph_stats(output, functions)?;
+        return Ok(true);
+    }
+
+    if args.list_functions {
+        let graph = CallGraph::with_call_pattern(module, &args.call_pattern);
+        let recursive = graph.recursive();
+        for function in functions {
+            let metrics = metrics_of(args, function);
+            let flags: String = [
+                (metrics.has_cycle, 'L'),
+                (metrics.unreachable_blocks > 0, 'U'),
+                (graph.index(&function.name).is_some_and(|n| recursive[n]), 'R'),
+            ].iter().filter(|(set, _)| *set).map(|(_, flag)| *flag).collect();
+            writeln!(output, "{}\t{}\t{}\t{}", function.name, metrics.blocks, metrics.instructions, if flags.is_empty() { "-" } else { &flags })?;
+        }
+        return Ok(true);
+    }
+
+    if let Some(what) = &args.function_hash {
+        let content = if what == "full" { HashContent::Instructions } else { HashContent::Structure };
+        for function in functions {
+            writeln!(output, "{:016x}  {}", function_hash(function, content), function.name)?;
+        }
+        return Ok(true);
+    }
+
+    if args.find_duplicates {
+        let groups = identical_functions(counted);
+        for (number, members) in groups.iter().enumerate() {
+            let first = counted[members[0]];
+            let names: Vec<&str> = members.iter().map(|&m| counted[m].name.as_ref()).collect();
+            writeln!(output, "group {}: {} functions, {} blocks, {} instructions each: {}",
+                number + 1, members.len(), first.blocks.len(), instruction_count(first), names.join(", "))?;
+        }
+        let duplicates: usize = groups.iter().map(|g| g.len() - 1).sum();
+        writeln!(output, "{} group(s), {duplicates} function(s) foldable into another", groups.len())?;
+        return Ok(true);
+    }
+
+    if args.find_self_loops {
+        print_self_loops(output, counted)?;
+        return Ok(true);
+    }
+
+    if let Some(kinds) = &args.find_patterns {
+        print_patterns(output, args, functions, kinds)?;
+        return Ok(true);
+    }
+
+    if args.terminator_stats {
+        print_terminator_stats(output, functions)?;
+        return Ok(true);
+    }
+
+    if args.opcode_histogram {
+        if args.per_function {
+            for function in counted {
+                writeln!(output, "### function {}\n", function.name)?;
+                print_histogram(output, &opcode_histogram(&[function], args.opcode_filter.as_ref()))?;
+                writeln!(output)?;
+            }
+        } else {
+            print_histogram(output, &opcode_histogram(counted, args.opcode_filter.as_ref()))?;
+        }
+        return Ok(true);
+    }
 
-    let mut lines = reader.lines();
-    while let Some(line) = lines.next() {
-        let line = line?;
-        if let Some(caps) = define_re.captures(&line) {
-            if let Some(func_name) = caps.get(1).map(|m| m.as_str().to_string()) {
-                let blocks = parse_function(&mut lines);
-                let current_function = Function {
-                    name: func_name.clone(),
-                    define: line.clone(),
-                    blocks,
-                };
-                functions.push(current_function);
+    if args.unreachable || args.unreachable_json.is_some() {
+        let report = unreachable_report(args, module, functions);
+        if let Some(file) = &args.unreachable_json {
+            let json = serde_json::to_string_pretty(&report).expect("json values always serialize");
+            std::fs::write(file, json + "\n")?;
+        }
+        if args.unreachable {
+            for function in report["functions"].as_array().unwrap() {
+                for block in function["unreachable"].as_array().unwrap() {
+                    writeln!(output, "{}: {} ({})", function["name"].as_str().unwrap(),
+                        block["block"].as_str().unwrap(), block["reason"].as_str().unwrap())?;
+                }
             }
+            return Ok(true);
+        }
+    }
+
+    if let Some(file) = &args.metrics {
+        let metrics: Vec<(&str, FunctionMetrics)> = functions.iter()
+            .map(|f| (f.name.as_ref(), metrics_of(args, f)))
+            .collect();
+        let json = serde_json::to_string_pretty(&metrics_json(args.input(), &metrics)).unwrap();
+        std::fs::write(file, json + "\n")?;
+    }
+
+    if args.aggregate {
+        let mut totals = ModuleMetrics::default();
+        functions.iter().for_each(|f| totals.add(&metrics_of(args, f)));
+        print_aggregate(output, &totals)?;
+        if let Some(file) = &args.summary_json {
+            let json = json!({
+                "input": args.input(),
+                "source_filename": source_name(args, module),
+                "functions": totals.functions,
+                "blocks": totals.blocks,
+                "instructions": totals.instructions,
+                "edges": totals.edges,
+                "total_complexity": totals.total_complexity,
+                "max_complexity": totals.max_complexity,
+                "functions_with_loops": totals.functions_with_loops,
+                "functions_with_unreachable": totals.functions_with_unreachable,
+                "terminators": totals.terminators.iter().map(|(t, n)| (t.as_str(), *n)).collect::<BTreeMap<_, _>>(),
+            });
+            std::fs::write(file, serde_json::to_string_pretty(&json).unwrap() + "\n")?;
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn print_graph_stats(output: &mut dyn Write, functions: &[&Function]) -> io::Result<()> {
+    writeln!(output, "| function | blocks | edges | avg degree | max fan-in | max fan-out | sources | leaves |")?;
+    writeln!(output, "|---|---:|---:|---:|---|---|---:|---:|")?;
+    let degree = |max: Option<(String, usize)>| max.map(|(block, n)| format!("{block} ({n})")).unwrap_or_default();
+    for function in functions {
+        let stats = graph_stats(function);
+        writeln!(output, "| {} | {} | {} | {:.2} | {} | {} | {} | {} |",
+            function.name, stats.blocks, stats.edges, stats.average_degree,
+            degree(stats.max_fan_in), degree(stats.max_fan_out), stats.sources, stats.leaves)?;
+    }
+    Ok(())
+}
+
+/// Prints the single-block loops of each function, then their count.
+fn print_self_loops(output: &mut dyn Write, functions: &[&Function]) -> io::Result<()> {
+    writeln!(output, "| function | block | instructions | exits |")?;
+    writeln!(output, "|---|---|---:|---|")?;
+    let mut count = 0;
+    for function in functions {
+        let cfg = Cfg::new(function);
+        for block in self_loops(&cfg) {
+            let exits: Vec<String> = cfg.succs[block].iter().filter(|&&s| s != block).map(|&s| format!("%{}", function.blocks[s].name)).collect();
+            let instructions = function.blocks[block].instructions.iter().filter(|i| opcode(i).is_some()).count();
+            writeln!(output, "| {} | %{} | {instructions} | {} |", function.name, function.blocks[block].name,
+                if exits.is_empty() { "none".to_string() } else { exits.join(", ") })?;
+            count += 1;
         }
-        else {
-            // skip
+    }
+    writeln!(output, "\n{count} self-loop(s)")
+}
+
+/// Prints the diamonds, triangles and self-loops of each function, then their counts.
+fn print_patterns(output: &mut dyn Write, args: &Args, functions: &[&Function], kinds: &[String]) -> io::Result<()> {
+    let kinds: Vec<PatternKind> = PatternKind::ALL.into_iter()
+        .filter(|k| kinds.is_empty() || kinds.iter().any(|name| name == k.as_str()))
+        .collect();
+    let mut counts = vec![];
+    writeln!(output, "| function | pattern | header | blocks |")?;
+    writeln!(output, "|---|---|---|---|")?;
+    for function in functions {
+        let patterns = find_patterns(&Cfg::new(function), function.entry_index(args.entry_name.as_deref()));
+        let mut count = BTreeMap::new();
+        for pattern in patterns.iter().filter(|p| kinds.contains(&p.kind)) {
+            let blocks: Vec<String> = pattern.blocks.iter().map(|&b| format!("%{}", function.blocks[b].name)).collect();
+            writeln!(output, "| {} | {} | %{} | {} |", function.name, pattern.kind.as_str(), function.blocks[pattern.header].name, blocks.join(" → "))?;
+            *count.entry(pattern.kind).or_insert(0) += 1;
         }
+        counts.push((function.name.as_ref(), count));
     }
+    writeln!(output)?;
+    let header: Vec<&str> = kinds.iter().map(|k| k.as_str()).collect();
+    writeln!(output, "| function | {} |", header.join(" | "))?;
+    writeln!(output, "|---|{}", "---:|".repeat(kinds.len()))?;
+    for (name, count) in counts {
+        let cells: Vec<String> = kinds.iter().map(|k| count.get(k).copied().unwrap_or(0).to_string()).collect();
+        writeln!(output, "| {name} | {} |", cells.join(" | "))?;
+    }
+    Ok(())
+}
+
+/// Builds the unreachable-block report of the selected functions.
+fn unreachable_report(args: &Args, module: &Module, functions: &[&Function]) -> serde_json::Value {
+    let address_taken = address_taken_blocks(module);
+    let functions: Vec<serde_json::Value> = functions.iter().map(|f| {
+        let entry = f.entry_index(args.entry_name.as_deref());
+        let cfg = Cfg::new(f);
+        let blocks: Vec<serde_json::Value> = unreachable_blocks(f, entry, &address_taken).iter().map(|u| json!({
+            "block": format!("%{}", f.blocks[u.index].name),
+            "reason": u.reason.as_str(),
+            "predecessors": cfg.preds[u.index].iter().map(|&p| format!("%{}", f.blocks[p].name)).collect::<Vec<_>>(),
+        })).collect();
+        json!({ "name": f.name, "unreachable": blocks })
+    }).collect();
+    json!({ "input": args.input(), "functions": functions })
+}
+
+fn print_aggregate(output: &mut dyn Write, totals: &ModuleMetrics) -> io::Result<()> {
+    writeln!(output, "| metric | value |")?;
+    writeln!(output, "|---|---:|")?;
+    let rows = [
+        ("functions", totals.functions),
+        ("blocks", totals.blocks),
+        ("instructions", totals.instructions),
+        ("edges", totals.edges),
+        ("total complexity", totals.total_complexity),
+        ("max complexity", totals.max_complexity),
+        ("functions with loops", totals.functions_with_loops),
+        ("functions with unreachable code", totals.functions_with_unreachable),
+    ];
+    for (metric, value) in rows {
+        writeln!(output, "| {metric} | {value} |")?;
+    }
+    for (kind, count) in &totals.terminators {
+        writeln!(output, "| `{}` terminators | {count} |", kind.as_str())?;
+    }
+    Ok(())
+}
 
-    Ok(functions)
+/// Prints the blocks of each function by terminator kind, then the totals.
+fn print_terminator_stats(output: &mut dyn Write, functions: &[&Function]) -> io::Result<()> {
+    let counts: Vec<_> = functions.iter().map(|f| terminator_counts(f)).collect();
+    let kinds: Vec<Terminator> = Terminator::ALL.into_iter().filter(|k| counts.iter().any(|c| c.contains_key(k))).collect();
+    let header: Vec<&str> = kinds.iter().map(|k| k.as_str()).collect();
+    writeln!(output, "| function | {} |", header.join(" | "))?;
+    writeln!(output, "|---|{}", "---:|".repeat(kinds.len()))?;
+    let cells = |count: &dyn Fn(Terminator) -> usize| kinds.iter().map(|&k| count(k).to_string()).collect::<Vec<_>>().join(" | ");
+    for (function, count) in functions.iter().zip(&counts) {
+        writeln!(output, "| {} | {} |", function.name, cells(&|k| count.get(&k).copied().unwrap_or(0)))?;
+    }
+    writeln!(output, "| **total** | {} |", cells(&|k| counts.iter().filter_map(|c| c.get(&k)).sum()))
 }
 
-fn parse_function<R: Read>(lines: &mut io::Lines<&mut BufReader<R>>) -> Vec<BasicBlock> {
-    let block_name_re = Regex::new(r"^([0-9a-zA-Z_\.]+):(\s*;\s*preds\s*=\s*(.*))?$").unwrap();
-    let jump_re = Regex::new(r"^\s*br\s+(.*)").unwrap();
+fn print_histogram(output: &mut dyn Write, histogram: &BTreeMap<String, usize>) -> io::Result<()> {
+    let mut counts: Vec<(&String, &usize)> = histogram.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    writeln!(output, "| opcode | count |")?;
+    writeln!(output, "|---|---:|")?;
+    for (op, count) in counts {
+        writeln!(output, "| {op} | {count} |")?;
+    }
+    Ok(())
+}
 
-    let mut blocks: Vec<BasicBlock> = vec![];
-    let mut current_block: Option<BasicBlock> = None;
+/// Renders the CFG differences of every selected function between `before` and `after`,
+/// each given with the label used in headings. Returns whether any selected function differs.
+fn diff_cfgs(output: &mut dyn Write, args: &Args, (before_label, before): (&str, &Module), (after_label, after): (&str, &Module),
+             options: &RenderOptions) -> io::Result<bool> {
+    let selected = |f: &&Function| args.selects(f);
+    let mut differs = false;
 
-    while let Some(line) = lines.next() {
-        let line = line.unwrap();
+    for old in before.functions.iter().filter(selected) {
+        let Some(new) = after.function(&old.name) else {
+            writeln!(output, "## function {}\n\n- removed in {after_label}\n", old.name)?;
+            differs = true;
+            continue;
+        };
+        let diff = diff_functions(old, new);
+        differs |= !diff.is_empty();
+        writeln!(output, "## function {}\n", old.name)?;
+        if args.side_by_side {
+            writeln!(output, "### before: {before_label}\n")?;
+            dump_cfg(output, old, &RenderOptions { id_prefix: "before_".to_string(), ..options.clone() });
+            writeln!(output, "\n### after: {after_label}\n")?;
+            dump_cfg(output, new, &RenderOptions { id_prefix: "after_".to_string(), ..options.clone() });
+        } else {
+            dump_cfg_diff(output, old, new, &diff, options)?;
+        }
+        writeln!(output)?;
+        diff.write_summary(output)?;
+        writeln!(output)?;
+    }
+    for new in after.functions.iter().filter(selected).filter(|f| before.function(&f.name).is_none()) {
+        writeln!(output, "## function {}\n\n- added in {after_label}\n", new.name)?;
+        differs = true;
+    }
+    Ok(differs)
+}
 
-        if let Some(caps) = block_name_re.captures(&line) { // name: ; preds = a, b, c
-            if let Some(block_name) = caps.get(1).map(|m| m.as_str().to_string()) {
-                if let Some(block) = current_block {
-                    blocks.push(block.clone());
+/// Renders the CFG of the --function before and after each pass of a `-print-after-all` log that changed it.
+fn diff_passes(output: &mut dyn Write, args: &Args) -> io::Result<()> {
+    let [name] = &args.function[..] else {
+        eprintln!("--diff-passes follows a single function, got {} --function flags", args.function.len());
+        std::process::exit(2);
+    };
+    let name = name.strip_prefix('@').unwrap_or(name);
+    let changes = function_changes(open_input(args.input())?, name)?;
+    let options = args.render_options();
+    writeln!(output, "# @{name} through {}\n", args.input())?;
+    writeln!(output, "{} pass(es) changed @{name}.\n", changes.len())?;
+    for (number, change) in changes.iter().enumerate() {
+        let (before, after) = (parse_ll_str(&change.before), parse_ll_str(&change.after));
+        let (Some(old), Some(new)) = (before.functions.first(), after.functions.first()) else { continue };
+        let target = if change.header.target.is_empty() { String::new() } else { format!(" on {}", change.header.target) };
+        writeln!(output, "## {}. {}{target} (dump {})\n", number + 1, change.header.pass, change.index)?;
+        diff_functions(old, new).write_summary(output)?;
+        writeln!(output, "\n### before\n")?;
+        dump_cfg(output, old, &RenderOptions { id_prefix: "before_".to_string(), ..options.clone() });
+        writeln!(output, "\n### after\n")?;
+        dump_cfg(output, new, &RenderOptions { id_prefix: "after_".to_string(), ..options.clone() });
+        writeln!(output)?;
+    }
+    Ok(())
+}
+
+/// Prints the regressions between two metrics documents, exiting with status 1 when there are any.
+fn metrics_compare(output: &mut dyn Write, args: &Args, old_file: &str, new_file: &str) -> io::Result<()> {
+    let read = |file: &str| -> io::Result<serde_json::Value> {
+        serde_json::from_str(&std::fs::read_to_string(file)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{file}: {e}")))
+    };
+    let thresholds = Thresholds { complexity: args.complexity_threshold, size_percent: args.size_threshold };
+    let regressions = compare_metrics(&read(old_file)?, &read(new_file)?, thresholds)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if regressions.is_empty() {
+        if !args.summary_only_on_error {
+            writeln!(output, "no regressions")?;
+        }
+        return Ok(());
+    }
+    writeln!(output, "| function | metric | old | new |")?;
+    writeln!(output, "|---|---|---:|---:|")?;
+    for r in &regressions {
+        writeln!(output, "| {} | {} | {} | {} |", r.function, r.metric, r.old, r.new)?;
+    }
+    output.flush()?;
+    std::process::exit(1);
+}
+
+/// Prints the per-function metric changes from the IR file `old_file` to `new_file`.
+fn metrics_delta(output: &mut dyn Write, args: &Args, old_file: &str, new_file: &str) -> io::Result<()> {
+    let (old, new) = (parse_ll_file(&mut open_input(old_file)?)?, parse_ll_file(&mut open_input(new_file)?)?);
+    let old_metrics: Vec<(&str, FunctionMetrics)> = old.functions.iter().filter(|f| args.selects(f)).map(|f| (f.name.as_ref(), metrics_of(args, f))).collect();
+    let new_metrics: Vec<(&str, FunctionMetrics)> = new.functions.iter().filter(|f| args.selects(f)).map(|f| (f.name.as_ref(), metrics_of(args, f))).collect();
+    let deltas = metrics_deltas(&old_metrics, &new_metrics);
+
+    writeln!(output, "| function | status | blocks | instructions | complexity | edges |")?;
+    writeln!(output, "|---|---|---:|---:|---:|---:|")?;
+    for delta in &deltas {
+        let cells: Vec<String> = DELTA_METRICS.iter().map(|(_, metric)| {
+            let value = |m: &Option<FunctionMetrics>| m.as_ref().map_or("—".to_string(), |m| metric(m).to_string());
+            format!("{} → {} ({:+})", value(&delta.old), value(&delta.new), delta.delta(metric))
+        }).collect();
+        writeln!(output, "| {} | {} | {} |", delta.function, delta.status(), cells.join(" | "))?;
+    }
+    if let Some(file) = &args.summary_json {
+        let json = deltas_json(old_file, new_file, &deltas);
+        std::fs::write(file, serde_json::to_string_pretty(&json).unwrap() + "\n")?;
+    }
+    Ok(())
+}
+
+/// Parses every input, printing the first parse problem of each file and a summary line.
+/// With --strict, a problem makes the exit status 1.
+fn parse_only(output: &mut dyn Write, args: &Args) -> io::Result<()> {
+    let (mut functions, mut blocks, mut problems) = (0, 0, 0);
+    for input in &args.input {
+        let mut text = String::new();
+        open_input(input)?.read_to_string(&mut text)?;
+        let module = match args.input_format(input) {
+            InputFormat::Ll => {
+                let found = parse_problems(&text);
+                if let Some(first) = found.first() {
+                    let more = if found.len() > 1 { format!(" (and {} more)", found.len() - 1) } else { String::new() };
+                    let severity = if args.strict { "error" } else { "warning" };
+                    eprintln!("{severity}: {input}:{}: {}{more}", first.line, first.message);
                 }
+                problems += found.len();
+                parse_ll_str(&text).functions.iter().map(|f| f.blocks.len()).collect::<Vec<_>>()
+            }
+            InputFormat::Dot => parse_dot_cfg(&text).functions.iter().map(|f| f.blocks.len()).collect(),
+            InputFormat::Objdump => parse_objdump(&text).functions.iter().map(|f| f.blocks.len()).collect(),
+        };
+        functions += module.len();
+        blocks += module.iter().sum::<usize>();
+    }
+    if args.strict && problems > 0 {
+        output.flush()?;
+        std::process::exit(1);
+    }
+    writeln!(output, "parsed {functions} functions, {blocks} blocks")
+}
 
-                let predecessors = caps.get(3).map(|m| m.as_str().to_string())
-                    .map(|s| s.split(", ").map(|s| s.to_string()).collect::<Vec<String>>() )
-                    .unwrap_or(vec![]);
+/// Writes one .canvas file per function, named after --output or else the input file.
+fn write_canvases(output: &mut dyn Write, args: &Args, functions: &[&Function], options: &RenderOptions) -> io::Result<()> {
+    let base = Path::new(args.output.as_deref().unwrap_or(args.input())).with_extension("");
+    for function in functions {
+        let path = if functions.len() == 1 {
+            base.with_extension("canvas")
+        } else {
+            base.with_file_name(format!("{}_{}.canvas", base.file_name().unwrap_or_default().to_string_lossy(), function.name))
+        };
+        let canvas = canvas_json(function, options);
+        std::fs::write(&path, serde_json::to_string_pretty(&canvas)? + "\n")?;
+        writeln!(output, "{}", path.display())?;
+    }
+    Ok(())
+}
 
-                current_block = Some(BasicBlock {
-                    name: block_name.clone(),
-                    instructions: vec![],
-                    predecessors,
-                    successors: vec![],
-                });
+/// Writes the selected functions of the IR inputs as JSON lines while parsing them, holding one function at a time.
+fn stream_json_lines(output: &mut dyn Write, args: &Args) -> io::Result<()> {
+    let mut progress = Progress::new("streaming", None, args.shows_progress());
+    for input in &args.input {
+        for function in stream_ll_file(open_input(input)?) {
+            let function = function?;
+            if args.selects(&function) {
+                writeln!(output, "{}", cfg_json(&function, function.entry_index(args.entry_name.as_deref())))?;
             }
+            progress.inc();
         }
-        else if line == "}" { // end of function
-            break;
+    }
+    progress.finish();
+    Ok(())
+}
+
+/// Writes one document per function plus an index.md to a directory or zip archive, then prints its path.
+fn write_split_output(output: &mut dyn Write, args: &Args, title: &str, functions: &[&Function], options: &RenderOptions, format: Format, path: &Path) -> io::Result<()> {
+    let extension = match format {
+        Format::Mermaid => "md",
+        Format::Dot => "dot",
+        Format::Graphml => "graphml",
+        Format::Json => "json",
+        _ => {
+            eprintln!("--split-output works with the mermaid, dot, graphml and json formats, not {format:?}");
+            std::process::exit(2);
         }
-        else { // instruction inside block
-            if current_block.is_none() {
-                current_block = Some(BasicBlock {
-                    name: "%1".to_string(),
-                    instructions: vec![],
-                    predecessors: vec![],
-                    successors: vec![],
-                });
-            }
-            let current_block: &mut BasicBlock = current_block.as_mut().unwrap();
-            if line.trim()  != "" {
-                current_block.instructions.push(line.clone());
+    };
+    let names: Vec<&str> = functions.iter().map(|f| f.name.as_ref()).collect();
+    let files = if args.split_layout == "nested" { nested_file_names(&names, extension) } else { split_file_names(&names, extension) };
+    let mut split = SplitOutput::create(path)?;
+    let mut index = format!("# {title}\n\n| function | blocks | file |\n|---|---:|---|\n");
+    for (function, file) in functions.iter().zip(&files) {
+        let mut document = vec![];
+        match format {
+            Format::Dot => dump_dot(&mut document, &[function], options)?,
+            Format::Graphml => dump_graphml(&mut document, &[function])?,
+            Format::Json => {
+                let cfg = cfg_json(function, function.entry_index(args.entry_name.as_deref()));
+                writeln!(document, "{}", serde_json::to_string_pretty(&cfg)?)?;
             }
-            if let Some(caps) = jump_re.captures(&line) {
-                if let Some(jump_content) = caps.get(1).map(|m| m.as_str().to_string()) {
-                    jump_content.split(',').filter(|s| s.contains("label ")).for_each(|s| {
-                        let jump_to = s.split_whitespace().last().unwrap().to_string();
-                        current_block.successors.push(jump_to);
-                    });
+            _ => {
+                writeln!(document, "# {}\n", function_heading(function))?;
+                dump_cfg(&mut document, function, options);
+                if args.include_ir {
+                    writeln!(document)?;
+                    write_function_ir(&mut document, function)?;
                 }
             }
         }
+        split.write(file, &document)?;
+        index.push_str(&format!("| {} | {} | [{file}]({file}) |\n", function.name, function.blocks.len()));
     }
+    split.write(INDEX, index.as_bytes())?;
+    split.finish()?;
+    writeln!(output, "{}", path.display())
+}
 
-    if let Some(block) = current_block {
-        blocks.push(block.clone());
+/// Runs `git` in the --git-repo directory and returns its standard output.
+fn git(args: &Args, git_args: &[&str]) -> io::Result<String> {
+    let result = std::process::Command::new("git").arg("-C").arg(&args.git_repo).args(git_args).output()?;
+    if !result.status.success() {
+        let message = String::from_utf8_lossy(&result.stderr);
+        return Err(io::Error::other(format!("git {}: {}", git_args.join(" "), message.trim())));
     }
+    String::from_utf8(result.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Diffs the CFGs of an IR file at two revisions given as REV:PATH, exiting with status 1 when they differ.
+fn git_compare(output: &mut dyn Write, args: &Args, before_spec: &str, after_spec: &str) -> io::Result<()> {
+    let label = |spec: &str| -> io::Result<String> {
+        let (rev, path) = spec.split_once(':')
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("expected REV:PATH, got {spec}")))?;
+        let id = git(args, &["rev-parse", "--short", &format!("{rev}^{{commit}}")])?;
+        Ok(format!("{rev} ({}) {path}", id.trim()))
+    };
+    let (before_label, after_label) = (label(before_spec)?, label(after_spec)?);
+    let before_text = git(args, &["show", before_spec])?;
+    let after_text = git(args, &["show", after_spec])?;
 
-    blocks
+    // with --summary-only-on-error the diff is only shown when there is one
+    let mut buffer = vec![];
+    let report: &mut dyn Write = if args.summary_only_on_error { &mut buffer } else { output };
+    writeln!(report, "# CFG diff: {before_label} → {after_label}\n")?;
+    let differs = diff_cfgs(report, args, (&before_label, &parse_ll_str(&before_text)), (&after_label, &parse_ll_str(&after_text)), &args.render_options())?;
+    if differs {
+        output.write_all(&buffer)?;
+    }
+    output.flush()?;
+    if differs {
+        std::process::exit(1);
+    }
+    Ok(())
 }
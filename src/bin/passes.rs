@@ -1,30 +1,335 @@
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufRead, BufWriter, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use clap::Parser;
+use serde_json::json;
+use learning_llvm_tools::input::{open_input, uncompressed_name};
+use learning_llvm_tools::manifest::update_manifest;
+use learning_llvm_tools::pass_dump::{clean_outputs, continue_split, index_path, split_dumps, split_manifest_entries, write_index, DumpInfo, Splitter};
+use learning_llvm_tools::pass_report::write_html_report;
+
+#[derive(Parser, Debug)]
+#[command(about, version, author)]
+struct Args {
+    /// The -print-after-all output to split, like abc.ll, or abc.ll.gz / abc.ll.xz; `-` reads standard input.
+    input: String,
+
+    /// Accept an input whose name does not end in .ll, like a .log or .txt capture of the pass output.
+    #[arg(long)]
+    force_ll: bool,
+
+    /// The base name of the split files and the index. Defaults to the input file name without .ll,
+    /// or `passes` when reading standard input or the name has no usable stem.
+    #[arg(long)]
+    name: Option<String>,
+
+    /// The directory the split files are written to.
+    #[arg(long, default_value = "./output")]
+    output_dir: PathBuf,
+
+    /// Remove files left by a previous split of the same input before writing.
+    #[arg(long)]
+    clean: bool,
+
+    /// Keep running and re-split whenever the input file changes: only from its last dump on while
+    /// the input grows, all of it when what came before changed.
+    #[arg(long)]
+    watch: bool,
+
+    /// Keep running and split the dumps appended to the input as they arrive, like `tail -f`, without
+    /// re-reading it. A dump is written to the index once the next marker shows it is complete.
+    #[arg(long, conflicts_with = "watch")]
+    follow: bool,
+
+    /// Warn about dumps with a line longer than BYTES. Such lines are written through to the dump file in
+    /// pieces rather than held in memory, except with --extract-function.
+    #[arg(long, value_name = "BYTES", default_value_t = 16 << 20)]
+    long_line_warning: usize,
+
+    /// Only keep this function in each dump (plus the module header lines), skipping dumps without it.
+    /// Mostly useful for -print-module-scope logs, where every dump holds the whole module.
+    #[arg(long, value_name = "FUNCTION")]
+    extract_function: Option<String>,
+
+    /// Keep at most N dump files on disk, deleting the oldest ones as new dumps are written.
+    /// The index still lists every dump.
+    #[arg(long, value_name = "N")]
+    ring: Option<usize>,
+
+    /// With --ring, never delete the first dump so the baseline is preserved.
+    #[arg(long, requires = "ring")]
+    keep_first: bool,
+
+    /// Also write a self-contained HTML report with the diff of every dump against the previous one.
+    #[arg(long, value_name = "FILE")]
+    report_html: Option<PathBuf>,
+
+    /// Write a JSON manifest of the generated files (path, input, function, format, hash, size) to FILE
+    /// once the split is complete.
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// With --manifest, delete the files listed by the previous manifest that this run did not write.
+    #[arg(long, requires = "manifest")]
+    prune_stale: bool,
+}
 
 fn main() -> io::Result<()> {
-    // args[1] is the input file like abc.ll
-    let input_file = std::env::args().nth(1).expect("no filename given");
-    if !input_file.ends_with(".ll") {
-        panic!("input file must end with .ll");
+    let args = Args::parse();
+    let stdin = args.input == "-";
+    if !stdin && !args.force_ll && !uncompressed_name(&args.input).ends_with(".ll") {
+        eprintln!("input file must end with .ll (optionally followed by .gz or .xz); pass --force-ll to split it anyway");
+        std::process::exit(2);
+    }
+    if stdin && args.watch {
+        eprintln!("--watch needs an input file, standard input cannot be re-read");
+        std::process::exit(2);
+    }
+    if args.follow && (stdin || uncompressed_name(&args.input) != args.input) {
+        eprintln!("--follow needs an uncompressed input file");
+        std::process::exit(2);
     }
 
-    let path = std::path::Path::new(&input_file);
-    let basename = path.file_stem().expect("no basename found").to_str().expect("basename is not a valid UTF-8 string");
+    let stem = Path::new(uncompressed_name(&args.input)).file_stem().and_then(|s| s.to_str()).filter(|s| !stdin && !s.is_empty());
+    let basename = args.name.as_deref().or(stem).unwrap_or("passes");
 
-    let file = File::open(input_file.as_str())?;
-    let reader = BufReader::new(file);
+    if args.clean || args.watch || args.follow {
+        clean_outputs(&args.output_dir, basename)?;
+    }
+    if args.follow {
+        return follow(&args, basename);
+    }
+    let dumps = split(&args, basename)?;
+    if args.watch {
+        println!("{} dumps, {} changed passes", dumps.len() - 1, dumps.iter().filter(|d| d.changed).count());
+        watch(&args, basename, dumps)?;
+    }
 
-    let mut file_count = 0;
-    let mut output_file = File::create(format!("./output/{basename}_{file_count}.ll"))?;
+    Ok(())
+}
 
-    for line in reader.lines() {
-        let line = line?;
-        if line.contains(" Dump After ") {
-            file_count += 1;
-            output_file = File::create(format!("./output/{basename}_{file_count}.ll"))?;
-        }
-        writeln!(output_file, "{}", line)?;
+fn split(args: &Args, basename: &str) -> io::Result<Vec<DumpInfo>> {
+    let input: Box<dyn BufRead> = if args.input == "-" { Box::new(io::stdin().lock()) } else { open_input(&args.input)? };
+    let dumps = split_dumps(input, &args.output_dir, basename, |splitter| splitter
+        .extract_function(args.extract_function.clone())
+        .ring(args.ring, args.keep_first))?;
+    report_split(args, basename, &dumps)?;
+    Ok(dumps)
+}
+
+/// Splits the input from the start of its dump `done.len()` on, keeping the files of the dumps `done`.
+fn split_from(args: &Args, basename: &str, done: Vec<DumpInfo>, offset: u64) -> io::Result<Vec<DumpInfo>> {
+    let mut input = open_input(&args.input)?;
+    io::copy(&mut input.by_ref().take(offset), &mut io::sink())?;
+    let splitter = Splitter::resume(&args.output_dir, basename, done, args.extract_function.clone()).ring(args.ring, args.keep_first);
+    let dumps = continue_split(input, splitter, offset)?;
+    report_split(args, basename, &dumps)?;
+    Ok(dumps)
+}
+
+/// Notes and warnings about a split, then the index and the other outputs.
+fn report_split(args: &Args, basename: &str, dumps: &[DumpInfo]) -> io::Result<()> {
+    let module_scope = dumps.iter().filter(|d| d.module_scope).count();
+    if args.extract_function.is_none() && module_scope > 1 && module_scope * 2 > dumps.len() {
+        eprintln!("note: {module_scope} dumps contain the whole module (-print-module-scope); --extract-function can trim them");
+    }
+    warn_long_lines(args, dumps);
+    write_outputs(args, basename, dumps)
+}
+
+fn warn_long_lines(args: &Args, dumps: &[DumpInfo]) {
+    for dump in dumps.iter().filter(|d| d.longest_line > args.long_line_warning) {
+        eprintln!("warning: {} has a line of {} bytes", dump.file.display(), dump.longest_line);
     }
+}
 
+/// Writes the index, and the report and manifest when asked for, describing `dumps`.
+fn write_outputs(args: &Args, basename: &str, dumps: &[DumpInfo]) -> io::Result<()> {
+    write_index(&mut File::create(index_path(&args.output_dir, basename))?, dumps)?;
+    if let Some(report) = &args.report_html {
+        write_html_report(&mut BufWriter::new(File::create(report)?), &args.input, dumps)?;
+    }
+    if let Some(manifest) = &args.manifest {
+        write_split_manifest(args, basename, manifest, dumps)?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Records the dump files still on disk, the index and the report in the manifest.
+fn write_split_manifest(args: &Args, basename: &str, manifest: &Path, dumps: &[DumpInfo]) -> io::Result<()> {
+    let entries = split_manifest_entries(&args.input, dumps, args.extract_function.as_deref(),
+        &index_path(&args.output_dir, basename), args.report_html.as_deref());
+    let options = json!({
+        "output_dir": args.output_dir,
+        "name": args.name,
+        "clean": args.clean,
+        "watch": args.watch,
+        "follow": args.follow,
+        "extract_function": args.extract_function,
+        "ring": args.ring,
+        "keep_first": args.keep_first,
+        "report_html": args.report_html,
+    });
+    for path in update_manifest(manifest, &entries, options, args.prune_stale)? {
+        eprintln!("pruned stale {}", path.display());
+    }
+    Ok(())
+}
+
+fn file_stamp(path: &str) -> Option<(u64, SystemTime)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// The hash of the first `length` bytes of the input, `None` when it is shorter.
+fn prefix_hash(path: &str, length: u64) -> io::Result<Option<u64>> {
+    let mut input = open_input(path)?.take(length);
+    let (mut hasher, mut read) = (DefaultHasher::new(), 0);
+    loop {
+        let buffer = input.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        hasher.write(buffer);
+        let used = buffer.len();
+        input.consume(used);
+        read += used as u64;
+    }
+    Ok((read == length).then(|| hasher.finish()))
+}
+
+/// Where the next split of the watched input can start: the offset of its last dump, which may yet
+/// grow, with the hash of the input before it. `None` when there is no dump.
+fn resume_point(args: &Args, dumps: &[DumpInfo]) -> io::Result<Option<(u64, u64)>> {
+    let Some(last) = dumps.last().filter(|d| d.header.is_some()) else { return Ok(None) };
+    Ok(prefix_hash(&args.input, last.offset)?.map(|hash| (last.offset, hash)))
+}
+
+/// Splits the watched input again: only from its last dump on when what precedes it is unchanged,
+/// as when `opt` is still appending, else all of it, as when the input was truncated and rewritten.
+fn resplit(args: &Args, basename: &str, dumps: &[DumpInfo], resume: Option<(u64, u64)>) -> io::Result<Vec<DumpInfo>> {
+    if let Some((offset, hash)) = resume {
+        if prefix_hash(&args.input, offset)? == Some(hash) {
+            return split_from(args, basename, dumps[..dumps.len() - 1].to_vec(), offset);
+        }
+    }
+    clean_outputs(&args.output_dir, basename)?;
+    split(args, basename)
+}
+
+/// Polls the input and splits it again once it has stopped changing for a short while. A failed
+/// split is reported and the watch goes on.
+fn watch(args: &Args, basename: &str, mut dumps: Vec<DumpInfo>) -> io::Result<()> {
+    const POLL: Duration = Duration::from_millis(250);
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    let mut processed = file_stamp(&args.input);
+    let mut resume = resume_point(args, &dumps).unwrap_or_default();
+    loop {
+        std::thread::sleep(POLL);
+        let stamp = file_stamp(&args.input);
+        if stamp.is_none() || stamp == processed {
+            continue; // missing (being rewritten) or unchanged
+        }
+
+        // wait for the writer to settle
+        let mut settled = stamp;
+        loop {
+            std::thread::sleep(DEBOUNCE);
+            let now = file_stamp(&args.input);
+            if now == settled {
+                break;
+            }
+            settled = now;
+        }
+        if settled.is_none() {
+            continue;
+        }
+        processed = settled;
+
+        let new_dumps = match resplit(args, basename, &dumps, resume) {
+            Ok(new_dumps) => new_dumps,
+            Err(err) => {
+                eprintln!("failed to split {}: {err}", args.input);
+                // the outputs may be half written, start over at the next change
+                (dumps, resume) = (vec![], None);
+                continue;
+            }
+        };
+        resume = resume_point(args, &new_dumps).unwrap_or_else(|err| {
+            eprintln!("failed to read {}: {err}", args.input);
+            None
+        });
+        let new_changed = new_dumps.iter()
+            .filter(|d| d.changed)
+            .filter(|d| dumps.get(d.index).is_none_or(|old| old.hash != d.hash || old.header != d.header))
+            .count();
+        println!("now {} dumps, {} new changed passes", new_dumps.len().saturating_sub(1), new_changed);
+        dumps = new_dumps;
+    }
+}
+
+/// Reads what was appended to the input since `offset` and feeds its complete lines to the splitter,
+/// keeping a trailing partial line in `partial`. Returns the new offset.
+fn read_appended(path: &str, offset: u64, partial: &mut Vec<u8>, splitter: &mut Splitter) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let read = file.read_to_end(partial)? as u64;
+    let Some(end) = partial.iter().rposition(|&b| b == b'\n') else { return Ok(offset + read) };
+    let complete: Vec<u8> = partial.drain(..=end).collect();
+    for line in String::from_utf8_lossy(&complete).lines() {
+        splitter.push_line(line)?;
+    }
+    Ok(offset + read)
+}
+
+/// Splits the input as it grows, reading only the appended bytes, and rewrites the index whenever
+/// dumps are completed. Starts over when the input shrinks, as when `opt` is rerun.
+fn follow(args: &Args, basename: &str) -> io::Result<()> {
+    const POLL: Duration = Duration::from_millis(250);
+
+    let new_splitter = || Splitter::new(&args.output_dir, basename)
+        .map(|splitter| splitter.extract_function(args.extract_function.clone()).ring(args.ring, args.keep_first));
+    let mut splitter = new_splitter()?;
+    let (mut offset, mut partial, mut listed) = (0, vec![], 0);
+    loop {
+        let Ok(len) = fs::metadata(&args.input).map(|m| m.len()) else {
+            std::thread::sleep(POLL);
+            continue; // not created yet, or being rewritten
+        };
+        if len < offset {
+            println!("{} was truncated, starting over", args.input);
+            clean_outputs(&args.output_dir, basename)?;
+            splitter = new_splitter()?;
+            (offset, listed) = (0, 0);
+            partial.clear();
+        }
+        if len == offset {
+            std::thread::sleep(POLL);
+            continue;
+        }
+        offset = match read_appended(&args.input, offset, &mut partial, &mut splitter) {
+            Ok(offset) => offset,
+            Err(err) => {
+                eprintln!("failed to read {}: {err}", args.input);
+                std::thread::sleep(POLL);
+                continue;
+            }
+        };
+
+        let dumps = splitter.dumps();
+        if dumps.len() > listed {
+            for dump in &dumps[listed..] {
+                let Some(header) = &dump.header else { continue };
+                println!("dump {}: {} on {} ({} lines{})", dump.index, header.pass, header.target, dump.lines,
+                    if dump.changed { ", changed" } else { "" });
+            }
+            warn_long_lines(args, &dumps[listed..]);
+            listed = dumps.len();
+            write_outputs(args, basename, dumps)?;
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Call graph of the functions defined in a module.
+
+use std::io::{self, Write};
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::ir::{Function, Module};
+use crate::render::function_color;
+
+/// How callees are found by default: `call` (also `tail`, `musttail` and `notail call`), `invoke`
+/// and `callbr` of `@name(`, or of `bitcast (... @name to ...)` as older typed-pointer IR writes
+/// calls through a mismatched function type.
+pub const DEFAULT_CALL_PATTERN: &str =
+    r"\b(?:call|invoke|callbr)\s(?:[^@(]*?\bbitcast\s*\([^@]*@([-\w.$]+)\s+to\b|[^@]*@([-\w.$]+)\s*\()";
+
+/// The callee of `inst` matched by `call_re`: the first capture group that took part in the match.
+pub fn call_target<'t>(call_re: &Regex, inst: &'t str) -> Option<&'t str> {
+    call_re.captures(inst)?.iter().skip(1).flatten().next().map(|m| m.as_str())
+}
+
+/// The functions called by `function`, in order of first appearance, found with `call_re`.
+pub fn callees(function: &Function, call_re: &Regex) -> Vec<String> {
+    let mut callees: Vec<String> = vec![];
+    for inst in function.blocks.iter().flat_map(|b| b.instructions.iter()) {
+        if let Some(callee) = call_target(call_re, inst) {
+            if !callees.iter().any(|c| c == callee) {
+                callees.push(callee.to_string());
+            }
+        }
+    }
+    callees
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    /// Defined functions first, in module order, then the external callees.
+    pub nodes: Vec<String>,
+    /// Whether the node at the same index is defined in the module.
+    pub defined: Vec<bool>,
+    /// Caller to callee, by node index.
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> CallGraph {
+        CallGraph::with_call_pattern(module, &Regex::new(DEFAULT_CALL_PATTERN).unwrap())
+    }
+
+    /// The call graph with the callees found by `call_re`, see [`call_target`].
+    pub fn with_call_pattern(module: &Module, call_re: &Regex) -> CallGraph {
+        let mut graph = CallGraph::default();
+        for function in &module.functions {
+            graph.node(&function.name, true);
+        }
+        for function in &module.functions {
+            let caller = graph.node(&function.name, true);
+            for callee in callees(function, call_re) {
+                let callee = graph.node(&callee, false);
+                graph.edges.push((caller, callee));
+            }
+        }
+        graph
+    }
+
+    fn node(&mut self, name: &str, defined: bool) -> usize {
+        match self.nodes.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(name.to_string());
+                self.defined.push(defined);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n == name)
+    }
+
+    pub fn callees_of(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges.iter().filter(move |(from, _)| *from == node).map(|&(_, to)| to)
+    }
+
+    /// Whether each node can call itself, directly or through other functions: its strongly
+    /// connected component (Kosaraju) has several nodes or a self edge.
+    pub fn recursive(&self) -> Vec<bool> {
+        let count = self.nodes.len();
+        let mut succs = vec![vec![]; count];
+        let mut preds = vec![vec![]; count];
+        for &(from, to) in &self.edges {
+            succs[from].push(to);
+            preds[to].push(from);
+        }
+
+        // first pass: nodes in order of DFS completion
+        let mut visited = vec![false; count];
+        let mut finished = vec![];
+        for root in 0..count {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            let mut stack = vec![(root, 0)];
+            while let Some((node, next)) = stack.pop() {
+                if let Some(&succ) = succs[node].get(next) {
+                    stack.push((node, next + 1));
+                    if !visited[succ] {
+                        visited[succ] = true;
+                        stack.push((succ, 0));
+                    }
+                } else {
+                    finished.push(node);
+                }
+            }
+        }
+
+        // second pass: components of the reversed graph, in reverse completion order
+        let mut component = vec![usize::MAX; count];
+        let mut sizes = vec![];
+        for &root in finished.iter().rev() {
+            if component[root] != usize::MAX {
+                continue;
+            }
+            let id = sizes.len();
+            let mut size = 0;
+            let mut stack = vec![root];
+            component[root] = id;
+            while let Some(node) = stack.pop() {
+                size += 1;
+                for &pred in &preds[node] {
+                    if component[pred] == usize::MAX {
+                        component[pred] = id;
+                        stack.push(pred);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        (0..count).map(|node| sizes[component[node]] > 1 || succs[node].contains(&node)).collect()
+    }
+}
+
+/// Mermaid node id for a function name.
+fn function_id(name: &str) -> String {
+    let sanitized: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    format!("fn_{sanitized}")
+}
+
+/// Writes the call graph as a mermaid flowchart. External functions are drawn with dashed borders;
+/// with `color_by_function` every defined function gets its own fill color.
+pub fn dump_call_graph(output: &mut dyn Write, graph: &CallGraph, color_by_function: bool) -> io::Result<()> {
+    writeln!(output, "```mermaid")?;
+    writeln!(output, "flowchart LR")?;
+    for (index, name) in graph.nodes.iter().enumerate() {
+        let id = function_id(name);
+        if graph.defined[index] {
+            writeln!(output, "{id}[\"@{name}\"]")?;
+        } else {
+            writeln!(output, "{id}([\"@{name}\"])")?;
+            writeln!(output, "style {id} stroke-dasharray:4")?;
+        }
+        if color_by_function && graph.defined[index] {
+            writeln!(output, "style {id} fill:{}", function_color(name))?;
+        }
+    }
+    for &(from, to) in &graph.edges {
+        writeln!(output, "\t{} --> {}", function_id(&graph.nodes[from]), function_id(&graph.nodes[to]))?;
+    }
+    writeln!(output, "```")?;
+    Ok(())
+}
+
+static MUSTTAIL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bmusttail\s+call\s[^@]*@([-\w.$]+)\s*\(").unwrap());
+
+/// The distinct functions `function` calls with `musttail`, in order of first appearance.
+pub fn musttail_callees(function: &Function) -> Vec<String> {
+    let mut callees: Vec<String> = vec![];
+    for inst in function.blocks.iter().flat_map(|b| b.instructions.iter()) {
+        if let Some(caps) = MUSTTAIL_RE.captures(inst) {
+            if !callees.iter().any(|c| *c == caps[1]) {
+                callees.push(caps[1].to_string());
+            }
+        }
+    }
+    callees
+}
+
+/// How a [`TailChain`] stops.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainEnd {
+    /// The last function makes no `musttail` call.
+    Done,
+    /// The last function is only declared, its tail calls are unknown.
+    External,
+    /// The last function tail-calls one that is already in the chain, at this index.
+    Cycle(usize),
+    /// The last function tail-calls several functions depending on the path taken.
+    Branches(Vec<String>),
+}
+
+/// The functions reached by following `musttail` calls from a start function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TailChain {
+    pub functions: Vec<String>,
+    pub end: ChainEnd,
+}
+
+/// Follows the `musttail` calls from `start` as long as each function makes them to a single callee.
+pub fn tail_chain(module: &Module, start: &str) -> TailChain {
+    let mut functions = vec![start.to_string()];
+    loop {
+        let current = functions.last().unwrap();
+        let Some(function) = module.function(current) else {
+            return TailChain { functions, end: ChainEnd::External };
+        };
+        let next = match &musttail_callees(function)[..] {
+            [] => return TailChain { functions, end: ChainEnd::Done },
+            [next] => next.clone(),
+            several => return TailChain { functions, end: ChainEnd::Branches(several.to_vec()) },
+        };
+        if let Some(index) = functions.iter().position(|f| *f == next) {
+            return TailChain { functions, end: ChainEnd::Cycle(index) };
+        }
+        functions.push(next);
+    }
+}
+
+/// Writes a tail-call chain as a left-to-right mermaid flowchart. External functions are dashed,
+/// a cycle is drawn back to the function it returns to, and branching tail calls fan out.
+pub fn dump_tail_chain(output: &mut dyn Write, chain: &TailChain) -> io::Result<()> {
+    writeln!(output, "```mermaid")?;
+    writeln!(output, "flowchart LR")?;
+    for name in &chain.functions {
+        writeln!(output, "{}[\"@{name}\"]", function_id(name))?;
+    }
+    for pair in chain.functions.windows(2) {
+        writeln!(output, "\t{} -->|musttail| {}", function_id(&pair[0]), function_id(&pair[1]))?;
+    }
+    let last = function_id(chain.functions.last().expect("a chain starts with a function"));
+    match &chain.end {
+        ChainEnd::Done => {}
+        ChainEnd::External => writeln!(output, "style {last} stroke-dasharray:4")?,
+        ChainEnd::Cycle(index) => writeln!(output, "\t{last} -->|musttail| {}", function_id(&chain.functions[*index]))?,
+        ChainEnd::Branches(callees) => {
+            for callee in callees {
+                writeln!(output, "{}[\"@{callee}\"]", function_id(callee))?;
+                writeln!(output, "\t{last} -.->|musttail| {}", function_id(callee))?;
+            }
+        }
+    }
+    writeln!(output, "```")?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Export of a function's CFG as an [Obsidian canvas](https://jsoncanvas.org) document.
+
+use std::collections::VecDeque;
+use serde_json::{json, Value};
+use crate::cfg::Cfg;
+use crate::ir::{Function, Terminator};
+use crate::render::{block_label, RenderOptions};
+
+const NODE_WIDTH: i64 = 420;
+const LINE_HEIGHT: i64 = 24;
+const H_GAP: i64 = 60;
+const V_GAP: i64 = 100;
+
+/// The layer of every block: its BFS distance from the entry. Blocks the entry
+/// cannot reach go in one extra layer at the bottom.
+fn layers(cfg: &Cfg, entry: usize) -> Vec<usize> {
+    let mut level = vec![usize::MAX; cfg.len()];
+    let mut queue = VecDeque::new();
+    if entry < cfg.len() {
+        level[entry] = 0;
+        queue.push_back(entry);
+    }
+    while let Some(block) = queue.pop_front() {
+        for &succ in &cfg.succs[block] {
+            if level[succ] == usize::MAX {
+                level[succ] = level[block] + 1;
+                queue.push_back(succ);
+            }
+        }
+    }
+    let unreached = level.iter().filter(|&&l| l != usize::MAX).max().map_or(0, |l| l + 1);
+    level.iter().map(|&l| if l == usize::MAX { unreached } else { l }).collect()
+}
+
+/// Builds the canvas of `function`: one text node per block, laid out in layers, and one edge per CFG edge.
+pub fn canvas_json(function: &Function, options: &RenderOptions) -> Value {
+    let cfg = Cfg::new(function);
+    let entry = function.entry_index(options.entry_name.as_deref());
+    let level = layers(&cfg, entry);
+
+    let texts: Vec<String> = function.blocks.iter().map(|block| {
+        if options.abbr {
+            format!("**%{}**", block.name)
+        } else {
+            format!("**%{}**\n```llvm\n{}\n```", block.name, block_label(block, options.max_lines))
+        }
+    }).collect();
+    let heights: Vec<i64> = texts.iter().map(|t| (t.lines().count() as i64 + 1) * LINE_HEIGHT).collect();
+
+    let layer_count = level.iter().max().map_or(0, |l| l + 1);
+    let mut layer_y = vec![0; layer_count];
+    for layer in 1..layer_count {
+        let tallest = (0..level.len()).filter(|&b| level[b] == layer - 1).map(|b| heights[b]).max().unwrap_or(0);
+        layer_y[layer] = layer_y[layer - 1] + tallest + V_GAP;
+    }
+
+    let mut next_x = vec![0; layer_count];
+    let nodes: Vec<Value> = function.blocks.iter().enumerate().map(|(index, block)| {
+        let layer = level[index];
+        let x = next_x[layer] * (NODE_WIDTH + H_GAP);
+        next_x[layer] += 1;
+        let mut node = json!({
+            "id": format!("{}%{}", options.id_prefix, block.name),
+            "type": "text",
+            "text": texts[index],
+            "x": x,
+            "y": layer_y[layer],
+            "width": NODE_WIDTH,
+            "height": heights[index],
+        });
+        // canvas preset colors: "1" red, "4" green, "6" purple
+        let color = match block.terminator() {
+            Some(Terminator::Ret) => Some("4"),
+            Some(Terminator::Unreachable) => Some("1"),
+            _ if index == entry => Some("6"),
+            _ => None,
+        };
+        if let Some(color) = color {
+            node["color"] = json!(color);
+        }
+        node
+    }).collect();
+
+    let edges: Vec<Value> = cfg.succs.iter().enumerate().flat_map(|(from, succs)| {
+        succs.iter().map(move |&to| (from, to))
+    }).map(|(from, to)| {
+        let (src, dst) = (&function.blocks[from].name, &function.blocks[to].name);
+        json!({
+            "id": format!("{}%{src}->%{dst}", options.id_prefix),
+            "fromNode": format!("{}%{src}", options.id_prefix),
+            "fromSide": "bottom",
+            "toNode": format!("{}%{dst}", options.id_prefix),
+            "toSide": "top",
+            "label": format!("%{dst}"),
+        })
+    }).collect();
+
+    json!({ "nodes": nodes, "edges": edges })
+}
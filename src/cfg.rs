@@ -0,0 +1,83 @@
+use crate::ir::{BasicBlock, Function, Terminator};
+
+/// Index-based view of a function's control flow edges.
+///
+/// Edges come from the parsed successors of each block, completed by the
+/// `; preds = ...` comments for terminators whose targets are not parsed.
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    pub succs: Vec<Vec<usize>>,
+    pub preds: Vec<Vec<usize>>,
+}
+
+impl Cfg {
+    pub fn new(function: &Function) -> Cfg {
+        let count = function.blocks.len();
+        let mut cfg = Cfg { succs: vec![vec![]; count], preds: vec![vec![]; count] };
+        for (from, block) in function.blocks.iter().enumerate() {
+            for to in block.successors.iter().filter_map(|s| function.block_index(s)) {
+                cfg.add_edge(from, to);
+            }
+        }
+        for (to, block) in function.blocks.iter().enumerate() {
+            for from in block.predecessors.iter().filter_map(|p| function.block_index(p)) {
+                cfg.add_edge(from, to);
+            }
+        }
+        cfg
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize) {
+        if !self.succs[from].contains(&to) {
+            self.succs[from].push(to);
+            self.preds[to].push(from);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.succs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.succs.is_empty()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.succs.iter().map(|s| s.len()).sum()
+    }
+}
+
+/// The blocks and edges of `function` as JSON, with block names carrying their `%`.
+pub fn cfg_json(function: &Function, entry: usize) -> serde_json::Value {
+    let cfg = Cfg::new(function);
+    let name = |index: usize| format!("%{}", function.blocks[index].name);
+    let blocks: Vec<serde_json::Value> = function.blocks.iter().enumerate().map(|(index, block)| serde_json::json!({
+        "name": name(index),
+        "instructions": block.instructions.iter().map(|i| i.trim()).collect::<Vec<_>>(),
+        "successors": cfg.succs[index].iter().map(|&s| name(s)).collect::<Vec<_>>(),
+        "predecessors": cfg.preds[index].iter().map(|&p| name(p)).collect::<Vec<_>>(),
+    })).collect();
+    serde_json::json!({
+        "name": function.name,
+        "entry": function.blocks.get(entry).map(|_| name(entry)),
+        "blocks": blocks,
+    })
+}
+
+/// The role of the edge from `block` to `target` in the block's terminator: `true`/`false` for a
+/// conditional branch, `default`/`case` for a switch, `normal`/`unwind` for an invoke,
+/// `fallthrough`/`indirect` for a callbr, `indirect` for an indirectbr, `jump` otherwise.
+pub fn edge_kind(block: &BasicBlock, target: &str) -> &'static str {
+    let position = block.successors.iter().position(|s| s.strip_prefix('%').unwrap_or(s) == target);
+    match (block.terminator(), position) {
+        (Some(Terminator::CondBr), Some(0)) => "true",
+        (Some(Terminator::CondBr), Some(_)) => "false",
+        (Some(Terminator::Switch), Some(0)) => "default",
+        (Some(Terminator::Switch), Some(_)) => "case",
+        (Some(Terminator::Invoke), Some(0)) => "normal",
+        (Some(Terminator::Invoke), Some(_)) => "unwind",
+        (Some(Terminator::CallBr), Some(0)) => "fallthrough",
+        (Some(Terminator::CallBr), Some(_)) | (Some(Terminator::IndirectBr), Some(_)) => "indirect",
+        _ => "jump",
+    }
+}
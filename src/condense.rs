@@ -0,0 +1,157 @@
+//! Removal of tiny blocks from a function before rendering, keeping its control flow intact,
+//! merging of duplicated blocks and shortening of alloca runs for display.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use crate::cfg::Cfg;
+use crate::ir::{opcode, Function};
+
+/// How many blocks [`condense_small_blocks`] removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Condensed {
+    /// Blocks appended to their single predecessor.
+    pub merged: usize,
+    /// Blocks whose predecessors now branch straight to their single successor.
+    pub bypassed: usize,
+}
+
+/// Returns a copy of `function` without the blocks of fewer than `min_size` instructions that
+/// fall through to a single other block. Such a block is appended to its predecessor when it is
+/// that block's only successor, and otherwise bypassed: its predecessors branch to its successor.
+/// The entry block, blocks with several successors and blocks without predecessors are kept, so
+/// every path of the original CFG still exists, minus the removed blocks.
+pub fn condense_small_blocks<'a>(function: &Function<'a>, min_size: usize, entry: usize) -> (Function<'a>, Condensed) {
+    let cfg = Cfg::new(function);
+    let (mut succs, mut preds) = (cfg.succs, cfg.preds);
+    let mut instructions: Vec<_> = function.blocks.iter().map(|b| b.instructions.clone()).collect();
+    let mut alive = vec![true; function.blocks.len()];
+    let mut condensed = Condensed::default();
+
+    for block in 0..function.blocks.len() {
+        let size = function.blocks[block].instructions.iter().filter(|i| opcode(i).is_some()).count();
+        let &[succ] = succs[block].as_slice() else { continue };
+        if block == entry || size >= min_size || succ == block || preds[block].is_empty() {
+            continue;
+        }
+        let block_preds = std::mem::take(&mut preds[block]);
+        if let [pred] = block_preds[..] {
+            if succs[pred] == [block] {
+                let tail = std::mem::take(&mut instructions[block]);
+                instructions[pred].push(Cow::Owned(format!("; %{}", function.blocks[block].name)));
+                instructions[pred].extend(tail);
+                succs[pred] = vec![succ];
+                preds[succ].iter_mut().filter(|p| **p == block).for_each(|p| *p = pred);
+                alive[block] = false;
+                condensed.merged += 1;
+                continue;
+            }
+        }
+        preds[succ].retain(|&p| p != block);
+        for &pred in &block_preds {
+            succs[pred].retain(|&s| s != block);
+            if !succs[pred].contains(&succ) {
+                succs[pred].push(succ);
+            }
+            if !preds[succ].contains(&pred) {
+                preds[succ].push(pred);
+            }
+        }
+        succs[block].clear();
+        alive[block] = false;
+        condensed.bypassed += 1;
+    }
+
+    let name = |index: usize| Cow::Owned(format!("%{}", function.blocks[index].name));
+    let blocks = function.blocks.iter().enumerate().filter(|(index, _)| alive[*index]).map(|(index, block)| {
+        let mut block = block.clone();
+        block.instructions = std::mem::take(&mut instructions[index]);
+        block.successors = succs[index].iter().map(|&s| name(s)).collect();
+        block.predecessors = preds[index].iter().map(|&p| name(p)).collect();
+        block
+    }).collect();
+    (Function { blocks, ..function.clone() }, condensed)
+}
+
+/// Returns a copy of `function` in which each set of blocks with the same instructions (tail
+/// duplication and unrolling leave many) is shown as its first block, and the groups merged,
+/// representative first. Edges into the other blocks of a group go to the representative, which
+/// is labeled with the blocks it stands for. Identical blocks branch to the same successors, so
+/// every path of the original CFG is still drawn; only which copy a path runs through is lost.
+pub fn merge_identical_blocks<'a>(function: &Function<'a>, entry: usize) -> (Function<'a>, Vec<Vec<String>>) {
+    let mut first: HashMap<Vec<&str>, usize> = HashMap::new();
+    let mut representative: Vec<usize> = (0..function.blocks.len()).collect();
+    for (index, block) in function.blocks.iter().enumerate() {
+        let text: Vec<&str> = block.instructions.iter().map(|i| i.trim()).filter(|i| !i.is_empty()).collect();
+        if index == entry || !text.iter().any(|i| opcode(i).is_some()) {
+            continue;
+        }
+        representative[index] = *first.entry(text).or_insert(index);
+    }
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for (index, &rep) in representative.iter().enumerate().filter(|&(index, &rep)| rep != index) {
+        match groups.iter_mut().find(|g| g[0] == rep) {
+            Some(group) => group.push(index),
+            None => groups.push(vec![rep, index]),
+        }
+    }
+
+    let cfg = Cfg::new(function);
+    let name = |index: usize| format!("%{}", function.blocks[index].name);
+    let mut blocks: Vec<_> = function.blocks.iter().enumerate().filter(|&(index, _)| representative[index] == index).map(|(index, block)| {
+        let mut block = block.clone();
+        let mut succs: Vec<usize> = vec![];
+        for &s in &cfg.succs[index] {
+            if !succs.contains(&representative[s]) {
+                succs.push(representative[s]);
+            }
+        }
+        block.successors = succs.into_iter().map(|s| Cow::Owned(name(s))).collect();
+        if let Some(group) = groups.iter().find(|g| g[0] == index) {
+            let names: Vec<String> = group.iter().map(|&b| name(b)).collect();
+            block.instructions.insert(0, Cow::Owned(format!("  ; {} identical blocks: {}", group.len(), names.join(", "))));
+        }
+        block
+    }).collect();
+    // the `; preds =` comments no longer hold, derive the predecessors from the successors
+    let predecessors: Vec<Vec<_>> = blocks.iter().map(|block| blocks.iter()
+        .filter(|b| b.successors.iter().any(|s| s.strip_prefix('%') == Some(block.name.as_ref())))
+        .map(|b| Cow::Owned(format!("%{}", b.name)))
+        .collect()).collect();
+    for (block, preds) in blocks.iter_mut().zip(predecessors) {
+        block.predecessors = preds;
+    }
+    let groups = groups.iter().map(|g| g.iter().map(|&b| name(b)).collect()).collect();
+    (Function { blocks, raw: vec![], ..function.clone() }, groups)
+}
+
+/// Returns a copy of `function` whose entry block has each run of two or more consecutive
+/// `alloca`s replaced by one `; N allocas` line, as `-O0` code starts with one per local variable,
+/// and the number of allocas collapsed. Nothing else changes.
+pub fn collapse_allocas<'a>(function: &Function<'a>, entry: usize) -> (Function<'a>, usize) {
+    let mut function = function.clone();
+    let Some(block) = function.blocks.get_mut(entry) else { return (function, 0) };
+    let mut instructions = vec![];
+    let mut run = vec![];
+    let mut collapsed = 0;
+    let mut flush = |run: &mut Vec<Cow<'a, str>>, instructions: &mut Vec<Cow<'a, str>>| {
+        if run.len() > 1 {
+            collapsed += run.len();
+            instructions.push(Cow::Owned(format!("  ; {} allocas", run.len())));
+        } else {
+            instructions.append(run);
+        }
+        run.clear();
+    };
+    for inst in block.instructions.drain(..) {
+        if opcode(&inst) == Some("alloca") {
+            run.push(inst);
+        } else {
+            flush(&mut run, &mut instructions);
+            instructions.push(inst);
+        }
+    }
+    flush(&mut run, &mut instructions);
+    block.instructions = instructions;
+    function.raw = vec![];
+    (function, collapsed)
+}
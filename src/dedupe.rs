@@ -0,0 +1,136 @@
+//! Grouping of identical function bodies across several modules, as produced by inline
+//! functions and template instantiations compiled into many translation units.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::cfg::Cfg;
+use crate::ir::Function;
+
+static ATTACHMENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r",?\s*!\w+\s+!\d+").unwrap());
+static LOCAL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"%(?:"[^"]*"|[-\w.$]+)"#).unwrap());
+
+/// The text of a function with what differs between translation units for the same source
+/// removed: comments, metadata attachments (`!dbg !12`) and surrounding whitespace.
+pub fn normalized_body(function: &Function) -> String {
+    let mut body = String::new();
+    for block in &function.blocks {
+        body.push_str(&block.name);
+        body.push_str(":\n");
+        for instruction in &block.instructions {
+            let code = instruction.split(';').next().unwrap_or_default();
+            body.push_str(ATTACHMENT_RE.replace_all(code, "").trim());
+            body.push('\n');
+        }
+    }
+    body
+}
+
+/// A stable 64-bit FNV-1a hash of the normalized body.
+pub fn body_hash(function: &Function) -> u64 {
+    normalized_body(function).bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// What [`function_hash`] covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashContent {
+    /// The blocks in order, each with its terminator opcode and the indices of its successors.
+    Structure,
+    /// The structure plus the normalized instructions, with arguments, values and blocks renumbered
+    /// in order of first appearance so that renaming them does not change the hash.
+    Instructions,
+}
+
+/// [`normalized_body`] with every local name (`%x`, `%"x y"`, `%12`) replaced by `%` and its rank
+/// of first appearance, the arguments ranking first. Named types (`%struct.S`) are renumbered too.
+pub fn renumbered_body(function: &Function) -> String {
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    for argument in function.arguments() {
+        let next = numbers.len();
+        numbers.entry(format!("%{argument}")).or_insert(next);
+    }
+    let mut body = String::new();
+    for line in normalized_body(function).lines() {
+        // block labels are locals too, written without the `%` where they are defined
+        let line = match line.strip_suffix(':') {
+            Some(label) if !line.contains(' ') => format!("%{label}:"),
+            _ => line.to_string(),
+        };
+        let renumbered = LOCAL_RE.replace_all(&line, |caps: &regex::Captures| {
+            let next = numbers.len();
+            format!("%{}", numbers.entry(caps[0].to_string()).or_insert(next))
+        });
+        body.push_str(&renumbered);
+        body.push('\n');
+    }
+    body
+}
+
+/// A stable 64-bit FNV-1a hash of `function` that ignores SSA and block renaming, comments and
+/// metadata attachments; `content` selects whether the instructions count or only the CFG shape.
+pub fn function_hash(function: &Function, content: HashContent) -> u64 {
+    let cfg = Cfg::new(function);
+    let mut text = String::new();
+    for (index, block) in function.blocks.iter().enumerate() {
+        let succs: Vec<String> = cfg.succs[index].iter().map(usize::to_string).collect();
+        text.push_str(&format!("{index} {} -> {}\n", block.terminator().map_or("none", |t| t.as_str()), succs.join(",")));
+    }
+    if content == HashContent::Instructions {
+        text.push_str(&renumbered_body(function));
+    }
+    text.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Functions sharing one normalized body; `members` index the slice given to [`group_duplicates`].
+#[derive(Clone, Debug)]
+pub struct BodyGroup {
+    pub hash: u64,
+    pub members: Vec<usize>,
+}
+
+/// Groups `functions` with the same name and normalized body, in order of first occurrence.
+pub fn group_duplicates(functions: &[&Function]) -> Vec<BodyGroup> {
+    let mut groups: Vec<BodyGroup> = vec![];
+    let mut by_key: BTreeMap<(&str, u64), usize> = BTreeMap::new();
+    for (index, function) in functions.iter().enumerate() {
+        let hash = body_hash(function);
+        let group = *by_key.entry((function.name.as_ref(), hash)).or_insert_with(|| {
+            groups.push(BodyGroup { hash, members: vec![] });
+            groups.len() - 1
+        });
+        groups[group].members.push(index);
+    }
+    groups
+}
+
+/// Groups of two or more of `functions`, whatever their names, that have the same blocks, edges and
+/// instructions up to renaming (see [`HashContent::Instructions`]), as indices into `functions` in
+/// order of first occurrence. These are what identical code folding would merge.
+pub fn identical_functions(functions: &[&Function]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut by_hash: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+    for (index, function) in functions.iter().enumerate() {
+        let candidates = by_hash.entry(function_hash(function, HashContent::Instructions)).or_default();
+        // compare the bodies too, the hash alone could collide
+        let body = renumbered_body(function);
+        match candidates.iter().find(|&&g| renumbered_body(functions[groups[g][0]]) == body) {
+            Some(&group) => groups[group].push(index),
+            None => {
+                candidates.push(groups.len());
+                groups.push(vec![index]);
+            }
+        }
+    }
+    groups.retain(|members| members.len() > 1);
+    groups
+}
+
+/// Names defined with more than one distinct body, with the indices of their groups.
+/// Under the one-definition rule these should not exist for `linkonce_odr`/`weak_odr` symbols.
+pub fn odr_conflicts<'f>(functions: &[&'f Function], groups: &[BodyGroup]) -> Vec<(&'f str, Vec<usize>)> {
+    let mut by_name: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, group) in groups.iter().enumerate() {
+        by_name.entry(functions[group.members[0]].name.as_ref()).or_default().push(index);
+    }
+    by_name.into_iter().filter(|(_, groups)| groups.len() > 1).collect()
+}
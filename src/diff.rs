@@ -0,0 +1,111 @@
+//! Structural comparison of two versions of a function's CFG.
+
+use std::io::{self, Write};
+use crate::cfg::Cfg;
+use crate::ir::Function;
+use crate::render::{block_label, node_id, RenderOptions};
+
+/// Blocks and edges (by block name) that differ between two versions of a function.
+#[derive(Clone, Debug, Default)]
+pub struct CfgDiff {
+    pub added_blocks: Vec<String>,
+    pub removed_blocks: Vec<String>,
+    /// Blocks present in both versions whose instructions differ.
+    pub changed_blocks: Vec<String>,
+    pub added_edges: Vec<(String, String)>,
+    pub removed_edges: Vec<(String, String)>,
+}
+
+fn edges(function: &Function) -> Vec<(String, String)> {
+    let cfg = Cfg::new(function);
+    cfg.succs.iter().enumerate()
+        .flat_map(|(from, succs)| succs.iter().map(move |&to| (from, to)))
+        .map(|(from, to)| (function.blocks[from].name.to_string(), function.blocks[to].name.to_string()))
+        .collect()
+}
+
+pub fn diff_functions(old: &Function, new: &Function) -> CfgDiff {
+    let mut diff = CfgDiff::default();
+    for block in &new.blocks {
+        match old.block_index(&block.name) {
+            None => diff.added_blocks.push(block.name.to_string()),
+            Some(index) if old.blocks[index].instructions != block.instructions => diff.changed_blocks.push(block.name.to_string()),
+            Some(_) => {}
+        }
+    }
+    diff.removed_blocks = old.blocks.iter()
+        .filter(|b| new.block_index(&b.name).is_none())
+        .map(|b| b.name.to_string())
+        .collect();
+
+    let (old_edges, new_edges) = (edges(old), edges(new));
+    diff.added_edges = new_edges.iter().filter(|e| !old_edges.contains(e)).cloned().collect();
+    diff.removed_edges = old_edges.iter().filter(|e| !new_edges.contains(e)).cloned().collect();
+    diff
+}
+
+impl CfgDiff {
+    /// True when both versions have the same blocks, edges and instructions.
+    pub fn is_empty(&self) -> bool {
+        self.added_blocks.is_empty() && self.removed_blocks.is_empty() && self.changed_blocks.is_empty()
+            && self.added_edges.is_empty() && self.removed_edges.is_empty()
+    }
+
+    /// Writes the differences as a markdown list.
+    pub fn write_summary(&self, output: &mut dyn Write) -> io::Result<()> {
+        if self.is_empty() {
+            return writeln!(output, "- no structural changes");
+        }
+        let blocks = |names: &[String]| names.iter().map(|n| format!("`%{n}`")).collect::<Vec<_>>().join(", ");
+        let edges = |edges: &[(String, String)]| edges.iter().map(|(a, b)| format!("`%{a} -> %{b}`")).collect::<Vec<_>>().join(", ");
+        let sections = [
+            ("added blocks", blocks(&self.added_blocks)),
+            ("removed blocks", blocks(&self.removed_blocks)),
+            ("changed blocks", blocks(&self.changed_blocks)),
+            ("added edges", edges(&self.added_edges)),
+            ("removed edges", edges(&self.removed_edges)),
+        ];
+        for (title, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+            writeln!(output, "- {title}: {items}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes one mermaid diagram merging both versions: added blocks and edges in green
+/// (edges drawn thick), removed ones in red (edges dotted), changed blocks in yellow.
+pub fn dump_cfg_diff(output: &mut dyn Write, old: &Function, new: &Function, diff: &CfgDiff, options: &RenderOptions) -> io::Result<()> {
+    let id = |name: &str| node_id(&options.id_prefix, name);
+    writeln!(output, "```mermaid")?;
+    writeln!(output, "flowchart TD")?;
+    writeln!(output, "%% function {}", new.name)?;
+
+    let removed = old.blocks.iter().filter(|b| diff.removed_blocks.iter().any(|n| *n == b.name));
+    for block in new.blocks.iter().chain(removed) {
+        if !options.abbr {
+            writeln!(output, "{}[\"{}\"]", id(&block.name), block_label(block, options.max_lines))?;
+        } else {
+            writeln!(output, "{}", id(&block.name))?;
+        }
+    }
+
+    for (from, to) in edges(new) {
+        let arrow = if diff.added_edges.contains(&(from.clone(), to.clone())) { "==>" } else { "-->" };
+        writeln!(output, "\t{} {arrow}|%{to}| {}", id(&from), id(&to))?;
+    }
+    for (from, to) in &diff.removed_edges {
+        writeln!(output, "\t{} -.->|%{to}| {}", id(from), id(to))?;
+    }
+
+    for name in &diff.added_blocks {
+        writeln!(output, "style {} fill:#dfd,stroke:#0a0", id(name))?;
+    }
+    for name in &diff.removed_blocks {
+        writeln!(output, "style {} fill:#fdd,stroke:#a00,stroke-dasharray:4", id(name))?;
+    }
+    for name in &diff.changed_blocks {
+        writeln!(output, "style {} fill:#ffd", id(name))?;
+    }
+    writeln!(output, "```")?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Dominator trees, computed with the Cooper-Harvey-Kennedy iterative algorithm.
+
+use std::borrow::Cow;
+use crate::cfg::Cfg;
+use crate::ir::Function;
+
+#[derive(Clone, Debug)]
+pub struct Dominators {
+    pub root: usize,
+    /// Immediate dominator of every block; `None` for the root and for blocks unreachable from it.
+    pub idom: Vec<Option<usize>>,
+    /// Position of each reachable block in reverse postorder.
+    rpo_index: Vec<Option<usize>>,
+}
+
+/// Blocks reachable from `root` in reverse postorder, following `succs`.
+pub fn reverse_postorder(succs: &[Vec<usize>], root: usize) -> Vec<usize> {
+    let mut visited = vec![false; succs.len()];
+    let mut postorder = vec![];
+    // iterative DFS: (block, index of next successor to visit)
+    let mut stack = vec![(root, 0)];
+    visited[root] = true;
+    while let Some((block, next)) = stack.pop() {
+        if let Some(&succ) = succs[block].get(next) {
+            stack.push((block, next + 1));
+            if !visited[succ] {
+                visited[succ] = true;
+                stack.push((succ, 0));
+            }
+        } else {
+            postorder.push(block);
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+impl Dominators {
+    /// Dominators of the graph given by `succs`/`preds`, rooted at `root`.
+    pub fn compute(succs: &[Vec<usize>], preds: &[Vec<usize>], root: usize) -> Dominators {
+        let count = succs.len();
+        let rpo = reverse_postorder(succs, root);
+        let mut rpo_index = vec![None; count];
+        rpo.iter().enumerate().for_each(|(i, &b)| rpo_index[b] = Some(i));
+
+        let mut idom: Vec<Option<usize>> = vec![None; count];
+        idom[root] = Some(root);
+        let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while rpo_index[a] > rpo_index[b] {
+                    a = idom[a].unwrap();
+                }
+                while rpo_index[b] > rpo_index[a] {
+                    b = idom[b].unwrap();
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in rpo.iter().skip(1) {
+                let mut new_idom = None;
+                for &pred in &preds[block] {
+                    if idom[pred].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(current) => intersect(&idom, pred, current),
+                    });
+                }
+                if new_idom.is_some() && idom[block] != new_idom {
+                    idom[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        idom[root] = None;
+        Dominators { root, idom, rpo_index }
+    }
+
+    /// Dominator tree of a function's CFG, rooted at its entry block.
+    pub fn new(cfg: &Cfg, entry: usize) -> Dominators {
+        Dominators::compute(&cfg.succs, &cfg.preds, entry)
+    }
+
+    /// Post-dominator tree of a function's CFG. Its root is a virtual exit with index `cfg.len()`,
+    /// the successor of every block without successors, so `idom` has one extra entry.
+    pub fn post(cfg: &Cfg) -> Dominators {
+        let exit = cfg.len();
+        let exits: Vec<usize> = (0..exit).filter(|&b| cfg.succs[b].is_empty()).collect();
+        let mut succs = cfg.preds.clone();
+        succs.push(exits.clone());
+        let mut preds = cfg.succs.clone();
+        exits.iter().for_each(|&b| preds[b].push(exit));
+        preds.push(vec![]);
+        Dominators::compute(&succs, &preds, exit)
+    }
+
+    /// Position of `block` in reverse postorder, `None` when it is unreachable.
+    pub fn rpo_position(&self, block: usize) -> Option<usize> {
+        self.rpo_index[block]
+    }
+
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.rpo_index[block].is_some()
+    }
+
+    /// Whether `a` dominates `b` (every block dominates itself).
+    pub fn dominates(&self, a: usize, mut b: usize) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom[b] {
+                Some(parent) => b = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// The chain of dominators of `block`, from the root down to `block` itself.
+    pub fn dominator_path(&self, mut block: usize) -> Vec<usize> {
+        if !self.is_reachable(block) {
+            return vec![];
+        }
+        let mut path = vec![block];
+        while let Some(parent) = self.idom[block] {
+            path.push(parent);
+            block = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Returns the chain of immediate dominators of `block`, from `entry` down to `block`, as a
+/// function of those blocks only, each branching to the next: the blocks that have executed
+/// whenever `block` does. `None` when `block` is unreachable from `entry`.
+pub fn dominator_chain<'a>(function: &Function<'a>, entry: usize, block: usize) -> Option<Function<'a>> {
+    let cfg = Cfg::new(function);
+    let path = Dominators::new(&cfg, entry).dominator_path(block);
+    if path.is_empty() {
+        return None;
+    }
+    let name = |index: usize| Cow::Owned(format!("%{}", function.blocks[index].name));
+    let blocks = path.iter().enumerate().map(|(position, &index)| {
+        let mut block = function.blocks[index].clone();
+        block.successors = path.get(position + 1).map(|&next| name(next)).into_iter().collect();
+        block.predecessors = position.checked_sub(1).map(|previous| name(path[previous])).into_iter().collect();
+        block
+    }).collect();
+    Some(Function { blocks, raw: vec![], ..function.clone() })
+}
@@ -0,0 +1,130 @@
+//! Graphviz output of function CFGs.
+
+use std::io::{self, Write};
+use crate::analysis::{loop_depths, natural_loops};
+use crate::cfg::Cfg;
+use crate::dom::Dominators;
+use crate::ir::{opcode, Function, Terminator};
+use crate::render::{block_label, degree_badge, function_color, truncate_label, EdgeBundling, RenderOptions};
+
+/// With edge bundling, the edges into a block with at least this many predecessors share one
+/// head port (`samehead`), and those out of a block with this many successors one tail port.
+const BUNDLED_FAN: usize = 3;
+
+/// Escapes `text` for a double-quoted dot string, such as an id, port name or edge label.
+fn dot_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes `text` for a node label. Each line is left-justified with `\l`.
+fn dot_label(text: &str) -> String {
+    let mut label = String::new();
+    for line in text.lines() {
+        label.push_str(&dot_string(line).replace('{', "\\{").replace('}', "\\}"));
+        label.push_str("\\l");
+    }
+    label
+}
+
+/// Writes one digraph holding a cluster per function, for a single `dot` run to lay out a whole
+/// module. Node ids are prefixed with the function name so that blocks of different functions
+/// never collide, and with `#N` when several inputs define a function of the same name.
+///
+/// With [`RenderOptions::edge_bundling`] the graph also gets `concentrate=true` or `splines=ortho`,
+/// and the edges at high fan-in and fan-out blocks get `samehead`/`sametail`, which only `dot`
+/// honors; other engines ignore them.
+///
+/// With [`RenderOptions::dot_attrs`] every node also carries `blockname`, `insts` (the instruction
+/// count), `term` (the terminator, `none` without one), `loopdepth` (the number of natural loops
+/// containing the block) and `reachable` (from the entry), for downstream tools to filter on.
+pub fn dump_dot(output: &mut dyn Write, functions: &[&Function], options: &RenderOptions) -> io::Result<()> {
+    writeln!(output, "digraph cfg {{")?;
+    match options.edge_bundling {
+        Some(EdgeBundling::Concentrate) => writeln!(output, "  concentrate=true;")?,
+        Some(EdgeBundling::Ortho) => writeln!(output, "  splines=ortho;")?,
+        None => {}
+    }
+    writeln!(output, "  node [shape=box, fontname=\"monospace\"];")?;
+    for (position, function) in functions.iter().enumerate() {
+        // functions of the same name from different inputs get a `#N` suffix, keeping ids unique
+        let repeats = functions[..position].iter().filter(|f| f.name == function.name).count();
+        let key = if repeats == 0 { function.name.to_string() } else { format!("{}#{repeats}", function.name) };
+        let id = |block: &str| format!("\"{}\"", dot_string(&format!("{}{key}%{block}", options.id_prefix)));
+        let entry = function.entry_index(options.entry_name.as_deref());
+        writeln!(output, "  subgraph \"cluster_{}\" {{", dot_string(&key))?;
+        writeln!(output, "    label=\"@{}\";", dot_string(&function.name))?;
+        let cfg = Cfg::new(function);
+        let analyses = (options.dot_attrs && !cfg.is_empty()).then(|| {
+            let doms = Dominators::new(&cfg, entry);
+            (loop_depths(&cfg, &natural_loops(&cfg, &doms)), doms)
+        });
+        for (index, block) in function.blocks.iter().enumerate() {
+            let mut label = dot_label(&format!("%{}:", block.name));
+            if !options.abbr {
+                label.push_str(&dot_label(&block_label(block, options.max_lines)));
+            }
+            if let Some(badge) = degree_badge(&cfg, index, options) {
+                label.push_str(&dot_label(&badge));
+            }
+            let mut attrs = vec![format!("label=\"{label}\"")];
+            if options.color_by_function {
+                attrs.push(format!("style=filled, fillcolor=\"{}\"", function_color(&function.name)));
+            }
+            if index == entry {
+                attrs.push("penwidth=3".to_string());
+            }
+            match block.terminator() {
+                Some(Terminator::Ret) => attrs.push("color=green".to_string()),
+                Some(Terminator::Unreachable) => attrs.push("color=red".to_string()),
+                _ => {}
+            }
+            if let Some((depths, doms)) = &analyses {
+                attrs.push(format!("blockname=\"{}\"", dot_string(&block.name)));
+                attrs.push(format!("insts={}", block.instructions.iter().filter(|i| opcode(i).is_some()).count()));
+                attrs.push(format!("term=\"{}\"", block.terminator().map_or("none", |t| t.as_str())));
+                attrs.push(format!("loopdepth={}", depths[index]));
+                attrs.push(format!("reachable={}", doms.is_reachable(index)));
+            }
+            writeln!(output, "    {} [{}];", id(&block.name), attrs.join(", "))?;
+        }
+        for (from, succs) in cfg.succs.iter().enumerate() {
+            for &to in succs {
+                let (src, dst) = (&function.blocks[from].name, &function.blocks[to].name);
+                let label = if options.edge_bundling == Some(EdgeBundling::Ortho) { "xlabel" } else { "label" };
+                let marker = if from == to { "↻ " } else { "" };
+                let text = format!("{marker}%{dst}");
+                let mut attrs = match truncate_label(&text, options.max_edge_label) {
+                    Some(cut) => vec![format!("{label}=\"{}\"", dot_string(&cut)), format!("comment=\"{}\"", dot_string(&text))],
+                    None => vec![format!("{label}=\"{}\"", dot_string(&text))],
+                };
+                if options.edge_bundling.is_some() {
+                    if cfg.preds[to].len() >= BUNDLED_FAN {
+                        attrs.push(format!("samehead=\"in_{}\"", dot_string(dst)));
+                    }
+                    if succs.len() >= BUNDLED_FAN {
+                        attrs.push(format!("sametail=\"out_{}\"", dot_string(src)));
+                    }
+                }
+                writeln!(output, "    {} -> {} [{}];", id(src), id(dst), attrs.join(", "))?;
+            }
+        }
+        writeln!(output, "  }}")?;
+    }
+    writeln!(output, "}}")
+}
+
+/// Renders dot source through the graphviz `dot` program, e.g. into `svg` or `png`.
+pub fn render_with_graphviz(dot_source: &[u8], format: &str) -> io::Result<Vec<u8>> {
+    let mut child = std::process::Command::new("dot")
+        .arg(format!("-T{format}"))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("cannot run graphviz `dot`: {err}")))?;
+    child.stdin.take().expect("piped stdin").write_all(dot_source)?;
+    let result = child.wait_with_output()?;
+    if !result.status.success() {
+        return Err(io::Error::other(format!("`dot -T{format}` failed with {}", result.status)));
+    }
+    Ok(result.stdout)
+}
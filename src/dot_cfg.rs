@@ -0,0 +1,113 @@
+//! Reading of the `.dot` files written by `opt -dot-cfg` and `-dot-cfg-only` back into functions.
+//!
+//! Only the subset opt emits is understood: one `digraph "CFG for 'name' function"` per function,
+//! `NodeN [shape=record, ..., label="{block:\l instructions\l|{<s0>T|<s1>F}}"]` nodes and
+//! `NodeN:sK -> NodeM` edges.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use regex::Regex;
+use crate::ir::{BasicBlock, Function, Module};
+
+/// The value of the `label="..."` attribute of a node statement, still escaped.
+fn label_attribute(attributes: &str) -> Option<&str> {
+    let start = attributes.find("label=\"")? + "label=\"".len();
+    let mut escaped = false;
+    for (offset, c) in attributes[start..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(&attributes[start..start + offset]),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The first field of a record label, with the `\l` line ends turned into lines and the record
+/// escapes removed. Lines that opt wrapped at 80 columns (continued with `...`) are joined again.
+fn record_text(label: &str) -> Vec<String> {
+    let inner = label.strip_prefix('{').and_then(|l| l.strip_suffix('}')).unwrap_or(label);
+    let mut lines: Vec<String> = vec![String::new()];
+    let mut chars = inner.chars();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('l') | Some('n') | Some('r') => lines.push(String::new()),
+                Some(escaped) => lines.last_mut().unwrap().push(escaped),
+                None => {}
+            },
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '|' if depth == 0 => break, // the successor ports follow
+            _ => lines.last_mut().unwrap().push(c),
+        }
+    }
+    let mut joined: Vec<String> = vec![];
+    for line in lines {
+        match (line.strip_prefix("..."), joined.last_mut()) {
+            (Some(rest), Some(previous)) => previous.push_str(rest),
+            _ => joined.push(line),
+        }
+    }
+    joined.retain(|l| !l.trim().is_empty());
+    joined
+}
+
+/// Parses the `digraph`s of `opt -dot-cfg` output into a module holding one function each.
+/// Block successors come from the edges, ordered by their record port.
+pub fn parse_dot_cfg(text: &str) -> Module<'static> {
+    let digraph_re = Regex::new(r#"^\s*digraph\s+"CFG for '(.+)' function"\s*\{"#).unwrap();
+    let node_re = Regex::new(r"^\s*(Node0x[0-9a-fA-F]+)\s*\[(.*)\]\s*;?\s*$").unwrap();
+    let edge_re = Regex::new(r"^\s*(Node0x[0-9a-fA-F]+)(?::s(\d+))?\s*->\s*(Node0x[0-9a-fA-F]+)").unwrap();
+
+    let mut module = Module::default();
+    // node id -> block index, and (source, port, target) per edge, of the current digraph
+    let mut nodes: HashMap<String, usize> = HashMap::new();
+    let mut edges: Vec<(String, usize, String)> = vec![];
+    let finish = |function: Option<&mut Function<'static>>, nodes: &mut HashMap<String, usize>, edges: &mut Vec<(String, usize, String)>| {
+        let Some(function) = function else { return };
+        edges.sort_by_key(|(source, port, _)| (nodes.get(source).copied(), *port));
+        for (source, _, target) in edges.drain(..) {
+            let (Some(&from), Some(&to)) = (nodes.get(&source), nodes.get(&target)) else { continue };
+            let name = format!("%{}", function.blocks[to].name);
+            function.blocks[from].successors.push(Cow::Owned(name));
+        }
+        nodes.clear();
+    };
+
+    for line in text.lines() {
+        if let Some(caps) = digraph_re.captures(line) {
+            finish(module.functions.last_mut(), &mut nodes, &mut edges);
+            let name = caps[1].to_string();
+            module.functions.push(Function {
+                define: Cow::Owned(format!("define @{name}() ; from opt -dot-cfg")),
+                name: Cow::Owned(name),
+                attrs: None,
+                blocks: vec![],
+                module: 0,
+                raw: vec![],
+            });
+        } else if let Some(caps) = edge_re.captures(line) {
+            let port = caps.get(2).and_then(|p| p.as_str().parse().ok()).unwrap_or(0);
+            edges.push((caps[1].to_string(), port, caps[3].to_string()));
+        } else if let Some(caps) = node_re.captures(line) {
+            let Some(function) = module.functions.last_mut() else { continue };
+            let Some(label) = label_attribute(&caps[2]) else { continue };
+            let mut lines = record_text(label).into_iter();
+            let header = lines.next().unwrap_or_default();
+            let name = header.trim().trim_end_matches(':');
+            let name = name.strip_prefix('%').unwrap_or(name).to_string();
+            nodes.insert(caps[1].to_string(), function.blocks.len());
+            function.blocks.push(BasicBlock {
+                name: Cow::Owned(name),
+                instructions: lines.map(Cow::Owned).collect(),
+                predecessors: vec![],
+                successors: vec![],
+            });
+        }
+    }
+    finish(module.functions.last_mut(), &mut nodes, &mut edges);
+    module
+}
@@ -0,0 +1,60 @@
+//! [GraphML](http://graphml.graphdrawing.org) output of function CFGs, for yEd and other graph editors.
+
+use std::io::{self, Write};
+use crate::analysis::instruction_count;
+use crate::cfg::{edge_kind, Cfg};
+use crate::ir::{opcode, Function};
+
+/// Escapes `text` for XML character data and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+/// `name` reduced to the characters allowed in a GraphML id (an XML NMTOKEN).
+fn id_token(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' }).collect()
+}
+
+/// Writes one GraphML document holding a directed graph per function. Node ids are prefixed
+/// with the function name, so that the graphs of a module never collide.
+pub fn dump_graphml(output: &mut dyn Write, functions: &[&Function]) -> io::Result<()> {
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(output, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns""#)?;
+    writeln!(output, r#"    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance""#)?;
+    writeln!(output, r#"    xsi:schemaLocation="http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd">"#)?;
+    writeln!(output, r#"  <key id="function" for="graph" attr.name="function" attr.type="string"/>"#)?;
+    writeln!(output, r#"  <key id="instructions_total" for="graph" attr.name="instructions" attr.type="int"/>"#)?;
+    writeln!(output, r#"  <key id="block" for="node" attr.name="block" attr.type="string"/>"#)?;
+    writeln!(output, r#"  <key id="instructions" for="node" attr.name="instructions" attr.type="int"/>"#)?;
+    writeln!(output, r#"  <key id="terminator" for="node" attr.name="terminator" attr.type="string"/>"#)?;
+    writeln!(output, r#"  <key id="kind" for="edge" attr.name="kind" attr.type="string"/>"#)?;
+    writeln!(output, r#"  <key id="label" for="edge" attr.name="label" attr.type="string"/>"#)?;
+    for function in functions {
+        let prefix = id_token(&function.name);
+        let node = |index: usize| format!("{prefix}:{}", id_token(&function.blocks[index].name));
+        writeln!(output, r#"  <graph id="{prefix}" edgedefault="directed">"#)?;
+        writeln!(output, r#"    <data key="function">{}</data>"#, escape_xml(&function.name))?;
+        writeln!(output, r#"    <data key="instructions_total">{}</data>"#, instruction_count(function))?;
+        for (index, block) in function.blocks.iter().enumerate() {
+            let instructions = block.instructions.iter().filter(|i| opcode(i).is_some()).count();
+            writeln!(output, r#"    <node id="{}">"#, node(index))?;
+            writeln!(output, r#"      <data key="block">%{}</data>"#, escape_xml(&block.name))?;
+            writeln!(output, r#"      <data key="instructions">{instructions}</data>"#)?;
+            if let Some(terminator) = block.terminator() {
+                writeln!(output, r#"      <data key="terminator">{}</data>"#, terminator.as_str())?;
+            }
+            writeln!(output, "    </node>")?;
+        }
+        let cfg = Cfg::new(function);
+        let edges = cfg.succs.iter().enumerate().flat_map(|(from, succs)| succs.iter().map(move |&to| (from, to)));
+        for (from, to) in edges {
+            let target = &function.blocks[to].name;
+            writeln!(output, r#"    <edge source="{}" target="{}">"#, node(from), node(to))?;
+            writeln!(output, r#"      <data key="kind">{}</data>"#, edge_kind(&function.blocks[from], target))?;
+            writeln!(output, r#"      <data key="label">%{}</data>"#, escape_xml(target))?;
+            writeln!(output, "    </edge>")?;
+        }
+        writeln!(output, "  </graph>")?;
+    }
+    writeln!(output, "</graphml>")
+}
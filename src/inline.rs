@@ -0,0 +1,212 @@
+//! Inlining preview: the CFG of a caller with a callee's CFG spliced in at one call site.
+
+use std::borrow::Cow;
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::callgraph::CallGraph;
+use crate::cfg::Cfg;
+use crate::ir::{BasicBlock, Function, Module, Terminator};
+
+/// The `(block, instruction)` indices of the calls to `callee` in `caller`, in program order.
+pub fn call_sites(caller: &Function, callee: &str) -> Vec<(usize, usize)> {
+    let call_re = Regex::new(&format!(r"\b(?:call|invoke)\s[^@]*@{}\s*\(", regex::escape(callee))).unwrap();
+    caller.blocks.iter().enumerate()
+        .flat_map(|(b, block)| block.instructions.iter().enumerate().map(move |(i, inst)| (b, i, inst)))
+        .filter(|(_, _, inst)| call_re.is_match(inst))
+        .map(|(b, i, _)| (b, i))
+        .collect()
+}
+
+/// The caller with the callee spliced in, and the names of the blocks that came from the callee.
+#[derive(Clone, Debug)]
+pub struct InlinePreview<'a> {
+    pub function: Function<'a>,
+    pub spliced: Vec<String>,
+    /// The number of call sites the callee has in the caller.
+    pub call_sites: usize,
+}
+
+/// Splits the block holding the `call_site`-th (1-based) call to `callee` in `caller` after the call,
+/// and routes it through a copy of the callee's blocks, renamed `<callee>.<block>`, whose returns
+/// branch to the continuation `<block>.cont`. Recursive callees, callees using exception handling
+/// and calls made with `invoke` are refused.
+pub fn inline_preview<'a>(module: &Module<'a>, caller: &str, callee: &str, call_site: usize) -> Result<InlinePreview<'a>, String> {
+    let caller_fn = module.function(caller).ok_or_else(|| format!("no function @{caller}"))?;
+    let callee_fn = module.function(callee).ok_or_else(|| format!("@{callee} is not defined in the module"))?;
+    let graph = CallGraph::new(module);
+    if graph.index(callee).is_some_and(|n| graph.recursive()[n]) {
+        return Err(format!("@{callee} is recursive; inlining it once would not show its real shape"));
+    }
+    let eh = callee_fn.blocks.iter().find_map(|b| match b.terminator() {
+        Some(t @ (Terminator::Invoke | Terminator::Resume | Terminator::CatchSwitch | Terminator::CatchRet | Terminator::CleanupRet)) => Some(t.as_str()),
+        _ => None,
+    });
+    if let Some(terminator) = eh {
+        return Err(format!("@{callee} uses exception handling ({terminator}), which the preview does not splice"));
+    }
+    let sites = call_sites(caller_fn, callee);
+    if sites.is_empty() {
+        return Err(format!("@{caller} does not call @{callee}"));
+    }
+    let &(site_block, site_inst) = sites.get(call_site.wrapping_sub(1))
+        .ok_or_else(|| format!("@{caller} calls @{callee} {} time(s); there is no call site {call_site}", sites.len()))?;
+    if Terminator::classify(&caller_fn.blocks[site_block].instructions[site_inst]) == Some(Terminator::Invoke) {
+        return Err(format!("call site {call_site} is an invoke, which the preview does not splice"));
+    }
+
+    let caller_cfg = Cfg::new(caller_fn);
+    let callee_cfg = Cfg::new(callee_fn);
+    let split = &caller_fn.blocks[site_block];
+    let continuation = format!("{}.cont", split.name);
+    let renamed = |index: usize| format!("{callee}.{}", callee_fn.blocks[index].name);
+    let percent = |name: String| Cow::Owned(format!("%{name}"));
+
+    let mut blocks = vec![];
+    for (index, block) in caller_fn.blocks.iter().enumerate() {
+        let mut block = block.clone();
+        block.successors = caller_cfg.succs[index].iter().map(|&s| percent(caller_fn.blocks[s].name.to_string())).collect();
+        if index != site_block {
+            blocks.push(block);
+            continue;
+        }
+        let mut tail = block.clone();
+        tail.name = Cow::Owned(continuation.clone());
+        tail.instructions = block.instructions.split_off(site_inst + 1);
+        block.successors = vec![percent(renamed(callee_fn.entry_index(None)))];
+        blocks.push(block);
+        for (callee_index, callee_block) in callee_fn.blocks.iter().enumerate() {
+            let mut copy = callee_block.clone();
+            copy.name = Cow::Owned(renamed(callee_index));
+            copy.successors = if callee_block.terminator() == Some(Terminator::Ret) {
+                vec![percent(continuation.clone())]
+            } else {
+                callee_cfg.succs[callee_index].iter().map(|&s| percent(renamed(s))).collect()
+            };
+            blocks.push(copy);
+        }
+        blocks.push(tail);
+    }
+    // the `; preds =` comments no longer hold, derive the predecessors from the successors
+    let predecessors: Vec<Vec<_>> = blocks.iter().map(|block| blocks.iter()
+        .filter(|b| b.successors.iter().any(|s| s.strip_prefix('%') == Some(block.name.as_ref())))
+        .map(|b| percent(b.name.to_string()))
+        .collect()).collect();
+    for (block, preds) in blocks.iter_mut().zip(predecessors) {
+        block.predecessors = preds;
+    }
+
+    Ok(InlinePreview {
+        function: Function { blocks, raw: vec![], ..caller_fn.clone() },
+        spliced: (0..callee_fn.blocks.len()).map(renamed).collect(),
+        call_sites: sites.len(),
+    })
+}
+
+/// A function with the defined functions it calls stitched in, see [`stitch_calls`].
+#[derive(Clone, Debug)]
+pub struct Stitched<'a> {
+    pub function: Function<'a>,
+    /// The number of call sites expanded.
+    pub expanded: usize,
+    /// The number of recursive calls left unexpanded at the depth limit.
+    pub recursion_cut: usize,
+}
+
+fn uses_exception_handling(function: &Function) -> bool {
+    function.blocks.iter().any(|b| matches!(b.terminator(),
+        Some(Terminator::Invoke | Terminator::Resume | Terminator::CatchSwitch | Terminator::CatchRet | Terminator::CleanupRet)))
+}
+
+fn empty_block<'a>(name: String) -> BasicBlock<'a> {
+    BasicBlock { name: Cow::Owned(name), instructions: vec![], predecessors: vec![], successors: vec![] }
+}
+
+/// A `call` and its callee, as expanded by --stitch; `invoke`s are left alone.
+static CALL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bcall\s[^@]*@([-\w.$]+)\s*\(").unwrap());
+
+struct Stitcher<'m, 'a> {
+    module: &'m Module<'a>,
+    blocks: Vec<BasicBlock<'a>>,
+    /// The functions being expanded, innermost last, with the name of their entry block instance.
+    stack: Vec<(String, String)>,
+    instances: usize,
+    expanded: usize,
+    recursion_cut: usize,
+}
+
+impl<'a> Stitcher<'_, 'a> {
+    /// Appends the blocks of one instance of `function`, named `<prefix><block>`. Its returns
+    /// branch to `return_to`, and its calls to defined functions are expanded `depth` levels deep.
+    fn expand(&mut self, function: &Function<'a>, prefix: &str, depth: usize, return_to: Option<&str>) {
+        let cfg = Cfg::new(function);
+        let name = |block: &str| format!("{prefix}{block}");
+        let percent = |name: String| Cow::Owned(format!("%{name}"));
+        self.stack.push((function.name.to_string(), name(&function.blocks[function.entry_index(None)].name)));
+        for (index, block) in function.blocks.iter().enumerate() {
+            let mut current = empty_block(name(&block.name));
+            let mut returns = 0;
+            for inst in &block.instructions {
+                current.instructions.push(inst.clone());
+                let Some(callee) = CALL_RE.captures(inst).and_then(|caps| self.module.function(&caps[1])) else { continue };
+                let ancestor = self.stack.iter().rev().find(|(f, _)| *f == callee.name).map(|(_, entry)| entry.clone());
+                if uses_exception_handling(callee) || (depth == 0 && ancestor.is_none()) {
+                    continue;
+                }
+                returns += 1;
+                let continuation = format!("{}.ret{returns}", name(&block.name));
+                if depth > 0 {
+                    self.instances += 1;
+                    let callee_prefix = format!("{}.{}.", callee.name, self.instances);
+                    current.successors = vec![percent(format!("{callee_prefix}{}", callee.blocks[callee.entry_index(None)].name))];
+                    self.blocks.push(std::mem::replace(&mut current, empty_block(continuation.clone())));
+                    self.expand(callee, &callee_prefix, depth - 1, Some(&continuation));
+                    self.expanded += 1;
+                } else if let Some(entry) = ancestor {
+                    // a recursive call at the depth limit: an edge back to the enclosing instance
+                    current.instructions.push(Cow::Owned(format!("  ; recursion cut at the depth limit, re-enters %{entry}")));
+                    current.successors = vec![percent(entry), percent(continuation.clone())];
+                    self.blocks.push(std::mem::replace(&mut current, empty_block(continuation)));
+                    self.recursion_cut += 1;
+                }
+            }
+            current.successors = match (block.terminator(), return_to) {
+                (Some(Terminator::Ret), Some(return_to)) => vec![percent(return_to.to_string())],
+                _ => cfg.succs[index].iter().map(|&s| percent(name(&function.blocks[s].name))).collect(),
+            };
+            self.blocks.push(current);
+        }
+        self.stack.pop();
+    }
+}
+
+/// The CFG of `root` with each call to a function defined in the module replaced by an edge into a
+/// copy of the callee's blocks, named `<callee>.<n>.<block>`, whose returns branch to the rest of
+/// the calling block, `<block>.ret<k>`. Callees are expanded `depth` levels deep, so recursion
+/// stops there; a recursive call at the limit gets an edge back to the entry of the enclosing
+/// instance of its callee. `invoke`s and callees using exception handling are not expanded.
+pub fn stitch_calls<'a>(module: &Module<'a>, root: &str, depth: usize) -> Result<Stitched<'a>, String> {
+    let function = module.function(root).ok_or_else(|| format!("no function @{root}"))?;
+    let mut stitcher = Stitcher {
+        module,
+        blocks: vec![],
+        stack: vec![],
+        instances: 0,
+        expanded: 0,
+        recursion_cut: 0,
+    };
+    stitcher.expand(function, "", depth, None);
+    let mut blocks = stitcher.blocks;
+    // the `; preds =` comments no longer hold, derive the predecessors from the successors
+    let predecessors: Vec<Vec<_>> = blocks.iter().map(|block| blocks.iter()
+        .filter(|b| b.successors.iter().any(|s| s.strip_prefix('%') == Some(block.name.as_ref())))
+        .map(|b| Cow::Owned(format!("%{}", b.name)))
+        .collect()).collect();
+    for (block, preds) in blocks.iter_mut().zip(predecessors) {
+        block.predecessors = preds;
+    }
+    Ok(Stitched {
+        function: Function { blocks, raw: vec![], ..function.clone() },
+        expanded: stitcher.expanded,
+        recursion_cut: stitcher.recursion_cut,
+    })
+}
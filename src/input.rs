@@ -0,0 +1,73 @@
+//! Opening of input files, decompressing gzip and xz archives on the fly so that the
+//! `BufRead`-based parsers see plain IR text.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Detects the compression of a file from its first bytes, falling back to its extension.
+pub fn detect_compression(path: &Path, head: &[u8]) -> Compression {
+    if head.starts_with(GZIP_MAGIC) {
+        return Compression::Gzip;
+    }
+    if head.starts_with(XZ_MAGIC) {
+        return Compression::Xz;
+    }
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") => Compression::Gzip,
+        Some("xz") => Compression::Xz,
+        _ => Compression::None,
+    }
+}
+
+/// The name of `path` without a trailing `.gz` or `.xz`.
+pub fn uncompressed_name(path: &str) -> &str {
+    path.strip_suffix(".gz").or_else(|| path.strip_suffix(".xz")).unwrap_or(path)
+}
+
+/// Adds the archive name to the errors of a decompressing reader, which otherwise only say
+/// "corrupt deflate stream" or similar.
+#[cfg_attr(not(any(feature = "gzip", feature = "xz")), allow(dead_code))]
+struct WithContext<R> {
+    inner: R,
+    path: String,
+}
+
+impl<R: Read> Read for WithContext<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).map_err(|err| io::Error::new(err.kind(), format!("cannot decompress {}: {err}", self.path)))
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "xz"))]
+fn with_context(inner: Box<dyn Read>, path: &Path) -> Box<dyn BufRead> {
+    Box::new(BufReader::new(WithContext { inner, path: path.display().to_string() }))
+}
+
+/// Opens `path` for reading, transparently decompressing `.gz` and `.xz` files (detected by
+/// magic bytes or extension) when the matching cargo feature is enabled.
+pub fn open_input(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = detect_compression(path, reader.fill_buf()?);
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(with_context(Box::new(flate2::bufread::MultiGzDecoder::new(reader)), path)),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(with_context(Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)), path)),
+        #[allow(unreachable_patterns)]
+        unsupported => Err(io::Error::new(io::ErrorKind::Unsupported,
+            format!("{} is {unsupported:?}-compressed, but this build lacks the matching cargo feature", path.display()))),
+    }
+}
@@ -0,0 +1,256 @@
+use std::borrow::Cow;
+
+/// Text borrowed from the parsed input when possible, owned otherwise.
+pub type Text<'a> = Cow<'a, str>;
+
+pub type BlockName<'a> = Text<'a>;
+
+#[derive(Clone, Debug)]
+pub struct BasicBlock<'a> {
+    pub name: BlockName<'a>,  // an unlabeled entry block is named by its value number, e.g. "0"
+    pub instructions: Vec<Text<'a>>,
+    pub predecessors: Vec<BlockName<'a>>,
+    pub successors: Vec<BlockName<'a>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Function<'a> {
+    pub name: Text<'a>,
+    pub define: Text<'a>, // the define line
+    pub attrs: Option<Text<'a>>, // from the `; Function Attrs:` comment right before the define line
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub module: usize, // index of the owning module when several inputs are parsed
+    pub raw: Vec<Text<'a>>, // verbatim lines, define through closing brace, when requested from the parser
+}
+
+/// A global variable (or alias) definition: `@name = ...`.
+#[derive(Clone, Debug)]
+pub struct Global<'a> {
+    pub name: Text<'a>,
+    pub line: Text<'a>,
+}
+
+/// An external function declaration: `declare ... @name(...)`.
+#[derive(Clone, Debug)]
+pub struct Declaration<'a> {
+    pub name: Text<'a>,
+    pub line: Text<'a>,
+}
+
+/// A parsed LLVM IR module.
+#[derive(Clone, Debug, Default)]
+pub struct Module<'a> {
+    pub target_triple: Option<Text<'a>>,
+    /// The `source_filename` the module was compiled from, e.g. `lib/cfg.c`.
+    pub source_filename: Option<Text<'a>>,
+    pub globals: Vec<Global<'a>>,
+    pub declarations: Vec<Declaration<'a>>,
+    pub functions: Vec<Function<'a>>,
+}
+
+impl<'a> Module<'a> {
+    /// Records `index` as the owning module of every function, for multi-module inputs.
+    pub fn set_index(&mut self, index: usize) {
+        self.functions.iter_mut().for_each(|f| f.module = index);
+    }
+
+    /// Appends the contents of `other`, keeping this module's target triple and source file name if it has them.
+    pub fn merge(&mut self, other: Module<'a>) {
+        self.target_triple = self.target_triple.take().or(other.target_triple);
+        self.source_filename = self.source_filename.take().or(other.source_filename);
+        self.globals.extend(other.globals);
+        self.declarations.extend(other.declarations);
+        self.functions.extend(other.functions);
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function<'a>> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    /// Drops everything but the functions.
+    pub fn into_functions(self) -> Vec<Function<'a>> {
+        self.functions
+    }
+}
+
+/// Every LLVM instruction opcode.
+pub const OPCODES: &[&str] = &[
+    "ret", "br", "switch", "indirectbr", "invoke", "callbr", "resume", "catchswitch", "catchret", "cleanupret",
+    "unreachable", "fneg", "add", "fadd", "sub", "fsub", "mul", "fmul", "udiv", "sdiv", "fdiv", "urem", "srem",
+    "frem", "shl", "lshr", "ashr", "and", "or", "xor", "extractelement", "insertelement", "shufflevector",
+    "extractvalue", "insertvalue", "alloca", "load", "store", "fence", "cmpxchg", "atomicrmw", "getelementptr",
+    "trunc", "zext", "sext", "fptrunc", "fpext", "fptoui", "fptosi", "uitofp", "sitofp", "ptrtoint", "inttoptr",
+    "bitcast", "addrspacecast", "icmp", "fcmp", "phi", "select", "freeze", "call", "va_arg", "landingpad",
+    "catchpad", "cleanuppad",
+];
+
+/// The opcode of an instruction line, or `None` for lines that are not instructions
+/// (comments, `switch` case lines, the closing `]` of a switch).
+pub fn opcode(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let line = match line.split_once(" = ") {
+        Some((lhs, rhs)) if lhs.starts_with('%') && !lhs.contains(' ') => rhs,
+        _ => line,
+    };
+    let mut tokens = line.split_whitespace()
+        .skip_while(|t| matches!(*t, "tail" | "musttail" | "notail"));
+    tokens.next().filter(|t| OPCODES.contains(t))
+}
+
+/// The parameters of a define or declare line, trimmed, without the `...` of variadic functions.
+pub fn parameters(define: &str) -> Vec<&str> {
+    let Some(start) = define.find('(') else { return vec![] };
+    let mut depth = 0;
+    let mut params = vec![];
+    let mut from = start + 1;
+    for (at, c) in define[start + 1..].char_indices().map(|(i, c)| (start + 1 + i, c)) {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' if depth == 0 => {
+                params.push(&define[from..at]);
+                break;
+            }
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(&define[from..at]);
+                from = at + 1;
+            }
+            _ => {}
+        }
+    }
+    params.into_iter().map(str::trim).filter(|p| !p.is_empty() && *p != "...").collect()
+}
+
+/// The kind of instruction ending a basic block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Terminator {
+    Ret,
+    Br,
+    CondBr,
+    Switch,
+    IndirectBr,
+    Invoke,
+    CallBr,
+    Resume,
+    CatchSwitch,
+    CatchRet,
+    CleanupRet,
+    Unreachable,
+}
+
+impl Terminator {
+    pub const ALL: [Terminator; 12] = [
+        Terminator::Ret, Terminator::Br, Terminator::CondBr, Terminator::Switch, Terminator::IndirectBr,
+        Terminator::Invoke, Terminator::CallBr, Terminator::Resume, Terminator::CatchSwitch,
+        Terminator::CatchRet, Terminator::CleanupRet, Terminator::Unreachable,
+    ];
+
+    /// Classifies an instruction line, returning `None` for non-terminators.
+    pub fn classify(inst: &str) -> Option<Terminator> {
+        Some(match opcode(inst)? {
+            "ret" => Terminator::Ret,
+            "br" if inst.contains(',') => Terminator::CondBr,
+            "br" => Terminator::Br,
+            "switch" => Terminator::Switch,
+            "indirectbr" => Terminator::IndirectBr,
+            "invoke" => Terminator::Invoke,
+            "callbr" => Terminator::CallBr,
+            "resume" => Terminator::Resume,
+            "catchswitch" => Terminator::CatchSwitch,
+            "catchret" => Terminator::CatchRet,
+            "cleanupret" => Terminator::CleanupRet,
+            "unreachable" => Terminator::Unreachable,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Terminator::Ret => "ret",
+            Terminator::Br => "br",
+            Terminator::CondBr => "condbr",
+            Terminator::Switch => "switch",
+            Terminator::IndirectBr => "indirectbr",
+            Terminator::Invoke => "invoke",
+            Terminator::CallBr => "callbr",
+            Terminator::Resume => "resume",
+            Terminator::CatchSwitch => "catchswitch",
+            Terminator::CatchRet => "catchret",
+            Terminator::CleanupRet => "cleanupret",
+            Terminator::Unreachable => "unreachable",
+        }
+    }
+}
+
+impl BasicBlock<'_> {
+    /// The last instruction of the block, skipping the case lines of a multi-line `switch`.
+    pub fn last_instruction(&self) -> Option<&str> {
+        self.instructions.iter().rev().map(|i| i.as_ref()).find(|i| opcode(i).is_some())
+    }
+
+    /// The terminator kind of the block, `None` when its last instruction is not a terminator.
+    pub fn terminator(&self) -> Option<Terminator> {
+        self.last_instruction().and_then(Terminator::classify)
+    }
+}
+
+impl Function<'_> {
+    /// Index of the block called `name`, with or without the leading `%`.
+    pub fn block_index(&self, name: &str) -> Option<usize> {
+        let name = name.strip_prefix('%').unwrap_or(name);
+        self.blocks.iter().position(|b| b.name == name)
+    }
+
+    /// Index of the entry block: the block named `entry_name` when given and present,
+    /// otherwise the first block.
+    pub fn entry_index(&self, entry_name: Option<&str>) -> usize {
+        entry_name.and_then(|name| self.block_index(name)).unwrap_or(0)
+    }
+
+    /// The linkage keyword of the define line, `external` when there is none.
+    pub fn linkage(&self) -> &'static str {
+        const LINKAGES: &[&str] = &[
+            "private", "internal", "available_externally", "linkonce", "weak", "common", "appending",
+            "extern_weak", "linkonce_odr", "weak_odr", "external",
+        ];
+        self.define.split_whitespace().skip(1).take_while(|t| !t.starts_with('@'))
+            .find_map(|t| LINKAGES.iter().find(|l| **l == t).copied())
+            .unwrap_or("external")
+    }
+
+    /// The names of the arguments, without `%`; unnamed ones get their value number.
+    pub fn arguments(&self) -> Vec<String> {
+        let mut unnamed = 0..;
+        parameters(&self.define).iter().map(|param| {
+            let tokens: Vec<&str> = param.split_whitespace().collect();
+            match tokens.last().and_then(|last| last.strip_prefix('%')) {
+                Some(name) if tokens.len() > 1 => name.to_string(),
+                _ => unnamed.next().unwrap().to_string(),
+            }
+        }).collect()
+    }
+
+    /// Whether any block branches to the block at `index`.
+    pub fn has_predecessors(&self, index: usize) -> bool {
+        let block = &self.blocks[index];
+        !block.predecessors.is_empty()
+            || self.blocks.iter().any(|b| b.successors.iter().any(|s| s.strip_prefix('%') == Some(&block.name)))
+    }
+}
+
+impl std::fmt::Display for Function<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Function: {}", self.name)?;
+        if let Some(attrs) = &self.attrs {
+            writeln!(f, "\tAttrs: {attrs}")?;
+        }
+        for block in &self.blocks {
+            writeln!(f, "\tBlock: {}\t; preds = {}", block.name, block.predecessors.join(", "))?;
+            for instr in &block.instructions {
+                writeln!(f, "\t\t  {}", instr)?;
+            }
+            writeln!(f, "\t; successors = {}", block.successors.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+//! Shared IR model, parser and renderers used by the learning LLVM tools.
+
+pub mod analysis;
+pub mod anonymize;
+pub mod callgraph;
+pub mod canvas;
+pub mod cfg;
+pub mod condense;
+pub mod dedupe;
+pub mod diff;
+pub mod dot;
+pub mod dom;
+pub mod dot_cfg;
+pub mod graphml;
+pub mod inline;
+pub mod input;
+pub mod ir;
+pub mod manifest;
+pub mod metrics;
+pub mod objdump;
+pub mod pass_dump;
+pub mod pass_report;
+pub mod parser;
+pub mod patterns;
+pub mod progress;
+pub mod region;
+pub mod remarks;
+pub mod render;
+pub mod split_output;
+pub mod suggest;
+pub mod text_diff;
+pub mod verify;
@@ -0,0 +1,95 @@
+//! Manifest of the files written by a run, for build systems that must declare their outputs
+//! and prune the ones a previous run left behind.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+
+/// One generated file.
+#[derive(Clone, Debug)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    /// The input the file was generated from.
+    pub source: String,
+    /// The function the file is about, when it is about a single one.
+    pub function: Option<String>,
+    /// `ll`, `md`, `html`, ...
+    pub format: String,
+}
+
+/// A stable 64-bit FNV-1a hash of `bytes`, as 16 hex digits.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |h, &b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("{hash:016x}")
+}
+
+/// Builds the manifest document, reading each file back for its hash and size.
+pub fn manifest_json(entries: &[ManifestEntry], options: Value) -> io::Result<Value> {
+    let mut files = vec![];
+    for entry in entries {
+        let bytes = fs::read(&entry.path)?;
+        files.push(json!({
+            "path": entry.path.display().to_string(),
+            "source": entry.source,
+            "function": entry.function,
+            "format": entry.format,
+            "hash": content_hash(&bytes),
+            "size": bytes.len(),
+        }));
+    }
+    Ok(json!({
+        "tool": env!("CARGO_PKG_NAME"),
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "options": options,
+        "files": files,
+    }))
+}
+
+/// Writes `document` to `path` through a temporary file renamed into place, so that readers
+/// never see a partial manifest.
+pub fn write_manifest(path: &Path, document: &Value) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(document)? + "\n")?;
+    fs::rename(&temporary, path)
+}
+
+/// The paths listed by the manifest at `path`; empty when there is none yet.
+pub fn manifest_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    let document: Value = serde_json::from_str(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display())))?;
+    Ok(document["files"].as_array().into_iter().flatten()
+        .filter_map(|file| file["path"].as_str())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Deletes the files of the previous manifest at `path` that are not in `entries`, returning them.
+pub fn prune_stale(path: &Path, entries: &[ManifestEntry]) -> io::Result<Vec<PathBuf>> {
+    let mut pruned = vec![];
+    for old in manifest_paths(path)? {
+        if entries.iter().any(|e| e.path == old) {
+            continue;
+        }
+        match fs::remove_file(&old) {
+            Ok(()) => pruned.push(old),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(pruned)
+}
+
+/// Writes the manifest of `entries` to `path`, first deleting the files of the previous manifest
+/// that are no longer generated when `prune` is set. Returns the deleted files.
+pub fn update_manifest(path: &Path, entries: &[ManifestEntry], options: Value, prune: bool) -> io::Result<Vec<PathBuf>> {
+    let pruned = if prune { prune_stale(path, entries)? } else { vec![] };
+    write_manifest(path, &manifest_json(entries, options)?)?;
+    Ok(pruned)
+}
@@ -0,0 +1,172 @@
+//! Versioned JSON metrics documents for tracking CFG size and complexity over time.
+
+use serde_json::{json, Value};
+use crate::analysis::{FunctionMetrics, ModuleMetrics};
+
+/// Bumped whenever a field is renamed or removed; new fields may be added without a bump.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Builds the metrics document for the given functions.
+pub fn metrics_json(input: &str, functions: &[(&str, FunctionMetrics)]) -> Value {
+    let mut totals = ModuleMetrics::default();
+    functions.iter().for_each(|(_, m)| totals.add(m));
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "tool": env!("CARGO_PKG_NAME"),
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "input": input,
+        "functions": functions.iter().map(|(name, m)| json!({
+            "name": name,
+            "blocks": m.blocks,
+            "edges": m.edges,
+            "instructions": m.instructions,
+            "loops": m.loops,
+            "complexity": m.complexity,
+            "max_fan_in": m.max_fan_in,
+            "max_fan_out": m.max_fan_out,
+            "weighted_instructions": m.weighted_instructions,
+        })).collect::<Vec<_>>(),
+        "totals": {
+            "functions": totals.functions,
+            "blocks": totals.blocks,
+            "edges": totals.edges,
+            "instructions": totals.instructions,
+            "total_complexity": totals.total_complexity,
+            "max_complexity": totals.max_complexity,
+        },
+    })
+}
+
+/// Writes one CSV row of metrics per function, after a header row.
+pub fn write_metrics_csv(output: &mut dyn std::io::Write, functions: &[(&str, FunctionMetrics)]) -> std::io::Result<()> {
+    writeln!(output, "function,blocks,edges,instructions,loops,complexity,unreachable_blocks,max_fan_in,max_fan_out,weighted_instructions")?;
+    for (name, m) in functions {
+        let name = if name.contains([',', '"']) { format!("\"{}\"", name.replace('"', "\"\"")) } else { name.to_string() };
+        writeln!(output, "{name},{},{},{},{},{},{},{},{},{}", m.blocks, m.edges, m.instructions, m.loops, m.complexity,
+            m.unreachable_blocks, m.max_fan_in, m.max_fan_out, m.weighted_instructions)?;
+    }
+    Ok(())
+}
+
+/// How much growth `compare_metrics` tolerates before reporting a regression.
+#[derive(Clone, Copy, Debug)]
+pub struct Thresholds {
+    /// Allowed increase of the cyclomatic complexity, in absolute terms.
+    pub complexity: u64,
+    /// Allowed growth of the instruction count, in percent.
+    pub size_percent: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    pub function: String,
+    pub metric: &'static str,
+    pub old: u64,
+    pub new: u64,
+}
+
+fn check_schema(document: &Value) -> Result<(), String> {
+    match document["schema_version"].as_u64() {
+        Some(SCHEMA_VERSION) => Ok(()),
+        Some(version) => Err(format!("unsupported metrics schema version {version} (expected {SCHEMA_VERSION})")),
+        None => Err("not a metrics document: missing schema_version".to_string()),
+    }
+}
+
+/// Lists the functions present in both documents whose complexity or size grew beyond `thresholds`.
+pub fn compare_metrics(old: &Value, new: &Value, thresholds: Thresholds) -> Result<Vec<Regression>, String> {
+    check_schema(old)?;
+    check_schema(new)?;
+    let empty = vec![];
+    let old_functions = old["functions"].as_array().unwrap_or(&empty);
+
+    let mut regressions = vec![];
+    for function in new["functions"].as_array().unwrap_or(&empty) {
+        let name = function["name"].as_str().unwrap_or_default();
+        let Some(previous) = old_functions.iter().find(|f| f["name"] == name) else { continue };
+        let metric = |doc: &Value, key: &str| doc[key].as_u64().unwrap_or(0);
+
+        let (old_complexity, new_complexity) = (metric(previous, "complexity"), metric(function, "complexity"));
+        if new_complexity > old_complexity + thresholds.complexity {
+            regressions.push(Regression { function: name.to_string(), metric: "complexity", old: old_complexity, new: new_complexity });
+        }
+        let (old_size, new_size) = (metric(previous, "instructions"), metric(function, "instructions"));
+        if new_size as f64 > old_size as f64 * (1.0 + thresholds.size_percent / 100.0) {
+            regressions.push(Regression { function: name.to_string(), metric: "instructions", old: old_size, new: new_size });
+        }
+    }
+    Ok(regressions)
+}
+
+/// The metrics of a function in two builds; `old` or `new` is `None` for an added or removed function.
+#[derive(Clone, Debug)]
+pub struct MetricsDelta {
+    pub function: String,
+    pub old: Option<FunctionMetrics>,
+    pub new: Option<FunctionMetrics>,
+}
+
+impl MetricsDelta {
+    /// The change of one metric, e.g. `delta(|m| m.instructions)`; a missing side counts as 0.
+    pub fn delta(&self, metric: impl Fn(&FunctionMetrics) -> usize) -> i64 {
+        let value = |m: &Option<FunctionMetrics>| m.as_ref().map_or(0, &metric) as i64;
+        value(&self.new) - value(&self.old)
+    }
+
+    /// `added`, `removed`, `changed` or `unchanged`, judged on the compared metrics.
+    pub fn status(&self) -> &'static str {
+        match (&self.old, &self.new) {
+            (None, _) => "added",
+            (_, None) => "removed",
+            _ if DELTA_METRICS.iter().all(|(_, metric)| self.delta(metric) == 0) => "unchanged",
+            _ => "changed",
+        }
+    }
+}
+
+/// Reads one figure out of [`FunctionMetrics`].
+pub type Metric = fn(&FunctionMetrics) -> usize;
+
+/// The metrics [`metrics_deltas`] reports on, by name.
+pub const DELTA_METRICS: [(&str, Metric); 4] = [
+    ("blocks", |m| m.blocks),
+    ("instructions", |m| m.instructions),
+    ("complexity", |m| m.complexity),
+    ("edges", |m| m.edges),
+];
+
+/// Matches the functions of two builds by name, largest absolute instruction change first and
+/// then by name, functions in one build only included.
+pub fn metrics_deltas(old: &[(&str, FunctionMetrics)], new: &[(&str, FunctionMetrics)]) -> Vec<MetricsDelta> {
+    let mut deltas: Vec<MetricsDelta> = old.iter().map(|(name, m)| MetricsDelta {
+        function: name.to_string(),
+        old: Some(m.clone()),
+        new: new.iter().find(|(n, _)| n == name).map(|(_, m)| m.clone()),
+    }).collect();
+    for (name, m) in new.iter().filter(|(name, _)| !old.iter().any(|(n, _)| n == name)) {
+        deltas.push(MetricsDelta { function: name.to_string(), old: None, new: Some(m.clone()) });
+    }
+    deltas.sort_by(|a, b| b.delta(|m| m.instructions).abs().cmp(&a.delta(|m| m.instructions).abs()).then_with(|| a.function.cmp(&b.function)));
+    deltas
+}
+
+/// The machine-readable form of [`metrics_deltas`]: per function its status, old and new values
+/// and deltas of each of [`DELTA_METRICS`].
+pub fn deltas_json(old_input: &str, new_input: &str, deltas: &[MetricsDelta]) -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "old": old_input,
+        "new": new_input,
+        "functions": deltas.iter().map(|d| {
+            let mut entry = json!({ "name": d.function, "status": d.status() });
+            for (name, metric) in DELTA_METRICS {
+                entry[name] = json!({
+                    "old": d.old.as_ref().map(metric),
+                    "new": d.new.as_ref().map(metric),
+                    "delta": d.delta(metric),
+                });
+            }
+            entry
+        }).collect::<Vec<_>>(),
+    })
+}
@@ -0,0 +1,187 @@
+//! Machine-level CFGs from `llvm-objdump -d` disassembly, for comparison with the IR ones.
+//!
+//! Blocks start at the first instruction, at every direct branch target and after every branch
+//! or return. Edges are the direct branch targets plus the fall-through; calls are kept as
+//! instructions, annotated with their relocation target when `-r` was given. x86-64 and AArch64
+//! branch mnemonics are recognized.
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use regex::Regex;
+use crate::ir::{BasicBlock, Function, Module};
+
+/// How an instruction affects control flow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    /// Continues with the next instruction (calls included).
+    Next,
+    /// Always jumps to its target.
+    Jump,
+    /// Jumps to its target or falls through.
+    CondJump,
+    /// Jumps through a register or memory: the successors are unknown.
+    IndirectJump,
+    /// Leaves the function.
+    Return,
+    /// Traps (`ud2`, `brk`, `udf`).
+    Trap,
+}
+
+/// Classifies an x86-64 or AArch64 instruction by mnemonic and operands.
+pub fn classify(mnemonic: &str, operands: &str) -> Flow {
+    const X86_CONDITIONS: &[&str] = &[
+        "a", "ae", "b", "be", "c", "e", "g", "ge", "l", "le", "na", "nae", "nb", "nbe", "nc", "ne", "ng", "nge",
+        "nl", "nle", "no", "np", "ns", "nz", "o", "p", "pe", "po", "s", "z", "cxz", "ecxz", "rcxz",
+    ];
+    match mnemonic {
+        "ret" | "retq" | "retl" | "retaa" | "retab" => Flow::Return,
+        "jmp" | "jmpq" if operands.trim_start().starts_with('*') => Flow::IndirectJump,
+        "jmp" | "jmpq" | "b" => Flow::Jump,
+        "br" | "braa" | "brab" | "braaz" | "brabz" => Flow::IndirectJump,
+        "cbz" | "cbnz" | "tbz" | "tbnz" => Flow::CondJump,
+        "ud2" | "brk" | "udf" => Flow::Trap,
+        _ if mnemonic.starts_with("b.") => Flow::CondJump,
+        _ if mnemonic.strip_prefix('j').is_some_and(|cc| X86_CONDITIONS.contains(&cc)) => Flow::CondJump,
+        _ => Flow::Next,
+    }
+}
+
+fn is_call(mnemonic: &str) -> bool {
+    matches!(mnemonic, "call" | "callq" | "bl" | "blr" | "blraa" | "blrab")
+}
+
+fn is_padding(mnemonic: &str) -> bool {
+    mnemonic.starts_with("nop") || mnemonic == "int3"
+}
+
+struct Instruction {
+    address: u64,
+    mnemonic: String,
+    text: String,
+    /// The `<Ln>` label or absolute address the instruction branches or calls to.
+    target: Option<Target>,
+    /// The symbol of the relocation applied to the instruction.
+    relocation: Option<String>,
+}
+
+enum Target {
+    Label(String),
+    Address(u64),
+}
+
+/// The functions of `llvm-objdump -d` output, optionally with `-r` and `--symbolize-operands`,
+/// with or without the raw instruction bytes.
+pub fn parse_objdump(text: &str) -> Module<'static> {
+    let symbol_re = Regex::new(r"^([0-9a-fA-F]+) <(.+)>:\s*$").unwrap();
+    let label_re = Regex::new(r"^<(L\d+)>:\s*$").unwrap();
+    let instruction_re = Regex::new(r"^\s*([0-9a-fA-F]+):\s([^\t]*)\t(.*)$").unwrap();
+    let relocation_re = Regex::new(r"^\s+[0-9a-fA-F]+:\s+R_\w+\s+(\S+?)(?:[-+]0x[0-9a-fA-F]+)?\s*$").unwrap();
+    let label_target_re = Regex::new(r"<(L\d+)>").unwrap();
+    let address_target_re = Regex::new(r"(?:0x)?([0-9a-fA-F]+)\s+<[^>]+>").unwrap();
+
+    let mut functions: Vec<(String, Vec<Instruction>, HashMap<String, u64>)> = vec![];
+    let mut pending_labels: Vec<String> = vec![];
+    for line in text.lines() {
+        if let Some(caps) = symbol_re.captures(line) {
+            functions.push((caps[2].to_string(), vec![], HashMap::new()));
+            pending_labels.clear();
+        } else if let Some(caps) = label_re.captures(line) {
+            pending_labels.push(caps[1].to_string());
+        } else if let Some(caps) = relocation_re.captures(line) {
+            if let Some(last) = functions.last_mut().and_then(|f| f.1.last_mut()) {
+                last.relocation = Some(caps[1].to_string());
+            }
+        } else if let Some(caps) = instruction_re.captures(line) {
+            let Some((_, instructions, labels)) = functions.last_mut() else { continue };
+            let Ok(address) = u64::from_str_radix(&caps[1], 16) else { continue };
+            let text = caps[3].trim().replace('\t', " ");
+            let (mnemonic, operands) = text.split_once(' ').unwrap_or((&text, ""));
+            for label in pending_labels.drain(..) {
+                labels.insert(label, address);
+            }
+            let target = match (label_target_re.captures(operands), address_target_re.captures(operands)) {
+                (Some(label), _) => Some(Target::Label(label[1].to_string())),
+                (None, Some(address)) => u64::from_str_radix(&address[1], 16).ok().map(Target::Address),
+                (None, None) => None,
+            };
+            instructions.push(Instruction { address, mnemonic: mnemonic.to_string(), text: text.clone(), target, relocation: None });
+        }
+    }
+
+    let mut module = Module::default();
+    for (name, instructions, labels) in functions {
+        module.functions.push(build_function(name, instructions, &labels));
+    }
+    module
+}
+
+fn build_function(name: String, mut instructions: Vec<Instruction>, labels: &HashMap<String, u64>) -> Function<'static> {
+    // alignment padding after the last instruction that leaves the function
+    while instructions.last().is_some_and(|i| is_padding(&i.mnemonic)) {
+        instructions.pop();
+    }
+    let addresses: BTreeSet<u64> = instructions.iter().map(|i| i.address).collect();
+    let target_of = |inst: &Instruction| match &inst.target {
+        Some(Target::Label(label)) => labels.get(label).copied(),
+        Some(Target::Address(address)) => Some(*address),
+        None => None,
+    }.filter(|address| addresses.contains(address));
+    let flow = |inst: &Instruction| classify(&inst.mnemonic, inst.text.split_once(' ').map_or("", |(_, o)| o));
+
+    let mut leaders = BTreeSet::new();
+    for (index, inst) in instructions.iter().enumerate() {
+        if index == 0 {
+            leaders.insert(inst.address);
+        }
+        if flow(inst) != Flow::Next {
+            if let Some(target) = target_of(inst) {
+                leaders.insert(target);
+            }
+            if let Some(next) = instructions.get(index + 1) {
+                leaders.insert(next.address);
+            }
+        }
+    }
+    let label_at: HashMap<u64, &str> = labels.iter().map(|(label, &address)| (address, label.as_str())).collect();
+    let block_name = |address: u64| label_at.get(&address).map_or_else(|| format!("{address:#x}"), |l| l.to_string());
+
+    let mut blocks: Vec<BasicBlock<'static>> = vec![];
+    for (index, inst) in instructions.iter().enumerate() {
+        if leaders.contains(&inst.address) {
+            blocks.push(BasicBlock { name: Cow::Owned(block_name(inst.address)), instructions: vec![], predecessors: vec![], successors: vec![] });
+        }
+        let block = blocks.last_mut().expect("the first instruction is a leader");
+        let mut line = format!("  {:x}: {}", inst.address, inst.text);
+        match (&inst.relocation, is_call(&inst.mnemonic)) {
+            (Some(symbol), true) => line.push_str(&format!(" ; call {symbol}")),
+            (Some(symbol), false) if flow(inst) == Flow::Jump => line.push_str(&format!(" ; tail call {symbol}")),
+            _ => {}
+        }
+        block.instructions.push(Cow::Owned(line));
+
+        let next = instructions.get(index + 1).map(|n| n.address);
+        let fallthrough = next.filter(|n| leaders.contains(n));
+        let mut successors = vec![];
+        match flow(inst) {
+            Flow::Next => successors.extend(fallthrough),
+            Flow::Jump => successors.extend(target_of(inst)),
+            Flow::CondJump => successors.extend(target_of(inst).into_iter().chain(next)),
+            Flow::IndirectJump => block.instructions.push(Cow::Borrowed("  ; dead end: indirect jump, successors unknown")),
+            Flow::Return | Flow::Trap => {}
+        }
+        for successor in successors {
+            let name = Cow::Owned(format!("%{}", block_name(successor)));
+            if !block.successors.contains(&name) {
+                block.successors.push(name);
+            }
+        }
+    }
+    Function {
+        define: Cow::Owned(format!("define @{name}() ; from llvm-objdump")),
+        name: Cow::Owned(name),
+        attrs: None,
+        blocks,
+        module: 0,
+        raw: vec![],
+    }
+}
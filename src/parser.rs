@@ -0,0 +1,354 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::sync::LazyLock;
+use regex::{CaptureLocations, Regex};
+use crate::ir::{parameters, BasicBlock, Declaration, Function, Global, Module, Terminator, Text};
+
+/// Parses an LLVM IR file from a reader. Every line is read into an owned `String`.
+pub fn parse_ll_file<R: BufRead>(reader: &mut R) -> io::Result<Module<'static>> {
+    stream_ll_file(reader).into_module()
+}
+
+/// Parses LLVM IR that is already in memory. The names and instructions of the
+/// returned module borrow from `input` instead of being copied.
+pub fn parse_ll_str(input: &str) -> Module<'_> {
+    stream_ll_str(input).into_module().expect("reading from a str never fails")
+}
+
+/// Returns the part of `line` in `range`, borrowing when `line` is borrowed.
+fn sub_text<'a>(line: &Text<'a>, range: Range<usize>) -> Text<'a> {
+    match line {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[range]),
+        Cow::Owned(s) => Cow::Owned(s[range].to_string()),
+    }
+}
+
+/// The range of capture group `group` of `re` in `line`. `Regex::captures` allocates the captures
+/// of every line it looks at, so the lines seen once per function or more reuse `locations` instead.
+fn capture_range(re: &Regex, locations: &mut CaptureLocations, line: &str, group: usize) -> Option<Range<usize>> {
+    re.captures_read(locations, line)?;
+    locations.get(group).map(|(start, end)| start..end)
+}
+
+/// Same as `sub_text`, for a `part` that is known to be a sub-slice of `line`.
+fn slice_text<'a>(line: &Text<'a>, part: &str) -> Text<'a> {
+    match line {
+        Cow::Borrowed(s) => {
+            let start = part.as_ptr() as usize - s.as_ptr() as usize;
+            Cow::Borrowed(&s[start..start + part.len()])
+        }
+        Cow::Owned(_) => Cow::Owned(part.to_string()),
+    }
+}
+
+// The regexes are compiled once per process: modules have thousands of functions.
+
+/// A `define` line the parser understands; the first group is the function name.
+static DEFINE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^define\s+.*@([a-zA-Z0-9_\.]+)\s*\(.*\)\s*(.*)\s*\{$").unwrap());
+
+/// A block label line, `name:` optionally followed by the `; preds = ...` comment in group 3.
+static BLOCK_LABEL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([0-9a-zA-Z_\.]+):(\s*;\s*preds\s*=\s*(.*))?$").unwrap());
+
+/// A branch target, `label %name`; the group is the name with its `%`.
+static LABEL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"label\s+(%[-\w.$]+)").unwrap());
+
+static DECLARE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^declare\s+.*@([a-zA-Z0-9_\.]+)\s*\(").unwrap());
+static GLOBAL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^@("[^"]*"|[a-zA-Z0-9_\.\$-]+)\s*="#).unwrap());
+static TRIPLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^target\s+triple\s*=\s*"(.*)""#).unwrap());
+static SOURCE_FILENAME_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^source_filename\s*=\s*"(.*)""#).unwrap());
+
+/// Lines read from a `BufRead`, each owned.
+pub type OwnedLines<R> = std::iter::Map<io::Lines<R>, fn(io::Result<String>) -> io::Result<Text<'static>>>;
+
+/// Lines borrowed from an in-memory buffer.
+pub type BorrowedLines<'a> = std::iter::Map<std::str::Lines<'a>, fn(&'a str) -> io::Result<Text<'a>>>;
+
+/// Streaming mode of the parser: yields functions one at a time as their closing brace is read,
+/// so only one function is held in memory. Module-level lines (triple, source file name, globals, declarations)
+/// seen so far are collected in `module`, whose `functions` stay empty.
+pub struct FunctionStream<'a, I> {
+    lines: I,
+    keep_raw: bool,
+    pub module: Module<'a>,
+    attrs: Option<Text<'a>>, // the `; Function Attrs:` comment on the previous line
+    define_locations: CaptureLocations,
+    label_locations: CaptureLocations,
+}
+
+/// Streams the functions of an LLVM IR file read from `reader`.
+pub fn stream_ll_file<R: BufRead>(reader: R) -> FunctionStream<'static, OwnedLines<R>> {
+    FunctionStream::new(reader.lines().map(|line| line.map(Cow::Owned)))
+}
+
+/// Streams the functions of in-memory LLVM IR, borrowing from `input`.
+pub fn stream_ll_str(input: &str) -> FunctionStream<'_, BorrowedLines<'_>> {
+    FunctionStream::new(input.lines().map(|line| Ok(Cow::Borrowed(line))))
+}
+
+impl<'a, I> FunctionStream<'a, I>
+    where I: Iterator<Item = io::Result<Text<'a>>>
+{
+    pub fn new(lines: I) -> Self {
+        FunctionStream {
+            lines,
+            keep_raw: false,
+            module: Module::default(),
+            attrs: None,
+            define_locations: DEFINE_RE.capture_locations(),
+            label_locations: BLOCK_LABEL_RE.capture_locations(),
+        }
+    }
+
+    /// Also keep the verbatim lines of every function, from `define` to the closing brace, in `Function::raw`.
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.keep_raw = keep_raw;
+        self
+    }
+
+    /// Reads the remaining input and returns the whole module.
+    pub fn into_module(mut self) -> io::Result<Module<'a>> {
+        let functions = self.by_ref().collect::<io::Result<Vec<_>>>()?;
+        self.module.functions = functions;
+        Ok(self.module)
+    }
+}
+
+impl<'a, I> Iterator for FunctionStream<'a, I>
+    where I: Iterator<Item = io::Result<Text<'a>>>
+{
+    type Item = io::Result<Function<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(line) = self.lines.next() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            let attrs = self.attrs.take();
+            if let Some(rest) = line.strip_prefix("; Function Attrs:") {
+                self.attrs = Some(slice_text(&line, rest.trim()));
+                continue;
+            }
+            // spares blank lines and comments the module-level regexes, which allocate their captures
+            if line.trim().is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = capture_range(&DEFINE_RE, &mut self.define_locations, &line, 1) {
+                let func_name = sub_text(&line, name);
+                let mut raw = vec![];
+                let blocks = match parse_function(&mut self.lines, &mut self.label_locations, unnamed_entry_name(&line), self.keep_raw.then_some(&mut raw)) {
+                    Ok(blocks) => blocks,
+                    Err(err) => return Some(Err(err)),
+                };
+                if self.keep_raw {
+                    raw.insert(0, line.clone());
+                }
+                return Some(Ok(Function {
+                    name: func_name,
+                    define: line,
+                    attrs,
+                    blocks,
+                    module: 0,
+                    raw,
+                }));
+            }
+            else if let Some(caps) = DECLARE_RE.captures(&line) {
+                let name = sub_text(&line, caps.get(1).unwrap().range());
+                self.module.declarations.push(Declaration { name, line });
+            }
+            else if let Some(caps) = GLOBAL_RE.captures(&line) {
+                let name = sub_text(&line, caps.get(1).unwrap().range());
+                self.module.globals.push(Global { name, line });
+            }
+            else if let Some(caps) = TRIPLE_RE.captures(&line) {
+                self.module.target_triple = Some(sub_text(&line, caps.get(1).unwrap().range()));
+            }
+            else if let Some(caps) = SOURCE_FILENAME_RE.captures(&line) {
+                self.module.source_filename = Some(sub_text(&line, caps.get(1).unwrap().range()));
+            }
+            else {
+                // skip
+            }
+        }
+        None
+    }
+}
+
+/// The implicit name of an unlabeled entry block: the first value number not taken by an unnamed parameter.
+fn unnamed_entry_name(define: &str) -> Text<'static> {
+    let unnamed = parameters(define).iter()
+        .filter(|p| match p.split_whitespace().last() {
+            Some(last) if last.starts_with('%') => last[1..].chars().all(|c| c.is_ascii_digit()),
+            _ => true,
+        })
+        .count();
+    Cow::Owned(unnamed.to_string())
+}
+
+/// The text before the brace closing a function, when `line` holds it: a lone `}`, however
+/// indented, or a `}` left unbalanced after the instruction it follows (`  ret void }`).
+/// Braces of aggregate types and constants, in strings and in comments do not count.
+pub fn function_end(line: &str) -> Option<&str> {
+    if line.trim() == "}" {
+        return Some(&line[..0]);
+    }
+    if !line.contains('}') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut in_string = false;
+    for (at, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            ';' => return None,
+            '{' => depth += 1,
+            '}' if depth == 0 => {
+                let rest = line[at + 1..].trim_start();
+                return (rest.is_empty() || rest.starts_with(';')).then_some(&line[..at]);
+            }
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_function<'a, I>(lines: &mut I, label_locations: &mut CaptureLocations, entry_name: Text<'static>, mut raw: Option<&mut Vec<Text<'a>>>) -> io::Result<Vec<BasicBlock<'a>>>
+    where I: Iterator<Item = io::Result<Text<'a>>>
+{
+    let mut blocks: Vec<BasicBlock> = vec![];
+    let mut current_block: Option<BasicBlock> = None;
+    let mut in_switch = false; // inside the case list of a multi-line switch
+
+    for line in lines.by_ref() {
+        let line = line?;
+        if let Some(raw) = raw.as_mut() {
+            raw.push(line.clone());
+        }
+
+        let (line, ends_function) = match function_end(&line) {
+            Some(rest) => (slice_text(&line, rest.trim_end()), true),
+            None => (line, false),
+        };
+        // labels start in the first column, instructions are indented and need not be matched
+        let label = if line.starts_with(char::is_whitespace) { None } else { capture_range(&BLOCK_LABEL_RE, label_locations, &line, 1) };
+        if let Some(name) = label { // name: ; preds = a, b, c
+            if let Some(block) = current_block {
+                blocks.push(block);
+            }
+
+            let predecessors = label_locations.get(3)
+                .map(|(start, end)| line[start..end].split(", ").map(|s| slice_text(&line, s)).collect::<Vec<_>>())
+                .unwrap_or_default();
+
+            current_block = Some(BasicBlock {
+                name: sub_text(&line, name),
+                instructions: vec![],
+                predecessors,
+                successors: vec![],
+            });
+        }
+        else if ends_function && line.trim().is_empty() {
+            break;
+        }
+        else { // instruction inside block
+            if current_block.is_none() {
+                current_block = Some(BasicBlock {
+                    name: entry_name.clone(),
+                    instructions: vec![],
+                    predecessors: vec![],
+                    successors: vec![],
+                });
+            }
+            let current_block: &mut BasicBlock = current_block.as_mut().unwrap();
+            let terminator = Terminator::classify(&line);
+            if terminator.is_some() || in_switch {
+                for m in LABEL_RE.find_iter(&line) {
+                    let target = &m.as_str()[m.as_str().find('%').unwrap()..];
+                    current_block.successors.push(slice_text(&line, target));
+                }
+            }
+            if terminator == Some(Terminator::Switch) {
+                in_switch = !line.contains(']');
+            } else if in_switch && line.contains(']') {
+                in_switch = false;
+            }
+            if line.trim() != "" {
+                current_block.instructions.push(line);
+            }
+        }
+        if ends_function {
+            break;
+        }
+    }
+
+    if let Some(block) = current_block {
+        blocks.push(block);
+    }
+
+    Ok(blocks)
+}
+
+/// Something in an IR file the parser skips or misreads, with its 1-based line number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseProblem {
+    pub line: usize,
+    pub message: String,
+}
+
+/// The constructs of `input` that the parser does not understand: `define` lines it cannot match
+/// (the function is skipped), lines in a function body that are neither block labels nor
+/// instructions, branches to blocks that do not exist, and functions left without a closing brace.
+pub fn parse_problems(input: &str) -> Vec<ParseProblem> {
+    let mut problems = vec![];
+    let mut problem = |line: usize, message: String| problems.push(ParseProblem { line: line + 1, message });
+    let mut lines = input.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        if !line.starts_with("define") {
+            continue;
+        }
+        let Some(caps) = DEFINE_RE.captures(line) else {
+            problem(number, format!("cannot read this define, the function is skipped: {}", line.trim()));
+            continue;
+        };
+        let name = &caps[1];
+        let (mut blocks, mut targets) = (vec![unnamed_entry_name(line).to_string()], vec![]);
+        let mut closed = false;
+        let mut depth = 0i32; // `[ ... ]` lists continued over several lines
+        for (number, line) in lines.by_ref() {
+            let (code, ends_function) = match function_end(line) {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            if let Some(caps) = BLOCK_LABEL_RE.captures(code.trim_end()) {
+                blocks.push(caps[1].to_string());
+                continue;
+            }
+            let code = code.split(" ;").next().unwrap_or_default();
+            if !code.trim().is_empty() && !code.trim_start().starts_with(';') {
+                if depth == 0 && crate::ir::opcode(code).is_none() {
+                    let what = if line.split_whitespace().next().is_some_and(|w| w.ends_with(':')) { "block label not understood" } else { "not an instruction" };
+                    problem(number, format!("{what} in @{name}: {}", line.trim()));
+                }
+                if Terminator::classify(code).is_some() || depth > 0 {
+                    targets.extend(LABEL_RE.captures_iter(code).map(|c| (number, c[1][1..].to_string())));
+                }
+                depth += code.matches('[').count() as i32 - code.matches(']').count() as i32;
+            }
+            if ends_function {
+                closed = true;
+                break;
+            }
+        }
+        for (number, target) in targets.into_iter().filter(|(_, t)| !blocks.contains(t)) {
+            problem(number, format!("branch to %{target}, which is not a block of @{name}"));
+        }
+        if !closed {
+            problem(number, format!("@{name} has no closing brace"));
+        }
+    }
+    problems.sort_by_key(|p| p.line);
+    problems
+}
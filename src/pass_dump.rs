@@ -0,0 +1,603 @@
+//! Splitting of `opt -print-after-all` logs into one file per dump.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::manifest::ManifestEntry;
+use crate::parser::stream_ll_str;
+
+/// The pass and IR unit named by a `*** IR Dump After <pass> on <target> ***` line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpHeader {
+    pub pass: String,
+    pub target: String,
+}
+
+static HEADER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"IR Dump After (.+?)(?: on (.+?))?\s*\*\*\*").unwrap());
+
+/// Parses a dump marker line, returning `None` for any other line.
+pub fn parse_dump_header(line: &str) -> Option<DumpHeader> {
+    if !line.contains(" Dump After ") {
+        return None;
+    }
+    match HEADER_RE.captures(line) {
+        Some(caps) => Some(DumpHeader {
+            pass: caps[1].trim().to_string(),
+            target: caps.get(2).map(|m| m.as_str().to_string()).unwrap_or_default(),
+        }),
+        None => Some(DumpHeader { pass: line.trim().to_string(), target: String::new() }),
+    }
+}
+
+/// One segment of the log as written to disk. Segment 0 holds whatever precedes the first marker.
+#[derive(Clone, Debug)]
+pub struct DumpInfo {
+    pub index: usize,
+    pub header: Option<DumpHeader>,
+    pub file: PathBuf,
+    pub lines: usize,
+    /// Hash of the dump body, excluding the marker line.
+    pub hash: u64,
+    /// Whether the body differs from the previous dump of the same target (always true for the first one).
+    pub changed: bool,
+    /// Time spent in the pass, when the log ends with a `-time-passes` report.
+    pub time: Option<PassTime>,
+    /// Whether the dump holds the whole module (`-print-module-scope`).
+    pub module_scope: bool,
+    /// The line count of the dump before a function was extracted from it.
+    pub original_lines: Option<usize>,
+    /// Whether the file was deleted again to honor the ring size.
+    pub pruned: bool,
+    /// The length in bytes of the longest line, marker excluded.
+    pub longest_line: usize,
+    /// Where the segment starts in the log read by [`split_dumps`], in bytes.
+    pub offset: u64,
+}
+
+impl DumpInfo {
+    fn new(index: usize, header: Option<DumpHeader>, file: PathBuf) -> DumpInfo {
+        DumpInfo { index, header, file, lines: 0, hash: 0, changed: false, time: None, module_scope: false, original_lines: None, pruned: false, longest_line: 0, offset: 0 }
+    }
+}
+
+/// Wall-clock seconds attributed to a dump.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PassTime {
+    /// The time of this very run of the pass.
+    Run(f64),
+    /// Only the pass total over `runs` runs is known.
+    Total { seconds: f64, runs: usize },
+}
+
+/// One row of the "Pass execution timing report" printed by `-time-passes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    pub name: String,
+    /// The `#N` run number printed with `-time-passes-per-run`.
+    pub run: Option<usize>,
+    pub wall: f64,
+}
+
+/// A row of the timing table: one or more `time (percent%)` columns, then the name.
+static ROW_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(?:\d+\.\d+\s+\(\s*[\d.]+%\)\s+)+(.+?)\s*$").unwrap());
+static TIME_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+\.\d+)\s+\(\s*[\d.]+%\)").unwrap());
+static RUN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(.+) #(\d+)$").unwrap());
+
+/// Parses the pass execution timing table, ignoring the other timer groups.
+pub fn parse_timing_report(lines: &[String]) -> Vec<PassTiming> {
+    let mut timings = vec![];
+    let mut in_report = false;
+    let mut wall_column = None;
+    for line in lines {
+        if line.contains("... Pass execution timing report ...") {
+            in_report = true;
+            continue;
+        }
+        if !in_report {
+            continue;
+        }
+        if line.starts_with("===-") && wall_column.is_some() {
+            break; // start of the next timer group
+        }
+        if line.contains("--- Name ---") {
+            let columns: Vec<&str> = line.split("--").map(|c| c.trim()).filter(|c| !c.is_empty() && *c != "-").collect();
+            wall_column = columns.iter().position(|c| c.contains("Wall Time"));
+            continue;
+        }
+        let (Some(column), Some(caps)) = (wall_column, ROW_RE.captures(line)) else { continue };
+        let name = caps[1].to_string();
+        if name == "Total" {
+            continue;
+        }
+        let times: Vec<f64> = TIME_RE.captures_iter(line).filter_map(|c| c[1].parse().ok()).collect();
+        let Some(&wall) = times.get(column).or(times.last()) else { continue };
+        match RUN_RE.captures(&name) {
+            Some(run) => timings.push(PassTiming { name: run[1].to_string(), run: run[2].parse().ok(), wall }),
+            None => timings.push(PassTiming { name, run: None, wall }),
+        }
+    }
+    timings
+}
+
+/// Attaches the timings to the dumps: the n-th dump of a pass gets run `#n` when per-run times
+/// are available, otherwise the pass total (which is exact when the pass ran once).
+pub fn assign_times(dumps: &mut [DumpInfo], timings: &[PassTiming]) {
+    let find = |pass: &str, run: Option<usize>| timings.iter()
+        .find(|t| t.run == run && (t.name == pass || pass.split(" (").next() == Some(t.name.as_str())))
+        .map(|t| t.wall);
+
+    let mut runs: HashMap<String, usize> = HashMap::new();
+    for dump in dumps.iter().filter_map(|d| d.header.as_ref()) {
+        *runs.entry(dump.pass.clone()).or_default() += 1;
+    }
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for dump in dumps.iter_mut() {
+        let Some(header) = &dump.header else { continue };
+        let occurrence = seen.entry(header.pass.clone()).or_default();
+        *occurrence += 1;
+        let total_runs = runs[&header.pass];
+        dump.time = match (find(&header.pass, Some(*occurrence)), find(&header.pass, None)) {
+            (Some(seconds), _) => Some(PassTime::Run(seconds)),
+            (None, Some(seconds)) if total_runs == 1 => Some(PassTime::Run(seconds)),
+            (None, Some(seconds)) => Some(PassTime::Total { seconds, runs: total_runs }),
+            (None, None) => None,
+        };
+    }
+}
+
+/// Lines longer than this are passed to the [`Splitter`] in pieces of this size, which are written
+/// through to the dump file instead of being held in memory.
+pub const LINE_CHUNK: usize = 1 << 20;
+
+/// Streams log lines into `<dir>/<basename>_<n>.ll` files, starting a new file at every dump marker.
+pub struct Splitter {
+    dir: PathBuf,
+    basename: String,
+    writer: Option<BufWriter<File>>,
+    current: DumpInfo,
+    hasher: DefaultHasher,
+    last_hash: HashMap<String, u64>,
+    dumps: Vec<DumpInfo>,
+    /// Lines of the trailing `-time-passes` report, which belong to no dump.
+    timing: Vec<String>,
+    /// With `extract` set, every dump is buffered until its end and only that function is written.
+    extract: Option<String>,
+    buffer: Vec<String>,
+    /// Keep at most this many dump files on disk, deleting the oldest.
+    ring: Option<usize>,
+    /// Never prune the first dump, which is the baseline for comparisons.
+    keep_first: bool,
+    /// The start of a line given by [`Splitter::push_partial`] that could not be written through.
+    partial: Vec<u8>,
+    /// Bytes of the current line already written through by [`Splitter::push_partial`].
+    written_through: usize,
+    /// The offset of the line being pushed, kept by [`split_dumps`] for [`DumpInfo::offset`].
+    line_start: u64,
+}
+
+impl Splitter {
+    pub fn new(dir: &Path, basename: &str) -> io::Result<Splitter> {
+        fs::create_dir_all(dir)?;
+        let file = dir.join(format!("{basename}_0.ll"));
+        Ok(Splitter {
+            dir: dir.to_path_buf(),
+            basename: basename.to_string(),
+            writer: Some(BufWriter::new(File::create(&file)?)),
+            current: DumpInfo::new(0, None, file),
+            hasher: DefaultHasher::new(),
+            last_hash: HashMap::new(),
+            dumps: vec![],
+            timing: vec![],
+            extract: None,
+            buffer: vec![],
+            ring: None,
+            keep_first: false,
+            partial: vec![],
+            written_through: 0,
+            line_start: 0,
+        })
+    }
+
+    /// Continues a split of which the segments `done`, segment 0 included, are on disk, for a log
+    /// that grew after them: the next line pushed must be the marker of dump `done.len()`. Whether a
+    /// dump changed and which files the ring deletes carry on from `done`, which were split with
+    /// `extract` as [`Splitter::extract_function`].
+    pub fn resume(dir: &Path, basename: &str, done: Vec<DumpInfo>, extract: Option<String>) -> Splitter {
+        let mut last_hash = HashMap::new();
+        for dump in &done {
+            if let Some(header) = &dump.header {
+                last_hash.insert(extract.clone().unwrap_or_else(|| header.target.clone()), dump.hash);
+            }
+        }
+        Splitter {
+            dir: dir.to_path_buf(),
+            basename: basename.to_string(),
+            // no writer and no header: the segment before the next marker, already in `dumps`
+            writer: None,
+            current: DumpInfo::new(done.len().saturating_sub(1), None, PathBuf::new()),
+            hasher: DefaultHasher::new(),
+            last_hash,
+            dumps: done,
+            timing: vec![],
+            extract,
+            buffer: vec![],
+            ring: None,
+            keep_first: false,
+            partial: vec![],
+            written_through: 0,
+            line_start: 0,
+        }
+    }
+
+    /// Only write the text of function `name` (plus the module header lines) into each split file,
+    /// skipping the dumps that do not contain it.
+    pub fn extract_function(mut self, name: Option<String>) -> Self {
+        self.extract = name;
+        self
+    }
+
+    /// Keep only the `size` most recent dump files (plus the first one when `keep_first`).
+    pub fn ring(mut self, size: Option<usize>, keep_first: bool) -> Self {
+        self.ring = size;
+        self.keep_first = keep_first;
+        self
+    }
+
+    /// Takes the next piece of a line too long to hold in memory; the rest of the line follows with
+    /// [`Splitter::push_line`]. The piece goes straight to the dump file, unless the line must be
+    /// kept whole: in the timing report or with [`Splitter::extract_function`].
+    pub fn push_partial(&mut self, part: &[u8]) -> io::Result<()> {
+        match &mut self.writer {
+            Some(writer) if self.timing.is_empty() => {
+                if self.written_through == 0 && part.starts_with(b"; ModuleID") {
+                    self.current.module_scope = true;
+                }
+                writer.write_all(part)?;
+                self.hasher.write(part);
+                self.written_through += part.len();
+            }
+            _ => self.partial.extend_from_slice(part),
+        }
+        Ok(())
+    }
+
+    pub fn push_line(&mut self, line: &str) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            let mut whole = std::mem::take(&mut self.partial);
+            whole.extend_from_slice(line.as_bytes());
+            return self.push_line(&String::from_utf8_lossy(&whole));
+        }
+        if self.written_through > 0 {
+            // the end of a written-through line, hashed like a `str` so a line hashes the same however it was read
+            let writer = self.writer.as_mut().expect("lines are only written through to a file");
+            writeln!(writer, "{}", line)?;
+            self.hasher.write(line.as_bytes());
+            self.hasher.write_u8(0xff);
+            let length = std::mem::take(&mut self.written_through) + line.len();
+            self.current.longest_line = self.current.longest_line.max(length);
+            self.current.lines += 1;
+            return Ok(());
+        }
+        if !self.timing.is_empty() || line.starts_with("===-") {
+            self.timing.push(line.to_string());
+            return Ok(());
+        }
+        if let Some(header) = parse_dump_header(line) {
+            self.finish_current()?;
+            let index = self.current.index + 1;
+            let file = self.dir.join(format!("{}_{index}.ll", self.basename));
+            self.current = DumpInfo::new(index, Some(header), file);
+            self.current.offset = self.line_start;
+            self.hasher = DefaultHasher::new();
+            if self.extract.is_none() {
+                self.writer = Some(BufWriter::new(File::create(&self.current.file)?));
+            } else {
+                self.buffer.clear();
+            }
+        }
+        else {
+            if line.starts_with("; ModuleID") {
+                self.current.module_scope = true;
+            }
+            line.hash(&mut self.hasher);
+            self.current.longest_line = self.current.longest_line.max(line.len());
+        }
+        match &mut self.writer {
+            Some(writer) => writeln!(writer, "{}", line)?,
+            None => self.buffer.push(line.to_string()),
+        }
+        self.current.lines += 1;
+        Ok(())
+    }
+
+    /// The marker, the module header lines and the text of the extracted function,
+    /// or `None` when the buffered dump does not contain the function.
+    fn extracted_lines(&self) -> Option<Vec<String>> {
+        let name = self.extract.as_deref()?;
+        let (marker, body) = self.buffer.split_first()?;
+        let text = body.join("\n");
+        let function = stream_ll_str(&text).keep_raw(true)
+            .find(|f| f.as_ref().is_ok_and(|f| f.name == name))?
+            .ok()?;
+
+        let mut lines = vec![marker.clone()];
+        lines.extend(body.iter().take_while(|l| !l.starts_with("define ") && !l.starts_with("; Function Attrs:")).cloned());
+        lines.extend(function.attrs.iter().map(|attrs| format!("; Function Attrs: {attrs}")));
+        lines.extend(function.raw.iter().map(|l| l.to_string()));
+        Some(lines)
+    }
+
+    fn finish_current(&mut self) -> io::Result<()> {
+        if self.writer.is_none() && self.current.header.is_none() {
+            return Ok(()); // resumed, the segment is in `dumps` already
+        }
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        else if self.current.header.is_some() {
+            let Some(lines) = self.extracted_lines() else { return Ok(()) }; // function absent, skip the dump
+            let mut writer = BufWriter::new(File::create(&self.current.file)?);
+            self.hasher = DefaultHasher::new();
+            for line in &lines {
+                writeln!(writer, "{}", line)?;
+            }
+            writer.flush()?;
+            lines[1..].iter().for_each(|line| line.hash(&mut self.hasher));
+            self.current.original_lines = Some(self.current.lines);
+            self.current.lines = lines.len();
+        }
+
+        let mut info = self.current.clone();
+        info.hash = self.hasher.finish();
+        if let Some(header) = &info.header {
+            // an extracted function is compared with itself whatever unit the pass ran on
+            let key = self.extract.clone().unwrap_or_else(|| header.target.clone());
+            info.changed = self.last_hash.insert(key, info.hash) != Some(info.hash);
+        }
+        self.dumps.push(info);
+        self.prune()
+    }
+
+    /// Deletes the oldest dump files beyond the ring size.
+    fn prune(&mut self) -> io::Result<()> {
+        let Some(size) = self.ring else { return Ok(()) };
+        let first = self.dumps.iter().position(|d| d.header.is_some());
+        let mut on_disk: Vec<usize> = (0..self.dumps.len())
+            .filter(|&i| self.dumps[i].header.is_some() && !self.dumps[i].pruned)
+            .filter(|&i| !(self.keep_first && Some(i) == first))
+            .collect();
+        while on_disk.len() > size {
+            let oldest = on_disk.remove(0);
+            fs::remove_file(&self.dumps[oldest].file)?;
+            self.dumps[oldest].pruned = true;
+        }
+        Ok(())
+    }
+
+    /// The segments completed so far, i.e. all but the one still being written.
+    pub fn dumps(&self) -> &[DumpInfo] {
+        &self.dumps
+    }
+
+    /// Flushes the last segment and returns every segment written.
+    pub fn finish(mut self) -> io::Result<Vec<DumpInfo>> {
+        self.finish_current()?;
+        assign_times(&mut self.dumps, &parse_timing_report(&self.timing));
+        Ok(self.dumps)
+    }
+}
+
+/// Splits the whole log read from `reader` with the given configuration. Lines are read in pieces
+/// of at most [`LINE_CHUNK`] bytes, so a huge single-line module does not have to fit in memory.
+pub fn split_dumps<R, F>(reader: R, dir: &Path, basename: &str, configure: F) -> io::Result<Vec<DumpInfo>>
+    where R: BufRead, F: FnOnce(Splitter) -> Splitter
+{
+    continue_split(reader, configure(Splitter::new(dir, basename)?), 0)
+}
+
+/// Feeds the rest of a log, read from `reader` starting at byte `offset`, to `splitter`, e.g. one
+/// made by [`Splitter::resume`], and returns every segment written.
+pub fn continue_split<R: BufRead>(mut reader: R, mut splitter: Splitter, offset: u64) -> io::Result<Vec<DumpInfo>> {
+    let mut line = vec![];
+    let (mut consumed, mut line_start) = (offset, offset);
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        let (used, end_of_line) = match buffer.iter().position(|&b| b == b'\n') {
+            Some(at) => (at + 1, true),
+            None => (buffer.len(), false),
+        };
+        line.extend_from_slice(&buffer[..used]);
+        reader.consume(used);
+        consumed += used as u64;
+        if end_of_line {
+            splitter.line_start = line_start;
+            push_line_end(&mut splitter, &mut line)?;
+            line_start = consumed;
+        } else if line.len() >= LINE_CHUNK {
+            // keep a `\r` that may start the line end, and a character that may be split, for the next piece
+            let mut cut = line.len() - (line.last() == Some(&b'\r')) as usize;
+            if let Some(lead) = (cut.saturating_sub(4)..cut).rev().find(|&i| line[i] & 0xc0 != 0x80) {
+                if line[lead] >= 0xc0 {
+                    cut = lead;
+                }
+            }
+            splitter.push_partial(&line[..cut])?;
+            line.drain(..cut);
+        }
+    }
+    if !line.is_empty() {
+        splitter.line_start = line_start;
+        push_line_end(&mut splitter, &mut line)?;
+    }
+    splitter.finish()
+}
+
+/// Passes the end of a line, with its `\n` or `\r\n` if any, as `BufRead::lines` would.
+fn push_line_end(splitter: &mut Splitter, line: &mut Vec<u8>) -> io::Result<()> {
+    let text = line.strip_suffix(b"\n").unwrap_or(line);
+    let text = text.strip_suffix(b"\r").unwrap_or(text);
+    let text = std::str::from_utf8(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    splitter.push_line(text)?;
+    line.clear();
+    Ok(())
+}
+
+/// A dump in which a function's text differs from its previous appearance in the log.
+#[derive(Clone, Debug)]
+pub struct FunctionChange {
+    /// The position of the dump in the log, counting from 1 as the split files do.
+    pub index: usize,
+    pub header: DumpHeader,
+    /// The function, `define` through closing brace, before and after the pass.
+    pub before: String,
+    pub after: String,
+}
+
+/// Follows `function` through a `-print-after-all` log and returns the dumps that changed it.
+/// Dumps without the function, as those of passes on other functions, are skipped; its first
+/// appearance is the baseline. Only one dump is held in memory at a time.
+pub fn function_changes<R: BufRead>(reader: R, function: &str) -> io::Result<Vec<FunctionChange>> {
+    let text_of = |dump: &str| stream_ll_str(dump).keep_raw(true)
+        .find(|f| f.as_ref().is_ok_and(|f| f.name == function))
+        .and_then(Result::ok)
+        .map(|f| f.raw.join("\n"));
+    let mut changes = vec![];
+    let mut last: Option<String> = None;
+    let mut dump: Option<(usize, DumpHeader, String)> = None;
+    let mut index = 0;
+    let mut finish = |dump: Option<(usize, DumpHeader, String)>, last: &mut Option<String>| {
+        let Some((index, header, text)) = dump else { return };
+        let Some(after) = text_of(&text) else { return };
+        match last.replace(after.clone()) {
+            Some(before) if before != after => changes.push(FunctionChange { index, header, before, after }),
+            _ => {}
+        }
+    };
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(header) = parse_dump_header(&line) {
+            index += 1;
+            finish(dump.replace((index, header, String::new())), &mut last);
+        } else if let Some((_, _, text)) = dump.as_mut() {
+            text.push_str(&line);
+            text.push('\n');
+        }
+    }
+    finish(dump, &mut last);
+    Ok(changes)
+}
+
+/// Removes the split files and index previously written for `basename` in `dir`.
+pub fn clean_outputs(dir: &Path, basename: &str) -> io::Result<usize> {
+    let Ok(entries) = fs::read_dir(dir) else { return Ok(0) };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some(rest) = name.strip_prefix(basename).and_then(|r| r.strip_prefix('_')) else { continue };
+        let is_dump = rest.strip_suffix(".ll").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if is_dump || rest == "index.md" {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// The manifest entries of a split of `source`: the dump files still on disk, each about the
+/// `extracted` function or else the function the pass ran on, then `index` and the HTML `report`.
+pub fn split_manifest_entries(source: &str, dumps: &[DumpInfo], extracted: Option<&str>, index: &Path, report: Option<&Path>) -> Vec<ManifestEntry> {
+    let entry = |path: &Path, function: Option<String>, format: &str| ManifestEntry {
+        path: path.to_path_buf(), source: source.to_string(), function, format: format.to_string(),
+    };
+    let mut entries: Vec<ManifestEntry> = dumps.iter()
+        .filter(|d| !d.pruned && d.file.exists())
+        .map(|d| {
+            let target = d.header.as_ref().map(|h| h.target.clone()).filter(|t| !t.is_empty() && !t.starts_with('['));
+            entry(&d.file, extracted.map(String::from).or(target), "ll")
+        })
+        .collect();
+    entries.push(entry(index, None, "md"));
+    entries.extend(report.map(|report| entry(report, None, "html")));
+    entries
+}
+
+/// Path of the index written next to the split files.
+pub fn index_path(dir: &Path, basename: &str) -> PathBuf {
+    dir.join(format!("{basename}_index.md"))
+}
+
+fn format_time(time: Option<PassTime>) -> String {
+    match time {
+        Some(PassTime::Run(seconds)) => format!("{seconds:.4}"),
+        Some(PassTime::Total { seconds, runs }) => format!("{seconds:.4} / {runs} runs *"),
+        None => String::new(),
+    }
+}
+
+/// Writes a markdown table describing every segment, followed by a per-pass summary
+/// when timings are known.
+pub fn write_index(output: &mut dyn Write, dumps: &[DumpInfo]) -> io::Result<()> {
+    let timed = dumps.iter().any(|d| d.time.is_some());
+    let extracted = dumps.iter().any(|d| d.original_lines.is_some());
+    writeln!(output, "| # | pass | target | lines |{} changed |{} file |",
+        if extracted { " original lines |" } else { "" }, if timed { " time (s) |" } else { "" })?;
+    writeln!(output, "|---:|---|---|---:|{}---|{}---|", if extracted { "---:|" } else { "" }, if timed { "---:|" } else { "" })?;
+    for dump in dumps {
+        let (pass, target) = dump.header.as_ref().map(|h| (h.pass.as_str(), h.target.as_str())).unwrap_or(("", ""));
+        let file = dump.file.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let original = match (extracted, dump.original_lines) {
+            (false, _) => String::new(),
+            (true, Some(lines)) => format!(" {lines} |"),
+            (true, None) => " |".to_string(),
+        };
+        let time = if timed { format!(" {} |", format_time(dump.time)) } else { String::new() };
+        let file = if dump.pruned { format!("~~{file}~~ (pruned)") } else { file.to_string() };
+        writeln!(output, "| {} | {} | {} | {} |{original} {} |{time} {} |",
+            dump.index, pass, target, dump.lines, if dump.changed { "yes" } else { "" }, file)?;
+    }
+    if !timed {
+        return Ok(());
+    }
+
+    // pass name -> (runs, changed runs, known seconds, whether only the total is known)
+    let mut summary: Vec<(&str, usize, usize, f64, bool)> = vec![];
+    for dump in dumps {
+        let Some(header) = &dump.header else { continue };
+        let at = match summary.iter().position(|s| s.0 == header.pass) {
+            Some(at) => at,
+            None => {
+                summary.push((&header.pass, 0, 0, 0.0, false));
+                summary.len() - 1
+            }
+        };
+        let entry = &mut summary[at];
+        entry.1 += 1;
+        entry.2 += dump.changed as usize;
+        match dump.time {
+            Some(PassTime::Run(seconds)) => entry.3 += seconds,
+            Some(PassTime::Total { seconds, .. }) => (entry.3, entry.4) = (seconds, true),
+            None => {}
+        }
+    }
+    summary.sort_by(|a, b| b.3.total_cmp(&a.3));
+
+    writeln!(output, "\n## Per-pass summary\n")?;
+    writeln!(output, "| pass | runs | changed | time (s) |")?;
+    writeln!(output, "|---|---:|---:|---:|")?;
+    for (pass, runs, changed, seconds, total_only) in &summary {
+        writeln!(output, "| {pass} | {runs} | {changed} | {seconds:.4}{} |", if *total_only { " *" } else { "" })?;
+    }
+    if summary.iter().any(|s| s.4) {
+        writeln!(output, "\n\\* only the total over all runs is known; rerun opt with `-time-passes-per-run` for per-run times.")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Self-contained HTML report over the dumps of a `-print-after-all` log.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::pass_dump::DumpInfo;
+use crate::text_diff::{diff_lines, DiffOp};
+
+const STYLE: &str = r#"
+body { margin: 0; font-family: sans-serif; display: flex; height: 100vh; }
+#sidebar { width: 22em; overflow-y: auto; border-right: 1px solid #ccc; font-size: 13px; }
+#sidebar div { padding: 2px 6px; cursor: pointer; white-space: nowrap; color: #888; }
+#sidebar div.changed { color: #000; font-weight: bold; }
+#sidebar div.selected { background: #cde; }
+#main { flex: 1; overflow: auto; padding: 0 1em; }
+#toolbar { position: sticky; top: 0; background: #fff; padding: 6px 0; border-bottom: 1px solid #eee; }
+pre { font-size: 13px; }
+.dump { display: none; }
+.dump.selected { display: block; }
+.diff .add { background: #dfd; }
+.diff .del { background: #fdd; }
+.kw { color: #00c; }
+.ty { color: #080; }
+.var { color: #a50; }
+.glob { color: #a0a; }
+.cm { color: #888; }
+"#;
+
+const SCRIPT: &str = r#"
+let mode = 'diff';
+function show(n) {
+  document.querySelectorAll('.selected').forEach(e => e.classList.remove('selected'));
+  document.getElementById('s' + n).classList.add('selected');
+  document.getElementById('d' + n).classList.add('selected');
+}
+function toggle() {
+  mode = mode === 'diff' ? 'full' : 'diff';
+  document.querySelectorAll('.full').forEach(e => e.style.display = mode === 'full' ? 'block' : 'none');
+  document.querySelectorAll('.diff').forEach(e => e.style.display = mode === 'diff' ? 'block' : 'none');
+  document.getElementById('mode').textContent = mode === 'diff' ? 'Show full dump' : 'Show diff';
+}
+"#;
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+static TOKEN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(concat!(
+    r#"(?P<cm>;.*$)|(?P<str>c?"[^"]*")|(?P<var>%[-a-zA-Z0-9$._"]+)|(?P<glob>@[-a-zA-Z0-9$._"]+)"#,
+    r"|(?P<ty>\b(?:i\d+|void|ptr|half|float|double|label|metadata)\b)",
+    r"|(?P<kw>\b(?:define|declare|ret|br|switch|indirectbr|invoke|resume|unreachable|call|tail|musttail",
+    r"|phi|select|load|store|alloca|getelementptr|icmp|fcmp|add|sub|mul|udiv|sdiv|and|or|xor|shl|lshr|ashr",
+    r"|zext|sext|trunc|bitcast|inttoptr|ptrtoint|to|private|internal|global|constant)\b)",
+)).unwrap());
+
+/// Keyword-based highlighting of one line of LLVM IR, returning escaped HTML.
+pub fn highlight_ir(line: &str) -> String {
+    let mut html = String::new();
+    let mut last = 0;
+    for caps in TOKEN_RE.captures_iter(line) {
+        let m = caps.get(0).unwrap();
+        html.push_str(&escape_html(&line[last..m.start()]));
+        let class = ["cm", "str", "var", "glob", "ty", "kw"].into_iter().find(|c| caps.name(c).is_some()).unwrap();
+        let class = if class == "str" { "cm" } else { class };
+        html.push_str(&format!("<span class=\"{class}\">{}</span>", escape_html(m.as_str())));
+        last = m.end();
+    }
+    html.push_str(&escape_html(&line[last..]));
+    html
+}
+
+/// Writes the report for `dumps`, reading each dump back from its split file (pruned dumps are left out).
+/// Every dump is diffed against the previous dump of the same target.
+pub fn write_html_report(output: &mut dyn Write, title: &str, dumps: &[DumpInfo]) -> io::Result<()> {
+    let dumps: Vec<&DumpInfo> = dumps.iter().filter(|d| d.header.is_some() && !d.pruned).collect();
+    writeln!(output, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>", escape_html(title))?;
+    writeln!(output, "<style>{STYLE}</style><script>{SCRIPT}</script></head><body>")?;
+
+    writeln!(output, "<div id=\"sidebar\">")?;
+    for dump in &dumps {
+        let header = dump.header.as_ref().unwrap();
+        writeln!(output, "<div id=\"s{0}\" class=\"{1}\" onclick=\"show({0})\">{0}. {2} <small>{3}</small></div>",
+            dump.index, if dump.changed { "changed" } else { "" }, escape_html(&header.pass), escape_html(&header.target))?;
+    }
+    writeln!(output, "</div>")?;
+
+    writeln!(output, "<div id=\"main\"><div id=\"toolbar\"><button id=\"mode\" onclick=\"toggle()\">Show full dump</button></div>")?;
+    let mut previous: HashMap<&str, String> = HashMap::new();
+    for dump in &dumps {
+        let header = dump.header.as_ref().unwrap();
+        let text = fs::read_to_string(&dump.file)?;
+        // skip the marker line itself
+        let body: Vec<&str> = text.lines().skip(1).collect();
+
+        writeln!(output, "<div id=\"d{}\" class=\"dump\"><h3>{}. {} on {}</h3>",
+            dump.index, dump.index, escape_html(&header.pass), escape_html(&header.target))?;
+        writeln!(output, "<pre class=\"full\" style=\"display:none\">")?;
+        for line in &body {
+            writeln!(output, "{}", highlight_ir(line))?;
+        }
+        writeln!(output, "</pre>\n<pre class=\"diff\">")?;
+        match previous.get(header.target.as_str()) {
+            None => writeln!(output, "<span class=\"cm\">(first dump of {})</span>", escape_html(&header.target))?,
+            Some(_) if !dump.changed => writeln!(output, "<span class=\"cm\">(unchanged)</span>")?,
+            Some(old) => {
+                let old: Vec<&str> = old.lines().collect();
+                for op in diff_lines(&old, &body) {
+                    match op {
+                        DiffOp::Equal(line) => writeln!(output, "  {}", highlight_ir(line))?,
+                        DiffOp::Insert(line) => writeln!(output, "<span class=\"add\">+ {}</span>", highlight_ir(line))?,
+                        DiffOp::Delete(line) => writeln!(output, "<span class=\"del\">- {}</span>", highlight_ir(line))?,
+                    }
+                }
+            }
+        }
+        writeln!(output, "</pre></div>")?;
+        previous.insert(header.target.as_str(), body.join("\n"));
+    }
+    writeln!(output, "</div>")?;
+    if let Some(first) = dumps.first() {
+        writeln!(output, "<script>show({});</script>", first.index)?;
+    }
+    writeln!(output, "</body></html>")?;
+    Ok(())
+}
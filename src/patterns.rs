@@ -0,0 +1,80 @@
+//! Detection of small structured control-flow shapes: if/else diamonds, if-without-else
+//! triangles and self-loops.
+
+use crate::cfg::Cfg;
+use crate::dom::Dominators;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PatternKind {
+    /// `header` branches to two arms that both fall through to the same join block.
+    Diamond,
+    /// `header` branches to one arm and to the join block the arm falls through to.
+    Triangle,
+    /// A block that branches to itself.
+    SelfLoop,
+}
+
+impl PatternKind {
+    pub const ALL: [PatternKind; 3] = [PatternKind::Diamond, PatternKind::Triangle, PatternKind::SelfLoop];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatternKind::Diamond => "diamond",
+            PatternKind::Triangle => "triangle",
+            PatternKind::SelfLoop => "self-loop",
+        }
+    }
+}
+
+/// One detected shape. `blocks` starts with the header and ends with the join block, if any.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub header: usize,
+    pub blocks: Vec<usize>,
+}
+
+/// The blocks branching to themselves, the single-block loops, in block order.
+pub fn self_loops(cfg: &Cfg) -> Vec<usize> {
+    (0..cfg.len()).filter(|&b| cfg.succs[b].contains(&b)).collect()
+}
+
+/// Finds the diamonds, triangles and self-loops of a CFG, in block order. The rejoin of
+/// diamonds and triangles is confirmed with dominators: the header dominates the join,
+/// which post-dominates the header.
+pub fn find_patterns(cfg: &Cfg, entry: usize) -> Vec<Pattern> {
+    if cfg.is_empty() {
+        return vec![];
+    }
+    let doms = Dominators::new(cfg, entry);
+    let post = Dominators::post(cfg);
+    let rejoins = |header: usize, join: usize| doms.dominates(header, join) && post.dominates(join, header);
+    // an arm is a block entered only from `header` that falls through to a single block
+    let arm_target = |header: usize, arm: usize| match (cfg.preds[arm].as_slice(), cfg.succs[arm].as_slice()) {
+        ([pred], [succ]) if *pred == header && *succ != arm && *succ != header => Some(*succ),
+        _ => None,
+    };
+
+    let mut patterns = vec![];
+    let self_loops = self_loops(cfg);
+    for header in 0..cfg.len() {
+        if self_loops.binary_search(&header).is_ok() {
+            patterns.push(Pattern { kind: PatternKind::SelfLoop, header, blocks: vec![header] });
+        }
+        let [a, b] = cfg.succs[header].as_slice() else { continue };
+        let (a, b) = (*a, *b);
+        if a == header || b == header {
+            continue;
+        }
+        match (arm_target(header, a), arm_target(header, b)) {
+            (Some(ja), Some(jb)) if ja == jb && rejoins(header, ja) =>
+                patterns.push(Pattern { kind: PatternKind::Diamond, header, blocks: vec![header, a, b, ja] }),
+            (Some(join), _) if join == b && rejoins(header, b) =>
+                patterns.push(Pattern { kind: PatternKind::Triangle, header, blocks: vec![header, a, b] }),
+            (_, Some(join)) if join == a && rejoins(header, a) =>
+                patterns.push(Pattern { kind: PatternKind::Triangle, header, blocks: vec![header, b, a] }),
+            _ => {}
+        }
+    }
+    patterns
+}
@@ -0,0 +1,58 @@
+//! A progress line on stderr for long runs over big modules: a bar when the number of functions
+//! is known, a spinner with a running count while they are still being parsed.
+
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// The style of the progress line: `rendering [#######.......] 12/40` with a `total`, and
+/// `parsing / 12` without one.
+pub fn progress_style(total: Option<usize>) -> ProgressStyle {
+    match total {
+        Some(_) => ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len}").unwrap().progress_chars("#."),
+        None => ProgressStyle::with_template("{msg} {spinner} {pos}").unwrap().tick_chars("|/-\\ "),
+    }
+}
+
+/// A progress line counting functions, drawn only when enabled and stderr is a terminal, so that
+/// redirected or piped runs never see it.
+pub struct Progress {
+    bar: Option<ProgressBar>,
+}
+
+impl Progress {
+    pub fn new(label: &str, total: Option<usize>, enabled: bool) -> Progress {
+        let bar = (enabled && io::stderr().is_terminal()).then(|| {
+            let bar = match total {
+                Some(total) => ProgressBar::new(total as u64),
+                None => ProgressBar::new_spinner(),
+            };
+            bar.set_style(progress_style(total));
+            bar.set_message(label.to_string());
+            if total.is_none() {
+                bar.enable_steady_tick(Duration::from_millis(100));
+            }
+            bar
+        });
+        Progress { bar }
+    }
+
+    /// Whether the line is drawn at all.
+    pub fn is_drawn(&self) -> bool {
+        self.bar.is_some()
+    }
+
+    /// Counts one more function.
+    pub fn inc(&mut self) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+        }
+    }
+
+    /// Clears the line, leaving stderr as it was.
+    pub fn finish(self) {
+        if let Some(bar) = self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
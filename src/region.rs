@@ -0,0 +1,117 @@
+//! Single-entry single-exit regions and their nesting, for a hierarchical view of large functions.
+//!
+//! A region is an `entry` block and the blocks reachable from it without passing its `exit`,
+//! provided `entry` dominates all of them; control then enters only through `entry` and leaves
+//! only to `exit`, which post-dominates `entry`. For every block the smallest such region with
+//! more than one block is kept, much like the canonical regions of LLVM's `RegionInfo`.
+
+use std::borrow::Cow;
+use crate::cfg::Cfg;
+use crate::dom::Dominators;
+use crate::ir::Function;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub entry: usize,
+    /// The block control leaves to, `None` when the region ends by returning.
+    pub exit: Option<usize>,
+    /// The blocks of the region, sorted, `entry` included.
+    pub blocks: Vec<usize>,
+    /// The smallest region containing this one.
+    pub parent: Option<usize>,
+    /// 0 for regions directly in the function body.
+    pub depth: usize,
+}
+
+/// The blocks reachable from `entry` without passing `exit`, if `entry` dominates them all.
+fn region_blocks(cfg: &Cfg, doms: &Dominators, entry: usize, exit: Option<usize>) -> Option<Vec<usize>> {
+    let mut inside = vec![false; cfg.len()];
+    inside[entry] = true;
+    let mut stack = vec![entry];
+    while let Some(block) = stack.pop() {
+        for &succ in &cfg.succs[block] {
+            if Some(succ) != exit && !inside[succ] {
+                if !doms.dominates(entry, succ) {
+                    return None;
+                }
+                inside[succ] = true;
+                stack.push(succ);
+            }
+        }
+    }
+    Some((0..cfg.len()).filter(|&b| inside[b]).collect())
+}
+
+/// The canonical regions of the CFG below the function body itself, parents before children.
+/// Regions that would overlap an already kept one without nesting are dropped.
+pub fn region_tree(cfg: &Cfg, entry: usize) -> Vec<Region> {
+    if cfg.is_empty() {
+        return vec![];
+    }
+    let doms = Dominators::new(cfg, entry);
+    let post = Dominators::post(cfg);
+    let reachable = (0..cfg.len()).filter(|&b| doms.is_reachable(b)).count();
+    let mut candidates = vec![];
+    for block in (0..cfg.len()).filter(|&b| doms.is_reachable(b)) {
+        // walk up the post-dominator tree until the blocks in between form a region of several blocks
+        let mut exit = post.idom[block];
+        while let Some(candidate) = exit {
+            let exit_block = (candidate < cfg.len()).then_some(candidate);
+            match region_blocks(cfg, &doms, block, exit_block) {
+                Some(blocks) if blocks.len() == reachable => break,
+                Some(blocks) if blocks.len() > 1 => {
+                    candidates.push(Region { entry: block, exit: exit_block, blocks, parent: None, depth: 0 });
+                    break;
+                }
+                _ => exit = post.idom[candidate],
+            }
+        }
+    }
+    candidates.sort_by_key(|r| std::cmp::Reverse(r.blocks.len()));
+
+    let mut regions: Vec<Region> = vec![];
+    for mut region in candidates {
+        let contains = |outer: &Region| region.blocks.iter().all(|b| outer.blocks.binary_search(b).is_ok());
+        let overlaps = |other: &Region| region.blocks.iter().any(|b| other.blocks.binary_search(b).is_ok());
+        if regions.iter().any(|r| overlaps(r) && !contains(r)) {
+            continue;
+        }
+        // the last containing region is the smallest, as they come largest first
+        region.parent = regions.iter().rposition(contains);
+        region.depth = region.parent.map_or(0, |p| regions[p].depth + 1);
+        regions.push(region);
+    }
+    regions
+}
+
+/// Returns a copy of `function` in which each region at nesting `depth` is replaced by its entry
+/// block, relabeled with the region's size and exit and branching straight to the exit, and the
+/// number of regions collapsed.
+pub fn collapse_regions<'a>(function: &Function<'a>, depth: usize, entry: usize) -> (Function<'a>, usize) {
+    let cfg = Cfg::new(function);
+    let regions: Vec<Region> = region_tree(&cfg, entry).into_iter().filter(|r| r.depth == depth).collect();
+    let name = |index: usize| format!("%{}", function.blocks[index].name);
+    let mut hidden = vec![false; cfg.len()];
+    for region in &regions {
+        region.blocks.iter().filter(|&&b| b != region.entry).for_each(|&b| hidden[b] = true);
+    }
+    let mut blocks: Vec<_> = function.blocks.iter().enumerate().filter(|(index, _)| !hidden[*index]).map(|(index, block)| {
+        let mut block = block.clone();
+        block.successors = cfg.succs[index].iter().map(|&s| Cow::Owned(name(s))).collect();
+        if let Some(region) = regions.iter().find(|r| r.entry == index) {
+            let exit = region.exit.map_or("return".to_string(), name);
+            block.instructions = vec![Cow::Owned(format!("  ; region {} -> {exit}: {} blocks", name(index), region.blocks.len()))];
+            block.successors = region.exit.map(|e| Cow::Owned(name(e))).into_iter().collect();
+        }
+        block
+    }).collect();
+    // the `; preds =` comments no longer hold, derive the predecessors from the successors
+    let predecessors: Vec<Vec<_>> = blocks.iter().map(|block| blocks.iter()
+        .filter(|b| b.successors.iter().any(|s| s.strip_prefix('%') == Some(block.name.as_ref())))
+        .map(|b| Cow::Owned(format!("%{}", b.name)))
+        .collect()).collect();
+    for (block, preds) in blocks.iter_mut().zip(predecessors) {
+        block.predecessors = preds;
+    }
+    (Function { blocks, raw: vec![], ..function.clone() }, regions.len())
+}
@@ -0,0 +1,149 @@
+//! Optimization remarks, as written by `opt -pass-remarks-output=remarks.yaml`, placed on the
+//! blocks whose `!dbg` locations match the source lines they refer to.
+//!
+//! Only the subset of YAML LLVM emits for remarks is understood: a stream of `--- !Kind`
+//! documents of `Key: value` lines, flow mappings for `DebugLoc`, and an `Args` list whose
+//! values concatenate into the remark message.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead};
+use regex::Regex;
+use crate::ir::Function;
+
+/// One optimization remark.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Remark {
+    /// `Passed`, `Missed`, `Analysis`, ...
+    pub kind: String,
+    pub pass: String,
+    pub name: String,
+    pub function: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// The `Args` values joined, e.g. `external will not be inlined into sum because its definition is unavailable`.
+    pub message: String,
+}
+
+impl Remark {
+    /// A one-line summary: `Missed inline: external will not be inlined into sum ...`.
+    pub fn summary(&self) -> String {
+        format!("{} {}: {}", self.kind, self.pass, self.message)
+    }
+}
+
+/// A plain, single- or double-quoted YAML scalar.
+fn scalar(value: &str) -> String {
+    let value = value.trim();
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        inner.replace("''", "'")
+    } else if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        inner.replace("\\\"", "\"").replace("\\\\", "\\")
+    } else {
+        value.to_string()
+    }
+}
+
+/// The `File`, `Line` and `Column` of a `{ File: a.c, Line: 3, Column: 10 }` flow mapping.
+fn debug_loc(value: &str) -> (Option<String>, Option<u32>, Option<u32>) {
+    let mut loc = (None, None, None);
+    for field in value.trim().trim_start_matches('{').trim_end_matches('}').split(',') {
+        match field.split_once(':').map(|(key, value)| (key.trim(), scalar(value))) {
+            Some(("File", file)) => loc.0 = Some(file),
+            Some(("Line", line)) => loc.1 = line.parse().ok(),
+            Some(("Column", column)) => loc.2 = column.parse().ok(),
+            _ => {}
+        }
+    }
+    loc
+}
+
+/// The remarks of a `-pass-remarks-output` YAML stream, in order.
+pub fn parse_remarks(text: &str) -> Vec<Remark> {
+    let start_re = Regex::new(r"^--- !(\w+)").unwrap();
+    let key_re = Regex::new(r"^(\s*)(- )?(\w+):\s*(.*)$").unwrap();
+    let mut remarks = vec![];
+    let mut current: Option<Remark> = None;
+    // a scalar continued on the next, more indented line goes to the last `Args` value
+    let mut in_args = false;
+    for line in text.lines() {
+        if let Some(caps) = start_re.captures(line) {
+            remarks.extend(current.take());
+            current = Some(Remark { kind: caps[1].to_string(), ..Default::default() });
+            in_args = false;
+            continue;
+        }
+        let Some(remark) = current.as_mut() else { continue };
+        if line == "..." {
+            remarks.extend(current.take());
+            continue;
+        }
+        match key_re.captures(line) {
+            Some(caps) if caps[1].is_empty() => {
+                let value = &caps[4];
+                in_args = &caps[3] == "Args";
+                match &caps[3] {
+                    "Pass" => remark.pass = scalar(value),
+                    "Name" => remark.name = scalar(value),
+                    "Function" => remark.function = scalar(value),
+                    "DebugLoc" => (remark.file, remark.line, remark.column) = debug_loc(value),
+                    _ => {}
+                }
+            }
+            // `DebugLoc` and other keys nested under an argument are not part of the message
+            Some(caps) if in_args && caps.get(2).is_some() => remark.message.push_str(&scalar(&caps[4])),
+            Some(_) => {}
+            None if in_args && !line.trim().is_empty() => {
+                remark.message.push(' ');
+                remark.message.push_str(&scalar(line));
+            }
+            None => {}
+        }
+    }
+    remarks.extend(current);
+    remarks
+}
+
+/// The source line and column of every `!N = !DILocation(line: L, column: C, ...)` of an IR file, keyed by `!N`.
+pub fn debug_lines<R: BufRead>(reader: R) -> io::Result<HashMap<String, (u32, u32)>> {
+    let location_re = Regex::new(r"^(!\d+)\s*=\s*(?:distinct\s+)?!DILocation\(line:\s*(\d+)(?:,\s*column:\s*(\d+))?").unwrap();
+    let mut lines = HashMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(caps) = location_re.captures(&line) {
+            let column = caps.get(3).map_or(0, |c| c.as_str().parse().unwrap_or_default());
+            lines.insert(caps[1].to_string(), (caps[2].parse().unwrap_or_default(), column));
+        }
+    }
+    Ok(lines)
+}
+
+/// Where the remarks go: block notes keyed by function and block name, and the indices of the
+/// remarks that name no selected function, have no `DebugLoc` or match no instruction's line.
+#[derive(Clone, Debug, Default)]
+pub struct RemarkPlacement {
+    pub blocks: BTreeMap<(String, String), Vec<usize>>,
+    pub unlocated: Vec<usize>,
+}
+
+/// Places each remark on the first block of its function holding an instruction whose `!dbg`
+/// location is at the remark's line and column, or failing that on its line, using the
+/// `!DILocation`s of the function's module.
+pub fn place_remarks(remarks: &[Remark], functions: &[&Function], lines: &[HashMap<String, (u32, u32)>]) -> RemarkPlacement {
+    let dbg_re = Regex::new(r"!dbg (!\d+)").unwrap();
+    let mut placement = RemarkPlacement::default();
+    for (index, remark) in remarks.iter().enumerate() {
+        let block = functions.iter().find(|f| f.name == remark.function).zip(remark.line).and_then(|(function, line)| {
+            let location = |inst: &str| dbg_re.captures(inst).and_then(|caps| lines.get(function.module)?.get(&caps[1]).copied());
+            let find = |matches: &dyn Fn((u32, u32)) -> bool| function.blocks.iter()
+                .find(|block| block.instructions.iter().any(|inst| location(inst).is_some_and(matches)));
+            let block = find(&|(l, c)| l == line && Some(c) == remark.column).or_else(|| find(&|(l, _)| l == line))?;
+            Some((function.name.to_string(), block.name.to_string()))
+        });
+        match block {
+            Some(key) => placement.blocks.entry(key).or_default().push(index),
+            None => placement.unlocated.push(index),
+        }
+    }
+    placement
+}
@@ -0,0 +1,569 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use std::sync::LazyLock;
+use crate::analysis::{hidden_behind, instruction_count, longest_path, neighborhood, value_definitions};
+use crate::cfg::{edge_kind, Cfg};
+use crate::dom::Dominators;
+use crate::ir::{BasicBlock, Function, Terminator};
+
+/// What `--annotate-dominators` adds to each block label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DominatorNotes {
+    /// The immediate dominator.
+    Idom,
+    /// The immediate dominator and every dominator from the entry down.
+    Full,
+}
+
+/// How `--edge-bundling` reduces clutter in the Graphviz output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeBundling {
+    /// `concentrate=true`: parallel edge segments merge. Honored by `dot` only.
+    Concentrate,
+    /// `splines=ortho`: axis-parallel edges, with the edge labels moved to `xlabel`s since ortho
+    /// routing does not place labels. Honored by `dot`, `neato` and `fdp`.
+    Ortho,
+}
+
+/// The kind of mermaid diagram [`dump_cfg`] writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MermaidKind {
+    #[default]
+    Flowchart,
+    /// `stateDiagram-v2`, with the entry entered from `[*]` and returns leading to `[*]`.
+    State,
+}
+
+/// Options controlling how a function's CFG is rendered.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// Omit the instructions inside basic blocks.
+    pub abbr: bool,
+    /// Keep at most this many instruction lines per block label.
+    pub max_lines: Option<usize>,
+    /// The block to treat as the entry instead of the first one.
+    pub entry_name: Option<String>,
+    /// Prepended to every node id, so several diagrams of the same function can share a document.
+    pub id_prefix: String,
+    /// Fill every node with a color derived from the function name.
+    pub color_by_function: bool,
+    /// Show the function attributes in a note attached to the entry block.
+    pub show_attrs: bool,
+    /// Replace the out-edges of a block with more than this many successors by a single
+    /// edge to a note listing them, so that giant switches stay readable.
+    pub max_successors: Option<usize>,
+    /// Only render the blocks within this many hops of the named block, which is highlighted.
+    /// Edges leaving that neighborhood end in placeholders counting the hidden blocks behind them.
+    pub around: Option<(String, usize)>,
+    /// Draw these blocks inside a subgraph with the given title, e.g. the blocks spliced in by an inlining preview.
+    pub cluster: Option<(String, Vec<String>)>,
+    /// Append the dominators of each block to its label.
+    pub dominators: Option<DominatorNotes>,
+    /// Draw each block with the node shape of its terminator, see [`terminator_shape`].
+    pub shape_by_terminator: bool,
+    /// Follow each instruction with a comment telling where the values it uses are defined.
+    pub resolve_values: bool,
+    /// Leave the SSA values out of the instructions, keeping their types, see [`values_as_types`].
+    /// Takes precedence over `resolve_values`.
+    pub value_types: bool,
+    /// Extra lines for the labels of some blocks, keyed by function and block name, e.g. optimization remarks.
+    pub block_notes: BTreeMap<(String, String), Vec<String>>,
+    /// Graphviz attributes bundling the edges of dense CFGs, see [`EdgeBundling`].
+    pub edge_bundling: Option<EdgeBundling>,
+    /// Append a `[in→out]` badge with the number of predecessors and/or successors to each block label.
+    pub predecessor_count: bool,
+    pub successor_count: bool,
+    /// Write a flowchart or a state diagram. Only the flowchart honors the options that add nodes or
+    /// change shapes (`max_successors`, `around`, `cluster`, `shape_by_terminator`, `show_attrs`).
+    pub mermaid_kind: MermaidKind,
+    /// Give the dot nodes per-block analysis attributes, see [`crate::dot::dump_dot`].
+    pub dot_attrs: bool,
+    /// Lay each flowchart out `TD` or `LR` as [`auto_direction`] picks, instead of always `TD`.
+    pub auto_direction: bool,
+    /// Cut edge labels longer than this many characters, see [`truncate_label`]. The full label
+    /// is kept in a `%%` comment (mermaid) or a `comment` attribute (dot).
+    pub max_edge_label: Option<usize>,
+}
+
+/// The opening and closing delimiters of the mermaid node shape for blocks ending with `terminator`:
+///
+/// | terminator | shape |
+/// |---|---|
+/// | `br` (conditional) | diamond `{ }` |
+/// | `switch` | hexagon `{{ }}` |
+/// | `indirectbr` | parallelogram `[/ /]` |
+/// | `invoke`, `callbr` | subroutine `[[ ]]` |
+/// | `ret` | rounded `( )` |
+/// | `unreachable` | asymmetric `> ]` |
+/// | `resume` and the other EH terminators | stadium `([ ])` |
+/// | `br` (unconditional), none | rectangle `[ ]` |
+///
+/// Labels inside these delimiters must be quoted, with their own `"` written as `#quot;`.
+pub fn terminator_shape(terminator: Option<Terminator>) -> (&'static str, &'static str) {
+    match terminator {
+        Some(Terminator::CondBr) => ("{", "}"),
+        Some(Terminator::Switch) => ("{{", "}}"),
+        Some(Terminator::IndirectBr) => ("[/", "/]"),
+        Some(Terminator::Invoke | Terminator::CallBr) => ("[[", "]]"),
+        Some(Terminator::Ret) => ("(", ")"),
+        Some(Terminator::Unreachable) => (">", "]"),
+        Some(Terminator::Resume | Terminator::CatchSwitch | Terminator::CatchRet | Terminator::CleanupRet) => ("([", "])"),
+        Some(Terminator::Br) | None => ("[", "]"),
+    }
+}
+
+/// The flowchart direction suiting the shape of the CFG: `LR` when its widest branch (the most
+/// successors of a block) has more blocks than its longest acyclic path from `entry`, as with a
+/// large `switch`, so that the branches stack vertically; `TD` otherwise, for deep or narrow CFGs.
+pub fn auto_direction(cfg: &Cfg, entry: usize) -> &'static str {
+    let fan_out = cfg.succs.iter().map(Vec::len).max().unwrap_or(0);
+    if fan_out > longest_path(cfg, entry) { "LR" } else { "TD" }
+}
+
+/// The degree badge of block `index` asked for by `options`: `[2→1]` with both counts, `[2→]` with
+/// only the predecessors, `[→1]` with only the successors.
+pub fn degree_badge(cfg: &Cfg, index: usize, options: &RenderOptions) -> Option<String> {
+    if !options.predecessor_count && !options.successor_count {
+        return None;
+    }
+    let count = |shown: bool, blocks: &[usize]| if shown { blocks.len().to_string() } else { String::new() };
+    Some(format!("[{}→{}]", count(options.predecessor_count, &cfg.preds[index]), count(options.successor_count, &cfg.succs[index])))
+}
+
+/// A light fill color derived from a hash of `name`, stable across runs. Hues near green and red
+/// are skipped, as those mark returning and unreachable blocks.
+pub fn function_color(name: &str) -> String {
+    // FNV-1a
+    let hash = name.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    // allowed hues: 30..80 (orange/yellow) and 160..330 (cyan/blue/purple)
+    let slot = (hash % 220) as f64;
+    let hue = if slot < 50.0 { 30.0 + slot } else { 160.0 + slot - 50.0 };
+    let (saturation, lightness) = (0.7, 0.85);
+
+    let c = (1.0 - (2.0 * lightness - 1.0f64).abs()) * saturation;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = lightness - c / 2.0;
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let channel = |v: f64| ((v + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+}
+
+/// The mermaid node id of a block; `name` may carry the leading `%` or not.
+pub fn node_id(prefix: &str, name: &str) -> String {
+    format!("{prefix}%{}", name.strip_prefix('%').unwrap_or(name))
+}
+
+/// Builds the label text of a block, keeping at most `max_lines` instructions.
+pub fn block_label(block: &BasicBlock, max_lines: Option<usize>) -> String {
+    match max_lines {
+        Some(max) if block.instructions.len() > max => {
+            let mut lines: Vec<&str> = block.instructions[..max].iter().map(|s| s.as_ref()).collect();
+            let more = format!("  ... ({} more lines)", block.instructions.len() - max);
+            lines.push(&more);
+            lines.join("\n")
+        }
+        _ => block.instructions.join("\n"),
+    }
+}
+
+/// A local or global name, `%x`, `@f` or quoted, `%"x y"`.
+static VALUE_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r#"[%@](?:"[^"]*"|[-\w.$]+)"#).unwrap());
+/// A local name with the space before it.
+static LOCAL_OPERAND_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r#"\s?%(?:"[^"]*"|[-\w.$]+)"#).unwrap());
+/// The commas left before other commas, closing brackets or the end once operands are removed.
+static DANGLING_COMMAS_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"(,\s*)+(,|\]|\)|$)").unwrap());
+
+/// `line` followed by a comment telling where each value it uses is defined, e.g.
+/// `%5 = mul i32 %3, %a ; %3 = add in %bb1, %a = argument`. Names missing from `definitions`
+/// (block labels, named types) are skipped, globals and functions are marked `global`.
+pub fn resolve_values(line: &str, definitions: &HashMap<String, String>) -> String {
+    let uses = match line.trim_start().split_once(" = ") {
+        Some((lhs, rhs)) if lhs.starts_with('%') && !lhs.contains(' ') => rhs,
+        _ => line,
+    };
+    let uses = uses.split_once(" ; ").map_or(uses, |(code, _)| code);
+    let mut notes: Vec<String> = vec![];
+    for value in VALUE_RE.find_iter(uses).map(|m| m.as_str()) {
+        let note = match definitions.get(value) {
+            Some(definition) => format!("{value} = {definition}"),
+            None if value.starts_with('@') => format!("{value} = global"),
+            None => continue,
+        };
+        if !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+    if notes.is_empty() {
+        line.to_string()
+    } else {
+        format!("{line} ; {}", notes.join(", "))
+    }
+}
+
+/// `line` with the SSA values of the function (the keys of `definitions`) left out, keeping their
+/// types: `%v = store i32 %x, ptr %p` becomes `store i32, ptr`. Globals, constants and block labels
+/// stay. A heuristic for comparing functions that only differ in value numbering.
+pub fn values_as_types(line: &str, definitions: &HashMap<String, String>) -> String {
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let code = match line.trim_start().split_once(" = ") {
+        Some((lhs, rhs)) if definitions.contains_key(lhs) => rhs,
+        _ => line.trim_start(),
+    };
+    let (code, comment) = code.split_once(" ; ").map_or((code, None), |(code, comment)| (code, Some(comment)));
+    let stripped = LOCAL_OPERAND_RE.replace_all(code, |caps: &regex::Captures| {
+        if definitions.contains_key(caps[0].trim_start()) { String::new() } else { caps[0].to_string() }
+    });
+    // the commas and brackets left around operands without a type
+    let stripped = DANGLING_COMMAS_RE.replace_all(&stripped, |caps: &regex::Captures| {
+        if caps[2].is_empty() { String::new() } else if &caps[2] == "," { ",".to_string() } else { format!(" {}", &caps[2]) }
+    });
+    let stripped = stripped.replace("[,", "[").replace("(,", "(").replace("( ", "(").replace(" )", ")");
+    match comment {
+        Some(comment) => format!("{indent}{stripped} ; {comment}"),
+        None => format!("{indent}{stripped}"),
+    }
+}
+
+/// The markdown heading text of a function's section.
+pub fn function_heading(function: &Function) -> String {
+    format!("function {}", function.name)
+}
+
+/// The anchor GitHub generates for a heading: lowercased, punctuation dropped, spaces turned into hyphens.
+pub fn heading_slug(text: &str) -> String {
+    text.trim().to_lowercase().chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// Anchors of a sequence of headings, suffixing repeats with `-1`, `-2`, ... like GitHub does.
+pub fn heading_slugs<S: AsRef<str>>(headings: &[S]) -> Vec<String> {
+    let mut used = HashSet::new();
+    headings.iter().map(|heading| {
+        let base = heading_slug(heading.as_ref());
+        let mut slug = base.clone();
+        let mut n = 0;
+        while !used.insert(slug.clone()) {
+            n += 1;
+            slug = format!("{base}-{n}");
+        }
+        slug
+    }).collect()
+}
+
+/// HTML anchor ids for the sections of `functions`: `fn-` and the name with every character other
+/// than ASCII letters, digits, `_` and `-` replaced by `-`, suffixed with `-1`, `-2`, ... on repeats.
+pub fn function_anchors(functions: &[&Function]) -> Vec<String> {
+    let mut used = HashSet::new();
+    functions.iter().map(|function| {
+        let base: String = function.name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' }).collect();
+        let base = format!("fn-{base}");
+        let mut id = base.clone();
+        let mut n = 0;
+        while !used.insert(id.clone()) {
+            n += 1;
+            id = format!("{base}-{n}");
+        }
+        id
+    }).collect()
+}
+
+/// Writes a bullet list linking to the section of each function, in the given order: to the
+/// heading, or with `anchors` to the `<a id>` of [`function_anchors`] written before it.
+pub fn write_toc(output: &mut dyn Write, functions: &[&Function], anchors: bool) -> io::Result<()> {
+    let headings: Vec<String> = functions.iter().map(|f| function_heading(f)).collect();
+    let targets = if anchors { function_anchors(functions) } else { heading_slugs(&headings) };
+    for (function, slug) in functions.iter().zip(targets) {
+        writeln!(output, "- [{}](#{slug}) — {} blocks, {} instructions", function.name, function.blocks.len(), instruction_count(function))?;
+    }
+    writeln!(output)
+}
+
+/// The default layout of the markdown document: what is written without `--output-template`.
+pub const DEFAULT_TEMPLATE: &str = "{toc}{diagrams}";
+
+static PLACEHOLDER_RE: LazyLock<regex::Regex> = LazyLock::new(|| regex::Regex::new(r"\{(\w+)\}").unwrap());
+
+/// Replaces each `{name}` of `template` by the value of `name`. Placeholders without a value are
+/// an error listing the supported ones.
+pub fn fill_template(template: &str, values: &[(&str, &str)]) -> Result<String, String> {
+    if let Some(unknown) = PLACEHOLDER_RE.captures_iter(template).find(|c| !values.iter().any(|(name, _)| *name == &c[1])) {
+        let supported: Vec<String> = values.iter().map(|(name, _)| format!("{{{name}}}")).collect();
+        return Err(format!("unknown placeholder {} in template; supported: {}", &unknown[0], supported.join(", ")));
+    }
+    Ok(PLACEHOLDER_RE.replace_all(template, |c: &regex::Captures| {
+        values.iter().find(|(name, _)| *name == &c[1]).map(|(_, value)| value.to_string()).unwrap_or_default()
+    }).into_owned())
+}
+
+/// Writes the text of `function` in a collapsed `<details>` section: the verbatim lines when the
+/// parser kept them, else a reconstruction from the parsed blocks.
+pub fn write_function_ir(output: &mut dyn Write, function: &Function) -> io::Result<()> {
+    writeln!(output, "<details>\n<summary>IR of @{}</summary>\n\n```llvm", function.name)?;
+    if let Some(attrs) = &function.attrs {
+        writeln!(output, "; Function Attrs: {attrs}")?;
+    }
+    if function.raw.is_empty() {
+        writeln!(output, "{}", function.define)?;
+        for (index, block) in function.blocks.iter().enumerate() {
+            if index > 0 {
+                writeln!(output)?;
+            }
+            writeln!(output, "{}:", block.name)?;
+            for instruction in &block.instructions {
+                writeln!(output, "{instruction}")?;
+            }
+        }
+        writeln!(output, "}}")?;
+    } else {
+        for line in &function.raw {
+            writeln!(output, "{line}")?;
+        }
+    }
+    writeln!(output, "```\n\n</details>")
+}
+
+/// The condition under which control goes from `block` to `target` (without `%`): `%c` or `!%c` for
+/// a conditional branch, `%x = 1, 2` for the cases of a switch, the [`edge_kind`] for the other
+/// terminators with several successors, and `None` for plain jumps.
+pub fn branch_condition(block: &BasicBlock, target: &str) -> Option<String> {
+    let kind = edge_kind(block, target);
+    let operand = |n: usize| block.last_instruction()?.split_whitespace().nth(n).map(|op| op.trim_end_matches(','));
+    match kind {
+        "jump" => None,
+        "true" | "false" => {
+            let condition = operand(2).unwrap_or(kind);
+            Some(if kind == "true" { condition.to_string() } else { format!("!{condition}") })
+        }
+        "case" => {
+            let values: Vec<&str> = block.instructions.iter()
+                .filter_map(|line| line.trim().strip_suffix(&format!("label %{target}")))
+                .filter_map(|case| case.trim_end().strip_suffix(',')?.split_whitespace().last())
+                .collect();
+            Some(format!("{} = {}", operand(2).unwrap_or("case"), values.join(", ")))
+        }
+        kind => Some(kind.to_string()),
+    }
+}
+
+/// `label` cut to `max` characters, the last of them an ellipsis, or `None` when it is short enough.
+pub fn truncate_label(label: &str, max: Option<usize>) -> Option<String> {
+    let max = max?;
+    if label.chars().count() <= max {
+        return None;
+    }
+    let mut cut: String = label.chars().take(max.saturating_sub(1)).collect();
+    cut.push('…');
+    Some(cut)
+}
+
+/// The edge label to draw for `label`; a cut one is preceded by a comment line holding it in full.
+fn edge_label(output: &mut dyn Write, label: String, options: &RenderOptions) -> String {
+    match truncate_label(&label, options.max_edge_label) {
+        Some(cut) => {
+            _ = writeln!(output, "%% edge label: {label}");
+            cut
+        }
+        None => label,
+    }
+}
+
+/// Writes the CFG of `function` as a mermaid `stateDiagram-v2`. States are named `b0`, `b1`, ...
+/// after the block index, as state ids cannot hold the `%` and `.` of block names, and each
+/// label line is a separate `state : line` description.
+fn dump_state_diagram(output: &mut dyn Write, function: &Function, options: &RenderOptions) -> Vec<(String, usize)> {
+    let mut label_sizes = vec![];
+    let prefix: String = options.id_prefix.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+    let id = |index: usize| format!("{prefix}b{index}");
+    _ = writeln!(output, "```mermaid");
+    _ = writeln!(output, "stateDiagram-v2");
+    _ = writeln!(output, "%% function {}", function.name);
+    let entry = function.entry_index(options.entry_name.as_deref());
+    let cfg = Cfg::new(function);
+    if !cfg.is_empty() {
+        _ = writeln!(output, "    [*] --> {}", id(entry));
+    }
+    for (index, block) in function.blocks.iter().enumerate() {
+        let mut lines = vec![format!("%{}", block.name)];
+        if !options.abbr {
+            let label = block_label(block, options.max_lines);
+            label_sizes.push((format!("%{}", block.name), block_label(block, None).chars().count()));
+            lines.extend(label.lines().map(|l| l.trim().to_string()));
+        }
+        lines.extend(degree_badge(&cfg, index, options));
+        lines.extend(options.block_notes.get(&(function.name.to_string(), block.name.to_string())).into_iter().flatten().cloned());
+        for line in lines.iter().filter(|l| !l.is_empty()) {
+            // `;` ends a statement and `%%` starts a comment, even inside a description
+            _ = writeln!(output, "    {} : {}", id(index), line.replace(';', "#59;").replace("%%", "%#37;"));
+        }
+        for &succ in &cfg.succs[index] {
+            match branch_condition(block, &function.blocks[succ].name) {
+                Some(condition) => {
+                    let condition = edge_label(output, condition, options);
+                    _ = writeln!(output, "    {} --> {} : {}", id(index), id(succ), condition.replace(';', "#59;"));
+                }
+                None => _ = writeln!(output, "    {} --> {}", id(index), id(succ)),
+            }
+        }
+        if block.terminator() == Some(Terminator::Ret) {
+            _ = writeln!(output, "    {} --> [*]", id(index));
+        }
+    }
+    _ = writeln!(output, "```");
+    label_sizes
+}
+
+/// Writes the CFG of `function` in mermaid format, returning the label length of each rendered block
+/// before truncation to `max_lines`.
+pub fn dump_cfg(output: &mut dyn Write, function: &Function, options: &RenderOptions) -> Vec<(String, usize)> {
+    if options.mermaid_kind == MermaidKind::State {
+        return dump_state_diagram(output, function, options);
+    }
+    let mut label_sizes = vec![];
+    _ = writeln!(output, "```mermaid");
+    let entry = function.entry_index(options.entry_name.as_deref());
+    let cfg = Cfg::new(function);
+    _ = writeln!(output, "flowchart {}", if options.auto_direction { auto_direction(&cfg, entry) } else { "TD" });
+    _ = writeln!(output, "%% function {}", function.name);
+    if let (true, Some(attrs), Some(entry_block)) = (options.show_attrs, &function.attrs, function.blocks.get(entry)) {
+        let attrs_id = format!("{}attrs", options.id_prefix);
+        _ = writeln!(output, "{attrs_id}([\"attrs: {attrs}\"]) -.- {}", node_id(&options.id_prefix, &entry_block.name));
+        if attrs.split_whitespace().any(|a| a == "noreturn") {
+            _ = writeln!(output, "style {attrs_id} stroke:#f00,stroke-dasharray:3");
+        }
+    }
+    let collapsed: Vec<bool> = cfg.succs.iter().map(|succs| options.max_successors.is_some_and(|max| succs.len() > max)).collect();
+    let focus = options.around.as_ref().and_then(|(name, hops)| Some((function.block_index(name)?, *hops)));
+    let visible = match focus {
+        Some((center, hops)) => neighborhood(&cfg, center, hops),
+        None => vec![true; cfg.len()],
+    };
+    let doms = options.dominators.filter(|_| !cfg.is_empty()).map(|notes| (notes, Dominators::new(&cfg, entry)));
+    let dominator_note = |index: usize| -> Option<String> {
+        let (notes, doms) = doms.as_ref()?;
+        let name = |b: usize| format!("%{}", function.blocks[b].name);
+        let idom = match doms.idom[index] {
+            _ if !doms.is_reachable(index) => "unreachable".to_string(),
+            Some(idom) => name(idom),
+            None => "none".to_string(),
+        };
+        let mut note = format!("idom: {idom}");
+        if *notes == DominatorNotes::Full && doms.is_reachable(index) {
+            let all: Vec<String> = doms.dominator_path(index).into_iter().map(name).collect();
+            note.push_str(&format!("\ndoms: {}", all.join(", ")));
+        }
+        Some(note)
+    };
+    let definitions = (options.resolve_values || options.value_types).then(|| value_definitions(function));
+    let hidden_id = |index: usize, side: &str| format!("{}__hidden_{side}", node_id(&options.id_prefix, &function.blocks[index].name));
+    function.blocks.iter().enumerate().filter(|(index, _)| visible[*index]).for_each(|(index, block)| {
+        let block_name = &node_id(&options.id_prefix, &block.name);
+        // the `; preds =` order first, then the edges only known from parsed successors
+        let extra_preds = cfg.preds[index].iter().map(|&p| &function.blocks[p].name)
+            .filter(|p| !block.predecessors.iter().any(|q| q.strip_prefix('%').unwrap_or(q) == p.as_ref()));
+        block.predecessors.iter().chain(extra_preds)
+            .filter(|src| !function.block_index(src).is_some_and(|src| collapsed[src]))
+            .for_each(|src_name| {
+                let src = match function.block_index(src_name) {
+                    Some(src) if !visible[src] => hidden_id(src, "in"),
+                    _ => node_id(&options.id_prefix, src_name),
+                };
+                // mark the single-block loops, which mermaid draws as a short curve hard to spot
+                let marker = if function.block_index(src_name) == Some(index) { "↻ " } else { "" };
+                let label = edge_label(output, format!("{marker}%{}", block.name), options);
+                _ = writeln!(output, "\t{src} -->|{label}| {block_name}");
+            });
+        if !collapsed[index] {
+            for &succ in cfg.succs[index].iter().filter(|&&s| !visible[s]) {
+                let label = edge_label(output, format!("%{}", function.blocks[succ].name), options);
+                _ = writeln!(output, "\t{block_name} -->|{label}| {}", hidden_id(succ, "out"));
+            }
+        }
+        if collapsed[index] {
+            let kind = if block.terminator() == Some(Terminator::Switch) { "cases" } else { "successors" };
+            let targets: Vec<String> = cfg.succs[index].iter().map(|&s| format!("%{}", function.blocks[s].name)).collect();
+            _ = writeln!(output, "\t{block_name} --> {block_name}__succs[\"{} ({} {kind}, {} targets)\n{}\"]",
+                block.terminator().map_or("terminator", |t| t.as_str()), block.successors.len(), targets.len(), targets.join(", "));
+            _ = writeln!(output, "style {block_name}__succs stroke-dasharray:3");
+        }
+        let extra_notes = options.block_notes.get(&(function.name.to_string(), block.name.to_string()));
+        let label = if !options.abbr {
+            let rewritten = definitions.as_ref().map(|definitions| {
+                let rewrite = if options.value_types { values_as_types } else { resolve_values };
+                BasicBlock { instructions: block.instructions.iter().map(|i| rewrite(i, definitions).into()).collect(), ..block.clone() }
+            });
+            let block = rewritten.as_ref().unwrap_or(block);
+            // untruncated, so that blocks cut to `max_lines` still count as large
+            label_sizes.push((format!("%{}", block.name), block_label(block, None).chars().count()));
+            Some(block_label(block, options.max_lines))
+        } else if options.dominators.is_some() || options.shape_by_terminator || extra_notes.is_some() || options.predecessor_count || options.successor_count {
+            Some(format!("%{}", block.name))
+        } else {
+            None
+        };
+        if let Some(mut label) = label {
+            if let Some(badge) = degree_badge(&cfg, index, options) {
+                label.push_str(&format!("\n{badge}"));
+            }
+            if let Some(note) = dominator_note(index) {
+                label.push_str(&format!("\n{note}"));
+            }
+            for note in extra_notes.into_iter().flatten() {
+                label.push_str(&format!("\n{}", note.replace('"', "#quot;")));
+            }
+            if options.shape_by_terminator {
+                let (open, close) = terminator_shape(block.terminator());
+                _ = writeln!(output, "{block_name}{open}\"{}\"{close}", label.replace('"', "#quot;"));
+            } else {
+                _ = writeln!(output, "{}[\"{}\"]", block_name, label);
+            }
+        }
+        let is_return = block.instructions.last().iter().any(|s| s.trim().starts_with("ret "));
+        let is_unreachable = block.instructions.last().iter().any(|s| s.trim().starts_with("unreachable"));
+        if options.color_by_function {
+            _ = writeln!(output, "style {block_name} fill:{}", function_color(&function.name));
+        }
+        if index == entry {
+            _ = writeln!(output, "style {block_name} stroke-width:3px");
+        }
+        if is_return {
+            _ = writeln!(output, "style {block_name} stroke:#0f0");
+        }
+        if is_unreachable {
+            _ = writeln!(output, "style {block_name} stroke:#f00");
+        }
+        if focus.is_some_and(|(center, _)| center == index) {
+            _ = writeln!(output, "style {block_name} fill:#ffe08a,stroke-width:4px");
+        }
+    });
+    if focus.is_some() {
+        // one placeholder per hidden block adjacent to the neighborhood, on each side it is seen from
+        for (hidden, forward) in (0..cfg.len()).filter(|&b| !visible[b]).flat_map(|b| [(b, true), (b, false)]) {
+            let adjacent = if forward { &cfg.preds[hidden] } else { &cfg.succs[hidden] };
+            if adjacent.iter().any(|&b| visible[b] && !(forward && collapsed[b])) {
+                let id = hidden_id(hidden, if forward { "out" } else { "in" });
+                _ = writeln!(output, "{id}([\"… {} hidden blocks\"])", hidden_behind(&cfg, hidden, &visible, forward));
+                _ = writeln!(output, "style {id} stroke-dasharray:3");
+            }
+        }
+    }
+    if let Some((title, members)) = &options.cluster {
+        let cluster_id = format!("{}cluster", options.id_prefix);
+        _ = writeln!(output, "subgraph {cluster_id} [\"{title}\"]");
+        for name in members.iter().filter(|name| function.block_index(name).is_some_and(|index| visible[index])) {
+            _ = writeln!(output, "\t{}", node_id(&options.id_prefix, name));
+        }
+        _ = writeln!(output, "end");
+        _ = writeln!(output, "style {cluster_id} fill:#eef4ff,stroke:#36c,stroke-dasharray:4");
+    }
+    _ = writeln!(output, "```");
+    label_sizes
+}
@@ -0,0 +1,151 @@
+//! Writing one document per function, either as loose files in a directory or as the entries
+//! of a single zip archive.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the index listing the per-function documents.
+pub const INDEX: &str = "index.md";
+
+/// The file names of the per-function documents: the function name with every character
+/// other than ASCII letters, digits, `.`, `_` and `-` replaced by `_`, then `extension`.
+/// Names that collide after the replacement, or with [`INDEX`], get a `-1`, `-2`, ... suffix.
+pub fn split_file_names(functions: &[&str], extension: &str) -> Vec<String> {
+    unique_paths(functions.iter().map(|name| (String::new(), sanitize(name))), extension)
+}
+
+/// Like [`split_file_names`], but each file goes into a subdirectory per namespace of the function,
+/// see [`namespaces`]: `_ZN4llvm3cfg4walkEv` becomes `llvm/cfg/_ZN4llvm3cfg4walkEv.md`.
+pub fn nested_file_names(functions: &[&str], extension: &str) -> Vec<String> {
+    unique_paths(functions.iter().map(|name| {
+        let dir: String = namespaces(name).iter().map(|n| sanitize(n) + "/").collect();
+        (dir, sanitize(name))
+    }), extension)
+}
+
+fn sanitize(name: &str) -> String {
+    let base: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '_' })
+        .collect();
+    if base.is_empty() || base.starts_with('.') { format!("_{base}") } else { base }
+}
+
+fn unique_paths(paths: impl Iterator<Item = (String, String)>, extension: &str) -> Vec<String> {
+    let mut used = HashSet::from([INDEX.to_string()]);
+    paths.map(|(dir, base)| {
+        let mut file = format!("{dir}{base}.{extension}");
+        let mut n = 0;
+        while !used.insert(file.clone()) {
+            n += 1;
+            file = format!("{dir}{base}-{n}.{extension}");
+        }
+        file
+    }).collect()
+}
+
+/// The namespaces (and classes) enclosing a function: the components before the last of an Itanium
+/// nested name (`_ZN...E`, also used by Rust's legacy mangling, whose trailing hash is dropped), or
+/// of a demangled name with `::` separators. Empty for names that are neither.
+pub fn namespaces(name: &str) -> Vec<String> {
+    if let Some(mut rest) = name.strip_prefix("_ZN") {
+        rest = rest.trim_start_matches(['r', 'V', 'K', 'R', 'O']);
+        let mut components = vec![];
+        // constructors, destructors and operators end a nested name without a component of their own
+        let mut ends_in_function = false;
+        loop {
+            if let Some(after) = rest.strip_prefix("St") {
+                components.push("std".to_string());
+                rest = after;
+                continue;
+            }
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                ends_in_function = rest.starts_with(['E', 'I']);
+                break;
+            }
+            let Some(component) = rest[digits..].get(..rest[..digits].parse().unwrap_or(usize::MAX)) else { break };
+            components.push(component.to_string());
+            rest = &rest[digits + component.len()..];
+        }
+        let is_hash = |c: &String| c.len() == 17 && c.starts_with('h') && c[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if ends_in_function && components.last().is_some_and(is_hash) {
+            components.pop();
+        }
+        if ends_in_function {
+            components.pop();
+        }
+        return components;
+    }
+    // `::` inside template arguments and parameter lists does not separate namespaces
+    let mut components = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (index, c) in name.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ':' if depth == 0 && name[index..].starts_with("::") && index > start => {
+                components.push(name[start..index].to_string());
+                start = index + 2;
+            }
+            _ => {}
+        }
+    }
+    components
+}
+
+/// Whether `path` names a zip archive rather than a directory.
+pub fn is_zip(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+/// Where the per-function documents go.
+pub enum SplitOutput {
+    Directory(PathBuf),
+    #[cfg(feature = "zip")]
+    Zip(Box<zip::ZipWriter<fs::File>>),
+}
+
+impl SplitOutput {
+    /// Creates the directory, or the archive when `path` ends in `.zip`.
+    pub fn create(path: &Path) -> io::Result<SplitOutput> {
+        if !is_zip(path) {
+            fs::create_dir_all(path)?;
+            return Ok(SplitOutput::Directory(path.to_path_buf()));
+        }
+        #[cfg(feature = "zip")]
+        return Ok(SplitOutput::Zip(Box::new(zip::ZipWriter::new(fs::File::create(path)?))));
+        #[cfg(not(feature = "zip"))]
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+            format!("cannot write {}: this build lacks the zip cargo feature", path.display())))
+    }
+
+    /// Writes the file or archive entry `name`.
+    pub fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        match self {
+            SplitOutput::Directory(dir) => {
+                let path = dir.join(name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, contents)
+            }
+            #[cfg(feature = "zip")]
+            SplitOutput::Zip(archive) => {
+                let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+                archive.start_file(name, options).map_err(io::Error::other)?;
+                io::Write::write_all(archive, contents)
+            }
+        }
+    }
+
+    /// Writes the archive's central directory; directories need nothing more.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            SplitOutput::Directory(_) => Ok(()),
+            #[cfg(feature = "zip")]
+            SplitOutput::Zip(archive) => archive.finish().map(drop).map_err(io::Error::other),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Line diff based on Myers' O(ND) algorithm, in its linear space variant.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffOp<'a> {
+    Equal(&'a str),
+    Insert(&'a str),
+    Delete(&'a str),
+}
+
+/// Beyond this many edits the diff degrades to "delete everything, insert everything".
+const MAX_EDITS: usize = 10_000;
+
+/// Computes a shortest edit script turning `old` into `new`.
+pub fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffOp<'a>> {
+    let (prefix, suffix) = common_ends(old, new);
+    let (inner_old, inner_new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let mut ops: Vec<DiffOp> = old[..prefix].iter().map(|l| DiffOp::Equal(l)).collect();
+    if inner_old.is_empty() || inner_new.is_empty() || middle_snake(inner_old, inner_new, MAX_EDITS).is_some() {
+        diff_into(inner_old, inner_new, &mut ops);
+    } else {
+        ops.extend(inner_old.iter().map(|l| DiffOp::Delete(l)));
+        ops.extend(inner_new.iter().map(|l| DiffOp::Insert(l)));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| DiffOp::Equal(l)));
+    ops
+}
+
+/// The number of lines `old` and `new` share at their start and, of the rest, at their end.
+fn common_ends(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    (prefix, suffix)
+}
+
+/// Appends a shortest edit script of `old` into `new` to `ops`, splitting both at the middle snake.
+fn diff_into<'a>(old: &[&'a str], new: &[&'a str], ops: &mut Vec<DiffOp<'a>>) {
+    let (prefix, suffix) = common_ends(old, new);
+    ops.extend(old[..prefix].iter().map(|l| DiffOp::Equal(l)));
+    let (old_rest, new_rest) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    if old_rest.is_empty() {
+        ops.extend(new_rest.iter().map(|l| DiffOp::Insert(l)));
+    } else if new_rest.is_empty() {
+        ops.extend(old_rest.iter().map(|l| DiffOp::Delete(l)));
+    } else {
+        let ((x, y), (u, v)) = middle_snake(old_rest, new_rest, usize::MAX).unwrap();
+        diff_into(&old_rest[..x], &new_rest[..y], ops);
+        ops.extend(old_rest[x..u].iter().map(|l| DiffOp::Equal(l)));
+        diff_into(&old_rest[u..], &new_rest[v..], ops);
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| DiffOp::Equal(l)));
+}
+
+/// The start and end of the snake halfway along a shortest edit script, found by searching from both ends at
+/// once and keeping only the furthest point reached on each diagonal. None when the script needs more than
+/// `limit` edits.
+fn middle_snake(old: &[&str], new: &[&str], limit: usize) -> Option<((usize, usize), (usize, usize))> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // the furthest x on each diagonal k = x - y, forward from the start and backward from the end
+    let mut forward = vec![0isize; 2 * max as usize + 3];
+    let mut backward = vec![0isize; 2 * max as usize + 3];
+
+    for d in 0..=max {
+        if d > 0 && 2 * d as usize - 1 > limit {
+            return None;
+        }
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && forward[idx - 1] < forward[idx + 1]) { forward[idx + 1] } else { forward[idx - 1] + 1 };
+            let start = (x, x - k);
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[idx] = x;
+            let reverse = delta - k;
+            if odd && (-(d - 1)..=d - 1).contains(&reverse) && x + backward[(reverse + offset) as usize] >= n {
+                return Some(((start.0 as usize, start.1 as usize), (x as usize, y as usize)));
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && backward[idx - 1] < backward[idx + 1]) { backward[idx + 1] } else { backward[idx - 1] + 1 };
+            let start = (x, x - k);
+            let mut y = x - k;
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[idx] = x;
+            let reverse = delta - k;
+            if !odd && (-d..=d).contains(&reverse) && x + forward[(reverse + offset) as usize] >= n {
+                if 2 * d as usize > limit {
+                    return None;
+                }
+                return Some((((n - x) as usize, (m - y) as usize), ((n - start.0) as usize, (m - start.1) as usize)));
+            }
+        }
+    }
+    unreachable!("a shortest edit script has at most {} edits", n + m)
+}
@@ -0,0 +1,120 @@
+//! Cheap well-formedness checks over parsed functions.
+
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::ir::{opcode, Function, Terminator};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The block holds no instruction at all.
+    EmptyBlock,
+    /// The last instruction of the block is not a terminator.
+    MissingTerminator,
+    /// Instructions follow the block's terminator.
+    InstructionAfterTerminator,
+    /// The incoming blocks of a `phi` differ from the predecessors of its block.
+    PhiIncomingMismatch {
+        /// Predecessors the `phi` has no incoming value for.
+        missing: Vec<String>,
+        /// Incoming blocks that are not predecessors.
+        extra: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Violation {
+    pub block: String,
+    pub kind: ViolationKind,
+    /// The offending instruction, when there is one.
+    pub instruction: Option<String>,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match &self.kind {
+            ViolationKind::EmptyBlock => "empty block".to_string(),
+            ViolationKind::MissingTerminator => "does not end with a terminator".to_string(),
+            ViolationKind::InstructionAfterTerminator => "instruction after the terminator".to_string(),
+            ViolationKind::PhiIncomingMismatch { missing, extra } => {
+                let mut parts = vec![];
+                if !missing.is_empty() {
+                    parts.push(format!("missing incoming for {}", missing.join(", ")));
+                }
+                if !extra.is_empty() {
+                    parts.push(format!("incoming from non-predecessors {}", extra.join(", ")));
+                }
+                format!("phi {}", parts.join("; "))
+            }
+        };
+        write!(f, "%{}: {message}", self.block)?;
+        if let Some(inst) = &self.instruction {
+            write!(f, ": `{}`", inst.trim())?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every block ends with exactly one terminator.
+pub fn verify_terminators(function: &Function) -> Vec<Violation> {
+    let mut violations = vec![];
+    for block in &function.blocks {
+        let violation = |kind, instruction: Option<&str>| Violation {
+            block: block.name.to_string(),
+            kind,
+            instruction: instruction.map(str::to_string),
+        };
+        let instructions: Vec<&str> = block.instructions.iter().map(|i| i.as_ref()).filter(|i| opcode(i).is_some()).collect();
+        let Some(&last) = instructions.last() else {
+            violations.push(violation(ViolationKind::EmptyBlock, None));
+            continue;
+        };
+        match instructions.iter().position(|i| Terminator::classify(i).is_some()) {
+            None => violations.push(violation(ViolationKind::MissingTerminator, Some(last))),
+            Some(at) if at + 1 < instructions.len() =>
+                violations.push(violation(ViolationKind::InstructionAfterTerminator, Some(instructions[at + 1]))),
+            Some(_) => {}
+        }
+    }
+    violations
+}
+
+static INCOMING_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r",\s*%([-\w.$]+)\s*\]").unwrap());
+
+/// The blocks named by the `[ value, %block ]` pairs of a `phi`.
+pub fn phi_incoming_blocks(phi: &str) -> Vec<&str> {
+    INCOMING_RE.captures_iter(phi).map(|c| c.get(1).unwrap().as_str()).collect()
+}
+
+/// Checks that the incoming blocks of every `phi` are exactly the predecessors of its block,
+/// as computed from the parsed successors (the `; preds =` comments are not trusted).
+pub fn verify_phis(function: &Function) -> Vec<Violation> {
+    let mut violations = vec![];
+    for block in &function.blocks {
+        let preds: BTreeSet<&str> = function.blocks.iter()
+            .filter(|b| b.successors.iter().any(|s| s.strip_prefix('%').unwrap_or(s) == block.name))
+            .map(|b| b.name.as_ref())
+            .collect();
+        for phi in block.instructions.iter().filter(|i| opcode(i) == Some("phi")) {
+            let incoming: BTreeSet<&str> = phi_incoming_blocks(phi).into_iter().collect();
+            let percent = |names: Vec<&&str>| names.into_iter().map(|n| format!("%{n}")).collect::<Vec<_>>();
+            let missing = percent(preds.difference(&incoming).collect());
+            let extra = percent(incoming.difference(&preds).collect());
+            if !missing.is_empty() || !extra.is_empty() {
+                violations.push(Violation {
+                    block: block.name.to_string(),
+                    kind: ViolationKind::PhiIncomingMismatch { missing, extra },
+                    instruction: Some(phi.to_string()),
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Runs every check on `function`.
+pub fn verify(function: &Function) -> Vec<Violation> {
+    let mut violations = verify_terminators(function);
+    violations.extend(verify_phis(function));
+    violations
+}
@@ -0,0 +1,79 @@
+use learning_llvm_tools::analysis::{function_metrics, function_metrics_with_trip_count, irreducible_regions, is_debug_intrinsic, opcode_histogram, without_debug_intrinsics};
+use learning_llvm_tools::cfg::Cfg;
+use learning_llvm_tools::dom::Dominators;
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = "\
+define i32 @f(i32 %x) !dbg !4 {
+entry:
+  %x.addr = alloca i32, align 4
+  call void @llvm.dbg.declare(metadata ptr %x.addr, metadata !9, metadata !DIExpression()), !dbg !10
+  store i32 %x, ptr %x.addr, align 4
+  call void @llvm.dbg.value(metadata i32 %x, metadata !9, metadata !DIExpression()), !dbg !10
+  %r = call i32 @llvm.dbg.helper(i32 %x)
+  ret i32 %r, !dbg !11
+}
+";
+
+#[test]
+fn debug_intrinsics_are_left_out_of_counts() {
+    let module = parse_ll_str(IR);
+    let function = &module.functions[0];
+    assert!(is_debug_intrinsic("  call void @llvm.dbg.label(metadata !12), !dbg !13"));
+    assert!(!is_debug_intrinsic(&function.blocks[0].instructions[4]));
+    assert_eq!(function_metrics(function, 0).instructions, 6);
+    let stripped = without_debug_intrinsics(function);
+    assert_eq!(function_metrics(&stripped, 0).instructions, 4);
+    assert_eq!(opcode_histogram(&[&stripped], None).get("call"), Some(&1));
+}
+
+#[test]
+fn trip_count_weights_loop_blocks_by_depth() {
+    let module = parse_ll_str("\
+define void @g(i1 %c) {
+entry:
+  br label %loop
+
+loop:
+  %i = phi i32 [ 0, %entry ], [ %j, %loop ]
+  %j = add i32 %i, 1
+  br i1 %c, label %loop, label %exit
+
+exit:
+  ret void
+}
+");
+    let function = &module.functions[0];
+    assert_eq!(function_metrics(function, 0).weighted_instructions, 5);
+    assert_eq!(function_metrics_with_trip_count(function, 0, 10).weighted_instructions, 1 + 3 * 10 + 1);
+}
+
+#[test]
+fn cycles_entered_at_several_blocks_are_irreducible() {
+    let module = parse_ll_str("\
+define void @f(i1 %c) {
+entry:
+  br i1 %c, label %a, label %b
+
+a:
+  br i1 %c, label %b, label %exit
+
+b:
+  br i1 %c, label %a, label %exit
+
+exit:
+  br label %loop
+
+loop:
+  br i1 %c, label %loop, label %done
+
+done:
+  ret void
+}
+");
+    let cfg = Cfg::new(&module.functions[0]);
+    let regions = irreducible_regions(&cfg, &Dominators::new(&cfg, 0));
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].blocks, [1, 2]);
+    assert_eq!(regions[0].entries, [1, 2]);
+}
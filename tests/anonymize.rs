@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+use learning_llvm_tools::anonymize::anonymize;
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = r#"; ModuleID = 'vault.c'
+source_filename = "vault.c"
+
+%struct.Credentials = type { ptr, ptr }
+
+@api_token = global [8 x i8] c"hunter2\00"
+
+define i32 @check_password(ptr %password, ptr %user_key) !dbg !3 {
+entry:
+  %creds = alloca %struct.Credentials
+  call void @llvm.dbg.declare(metadata ptr %password, metadata !5, metadata !DIExpression()), !dbg !6
+  %verdict = call i32 @strcmp(ptr %password, ptr @api_token)
+  %is_match = icmp eq i32 %verdict, 0
+  br i1 %is_match, label %grant_access, label %deny
+
+grant_access:                                     ; preds = %entry
+  call void @log_secret_event(ptr %user_key)
+  ret i32 1
+
+deny:                                             ; preds = %entry
+  %0 = add i32 %verdict, 1
+  ret i32 %0
+}
+
+declare void @log_secret_event(ptr %event_payload)
+declare i32 @strcmp(ptr, ptr)
+declare void @llvm.dbg.declare(metadata, metadata, metadata)
+
+!0 = !{!"clang version 17 (internal vault build)"}
+!3 = distinct !DISubprogram(name: "check_password", linkageName: "check_password", scope: null)
+!5 = !DILocalVariable(name: "password", arg: 1, scope: !3)
+!6 = !DILocation(line: 4, scope: !3)
+"#;
+
+#[test]
+fn no_original_identifier_or_string_survives() {
+    let (text, mapping) = anonymize(IR, &["strcmp".to_string()].into_iter().collect::<HashSet<_>>());
+    let secrets = [
+        "vault", "Credentials", "api_token", "hunter2", "check_password", "password", "user_key", "creds", "verdict",
+        "is_match", "grant_access", "deny", "log_secret_event", "event_payload", "clang version", "internal",
+    ];
+    for secret in secrets {
+        assert!(!text.contains(secret), "{secret} survives in\n{text}");
+    }
+
+    // still a module with the same shape, whose names map back
+    let module = parse_ll_str(&text);
+    let f = &module.functions[0];
+    assert_eq!((f.name.as_ref(), f.blocks.len()), ("f1", 3));
+    assert!(text.contains("define i32 @f1(ptr %v1, ptr %v2)"), "{text}");
+    assert!(text.contains("%0 = add i32 %v4, 1"), "{text}");
+    assert!(text.contains("call i32 @strcmp(") && text.contains("@llvm.dbg.declare("));
+    assert_eq!(mapping.values["f1"]["v1"], "password");
+    assert_eq!(mapping.values["f1"]["v4"], "verdict");
+    assert_eq!(mapping.values["f2"]["v1"], "event_payload");
+    assert_eq!(mapping.blocks["f1"]["bb2"], "grant_access");
+}
@@ -0,0 +1,89 @@
+use regex::Regex;
+use learning_llvm_tools::callgraph::{call_target, tail_chain, CallGraph, ChainEnd, DEFAULT_CALL_PATTERN};
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = "\
+declare i32 @halt(i32)
+
+define i32 @start(i32 %x) {
+  %r = musttail call i32 @step(i32 %x)
+  ret i32 %r
+}
+
+define i32 @step(i32 %x) {
+  %y = call i32 @helper(i32 %x)
+  %r = musttail call fastcc i32 @finish(i32 %y)
+  ret i32 %r
+}
+
+define i32 @finish(i32 %x) {
+  %r = musttail call i32 @halt(i32 %x)
+  ret i32 %r
+}
+
+define i32 @helper(i32 %x) {
+  ret i32 %x
+}
+
+define i32 @ping(i32 %x) {
+  %r = musttail call i32 @pong(i32 %x)
+  ret i32 %r
+}
+
+define i32 @pong(i32 %x) {
+  %c = icmp eq i32 %x, 0
+  br i1 %c, label %a, label %b
+a:
+  %r = musttail call i32 @ping(i32 %x)
+  ret i32 %r
+b:
+  %s = musttail call i32 @ping(i32 1)
+  ret i32 %s
+}
+";
+
+#[test]
+fn chains_follow_only_musttail_calls() {
+    let module = parse_ll_str(IR);
+    let chain = tail_chain(&module, "start");
+    assert_eq!(chain.functions, ["start", "step", "finish", "halt"]);
+    assert_eq!(chain.end, ChainEnd::External);
+    assert_eq!(tail_chain(&module, "helper").end, ChainEnd::Done);
+}
+
+#[test]
+fn cycles_end_the_chain() {
+    let module = parse_ll_str(IR);
+    let chain = tail_chain(&module, "ping");
+    assert_eq!(chain.functions, ["ping", "pong"]);
+    assert_eq!(chain.end, ChainEnd::Cycle(0));
+}
+
+#[test]
+fn default_call_pattern_sees_through_bitcasts() {
+    let call_re = Regex::new(DEFAULT_CALL_PATTERN).unwrap();
+    let target = |inst| call_target(&call_re, inst);
+    assert_eq!(target("  call void bitcast (void (i32)* @foo to void ()*)()"), Some("foo"));
+    assert_eq!(target("  %r = tail call i32 bitcast (i32 (i8*)* @bar to i32 (i32*)*)(i32* %p)"), Some("bar"));
+    assert_eq!(target("  invoke void bitcast (void ()* @baz to void (i32)*)(i32 1) to label %ok unwind label %lp"), Some("baz"));
+    assert_eq!(target("  %n = call i32 (i8*, ...) @printf(i8* %fmt, i32 %x)"), Some("printf"));
+    assert_eq!(target("  %r = musttail call fastcc i32 @step(i32 %x)"), Some("step"));
+    // a bitcast among the arguments of a direct call is not the callee
+    assert_eq!(target("  call void @use(i8* bitcast (i32* @g to i8*))"), Some("use"));
+    assert_eq!(target("  call void %fp(i32 1)"), None);
+}
+
+#[test]
+fn custom_call_patterns_replace_the_default() {
+    let module = parse_ll_str("\
+define void @f() {
+  call void @g()
+  %r = call i32 @my.call.wrapper(ptr @h)
+  ret void
+}
+");
+    let graph = CallGraph::new(&module);
+    assert_eq!(graph.nodes, ["f", "g", "my.call.wrapper"]);
+    let graph = CallGraph::with_call_pattern(&module, &Regex::new(r"@my\.call\.wrapper\(ptr @([\w.]+)").unwrap());
+    assert_eq!(graph.nodes, ["f", "h"]);
+}
@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+use learning_llvm_tools::canvas::canvas_json;
+use learning_llvm_tools::parser::parse_ll_str;
+use learning_llvm_tools::render::RenderOptions;
+
+const IR: &str = "\
+define i32 @max(i32 %a, i32 %b) {
+entry:
+  %cmp = icmp sgt i32 %a, %b
+  br i1 %cmp, label %then, label %else
+
+then:                                             ; preds = %entry
+  br label %exit
+
+else:                                             ; preds = %entry
+  br label %exit
+
+exit:                                             ; preds = %else, %then
+  %r = phi i32 [ %a, %then ], [ %b, %else ]
+  ret i32 %r
+
+dead:
+  unreachable
+}
+";
+
+#[test]
+fn canvas_has_the_json_canvas_shape() {
+    let module = parse_ll_str(IR);
+    let canvas = canvas_json(&module.functions[0], &RenderOptions::default());
+
+    let nodes = canvas["nodes"].as_array().expect("nodes array");
+    let edges = canvas["edges"].as_array().expect("edges array");
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(edges.len(), 4);
+
+    let mut ids = HashSet::new();
+    for node in nodes {
+        assert!(ids.insert(node["id"].as_str().expect("string id")), "duplicate node id");
+        assert_eq!(node["type"], "text");
+        assert!(node["text"].is_string());
+        for key in ["x", "y", "width", "height"] {
+            assert!(node[key].is_i64(), "node {key} must be an integer");
+        }
+        assert!(node["width"].as_i64().unwrap() > 0 && node["height"].as_i64().unwrap() > 0);
+    }
+    for edge in edges {
+        assert!(ids.contains(edge["fromNode"].as_str().unwrap()));
+        assert!(ids.contains(edge["toNode"].as_str().unwrap()));
+        assert!(edge["id"].is_string() && edge["label"].is_string());
+    }
+}
+
+#[test]
+fn layers_follow_bfs_depth() {
+    let module = parse_ll_str(IR);
+    let canvas = canvas_json(&module.functions[0], &RenderOptions::default());
+    let y = |id: &str| canvas["nodes"].as_array().unwrap().iter().find(|n| n["id"] == id).unwrap()["y"].as_i64().unwrap();
+
+    assert_eq!(y("%entry"), 0);
+    assert_eq!(y("%then"), y("%else"));
+    assert!(y("%then") > y("%entry"));
+    assert!(y("%exit") > y("%then"));
+    assert!(y("%dead") > y("%exit"));
+}
@@ -0,0 +1,130 @@
+use std::process::Command;
+
+const IR: &str = "\
+define void @f() {
+entry:
+  %a = alloca i32
+  %b = alloca i32
+  br label %exit
+
+exit:
+  ret void
+}
+";
+
+/// Runs ll2cfg on `ir`, written to a temp file, with `args`, returning its stdout.
+fn ll2cfg(name: &str, ir: &str, args: &[&str]) -> String {
+    let input = std::env::temp_dir().join(format!("{name}_{}.ll", std::process::id()));
+    std::fs::write(&input, ir).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ll2cfg")).arg(&input).args(args).output().unwrap();
+    std::fs::remove_file(&input).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn json_lines_are_streamed_untransformed() {
+    let out = ll2cfg("cli_jsonl", IR, &["--format", "jsonl"]);
+    let function: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+    assert_eq!(function["blocks"][0]["instructions"][0], "%a = alloca i32");
+}
+
+#[test]
+fn json_lines_apply_the_transformations() {
+    let out = ll2cfg("cli_jsonl_allocas", IR, &["--format", "jsonl", "--collapse-allocas"]);
+    assert_eq!(out.lines().count(), 1);
+    let function: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+    assert_eq!(function["blocks"][0]["instructions"][0], "; 2 allocas");
+}
+
+#[test]
+fn json_lines_give_way_to_an_analysis_mode() {
+    let out = ll2cfg("cli_jsonl_list", IR, &["--format", "jsonl", "--list-functions"]);
+    assert_eq!(out, "f\t2\t4\t-\n");
+}
+
+const DEBUG_IR: &str = "\
+define i32 @g(i32 %x) {
+entry:
+  call void @llvm.dbg.value(metadata i32 %x, metadata !1, metadata !DIExpression())
+  ret i32 %x
+}
+";
+
+#[test]
+fn template_metrics_leave_out_debug_intrinsics() {
+    let template = std::env::temp_dir().join(format!("cli_metrics_template_{}.md", std::process::id()));
+    std::fs::write(&template, "{metrics}").unwrap();
+    let out = ll2cfg("cli_metrics", DEBUG_IR, &["--output-template", template.to_str().unwrap(), "--ignore-debug"]);
+    std::fs::remove_file(&template).unwrap();
+    assert_eq!(out.lines().nth(2), Some("| g | 1 | 0 | 1 | 0 | 1 | 0 | 0 |"), "{out}");
+}
+
+#[test]
+fn table_of_contents_and_self_loops_leave_out_debug_intrinsics() {
+    let ir = format!("{DEBUG_IR}
+define void @spin() {{
+entry:
+  br label %loop
+
+loop:
+  call void @llvm.dbg.value(metadata i32 0, metadata !1, metadata !DIExpression())
+  %v = load volatile i32, ptr @flag
+  br label %loop
+}}
+");
+    let out = ll2cfg("cli_toc", &ir, &["--ignore-debug"]);
+    assert!(out.starts_with("- [g](#function-g) — 1 blocks, 1 instructions\n- [spin](#function-spin) — 2 blocks, 3 instructions\n"), "{out}");
+    let out = ll2cfg("cli_self_loops", &ir, &["--ignore-debug", "--find-self-loops"]);
+    assert!(out.contains("| spin | %loop | 2 | none |"), "{out}");
+}
+
+#[test]
+fn template_metrics_show_the_fan_in_and_out() {
+    let ir = "\
+define void @h(i32 %x) {
+entry:
+  switch i32 %x, label %done [ i32 0, label %a
+                               i32 1, label %b ]
+a:
+  br label %done
+b:
+  br label %done
+done:
+  ret void
+}
+";
+    let template = std::env::temp_dir().join(format!("cli_fan_template_{}.md", std::process::id()));
+    std::fs::write(&template, "{metrics}").unwrap();
+    let out = ll2cfg("cli_fan", ir, &["--output-template", template.to_str().unwrap()]);
+    std::fs::remove_file(&template).unwrap();
+    assert!(out.starts_with("| function | blocks | edges | instructions | loops | complexity | max fan-in | max fan-out |\n"), "{out}");
+    assert_eq!(out.lines().nth(2), Some("| h | 4 | 5 | 4 | 0 | 3 | 3 | 3 |"), "{out}");
+}
+
+#[test]
+fn no_progress_line_when_stderr_is_piped() {
+    let input = std::env::temp_dir().join(format!("cli_progress_{}.ll", std::process::id()));
+    std::fs::write(&input, IR).unwrap();
+    for format in ["mermaid", "jsonl"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ll2cfg")).arg(&input).args(["--progress", "--format", format]).output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    }
+    std::fs::remove_file(&input).unwrap();
+}
+
+#[test]
+fn combined_dot_selects_the_dot_format() {
+    let out = ll2cfg("cli_combined_dot", &format!("{IR}\n{DEBUG_IR}"), &["--combined-dot"]);
+    assert!(out.starts_with("digraph cfg {\n"), "{out}");
+    assert_eq!(out.matches("subgraph \"cluster_").count(), 2);
+    assert_eq!(out, ll2cfg("cli_combined_dot", &format!("{IR}\n{DEBUG_IR}"), &["--format", "dot"]));
+}
+
+#[test]
+fn json_lines_keep_debug_intrinsics_with_ignore_debug() {
+    let out = ll2cfg("cli_jsonl_debug", DEBUG_IR, &["--format", "jsonl", "--ignore-debug"]);
+    let function: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+    assert!(function["blocks"][0]["instructions"][0].as_str().unwrap().starts_with("call void @llvm.dbg.value("));
+}
@@ -0,0 +1,69 @@
+use learning_llvm_tools::condense::{collapse_allocas, merge_identical_blocks};
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = "\
+define void @f(i32 %x) {
+entry:
+  switch i32 %x, label %d [
+    i32 0, label %a
+    i32 1, label %b
+    i32 2, label %c
+  ]
+
+a:
+  call void @abort()
+  unreachable
+
+b:
+  call void @abort()
+  unreachable
+
+c:
+  call void @g()
+  br label %d
+
+d:
+  ret void
+}
+";
+
+#[test]
+fn identical_blocks_share_one_node() {
+    let module = parse_ll_str(IR);
+    let (merged, groups) = merge_identical_blocks(&module.functions[0], 0);
+    assert_eq!(groups, [["%a", "%b"]]);
+    let names: Vec<&str> = merged.blocks.iter().map(|b| b.name.as_ref()).collect();
+    assert_eq!(names, ["entry", "a", "c", "d"]);
+    assert_eq!(merged.blocks[0].successors, ["%d", "%a", "%c"]);
+    assert_eq!(merged.blocks[1].instructions[0], "  ; 2 identical blocks: %a, %b");
+    assert_eq!(merged.blocks[3].predecessors, ["%entry", "%c"]);
+}
+
+#[test]
+fn alloca_runs_in_the_entry_become_one_line() {
+    let module = parse_ll_str("\
+define i32 @main(i32 %argc) {
+entry:
+  %retval = alloca i32, align 4
+  %argc.addr = alloca i32, align 4
+  %buf = alloca [16 x i8], align 1
+  store i32 0, ptr %retval, align 4
+  %single = alloca i64, align 8
+  br label %exit
+
+exit:
+  %late = alloca i32, align 4
+  %later = alloca i32, align 4
+  ret i32 0
+}
+");
+    let (function, collapsed) = collapse_allocas(&module.functions[0], 0);
+    assert_eq!(collapsed, 3);
+    assert_eq!(function.blocks[0].instructions, [
+        "  ; 3 allocas",
+        "  store i32 0, ptr %retval, align 4",
+        "  %single = alloca i64, align 8",
+        "  br label %exit",
+    ]);
+    assert_eq!(function.blocks[1].instructions.len(), 3);
+}
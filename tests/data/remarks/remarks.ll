@@ -0,0 +1,48 @@
+; ModuleID = 'remarks.c'
+source_filename = "remarks.c"
+
+declare i32 @external(i32)
+
+define internal i32 @twice(i32 %x) {
+  %y = shl i32 %x, 1
+  ret i32 %y
+}
+
+define i32 @sum(i32* %a, i32 %n) !dbg !6 {
+entry:
+  %cmp = icmp sgt i32 %n, 0, !dbg !10
+  br i1 %cmp, label %loop, label %exit, !dbg !10
+
+loop:
+  %i = phi i32 [ 0, %entry ], [ %next, %loop ]
+  %acc = phi i32 [ 0, %entry ], [ %add, %loop ]
+  %idx = sext i32 %i to i64, !dbg !11
+  %p = getelementptr inbounds i32, i32* %a, i64 %idx, !dbg !11
+  %v = load i32, i32* %p, align 4, !dbg !11
+  %add = add nsw i32 %acc, %v, !dbg !11
+  %next = add nuw nsw i32 %i, 1, !dbg !12
+  %done = icmp eq i32 %next, %n, !dbg !12
+  br i1 %done, label %exit, label %loop, !dbg !12
+
+exit:
+  %r = phi i32 [ 0, %entry ], [ %add, %loop ]
+  %t = call i32 @twice(i32 %r), !dbg !14
+  %e = call i32 @external(i32 %t), !dbg !13
+  ret i32 %e, !dbg !13
+}
+
+!llvm.dbg.cu = !{!0}
+!llvm.module.flags = !{!3, !4}
+
+!0 = distinct !DICompileUnit(language: DW_LANG_C99, file: !1, producer: "hand", isOptimized: true, runtimeVersion: 0, emissionKind: FullDebug)
+!1 = !DIFile(filename: "remarks.c", directory: "/tmp")
+!3 = !{i32 7, !"Dwarf Version", i32 4}
+!4 = !{i32 2, !"Debug Info Version", i32 3}
+!6 = distinct !DISubprogram(name: "sum", scope: !1, file: !1, line: 3, type: !7, scopeLine: 3, spFlags: DISPFlagDefinition | DISPFlagOptimized, unit: !0)
+!7 = !DISubroutineType(types: !8)
+!8 = !{null}
+!10 = !DILocation(line: 5, column: 7, scope: !6)
+!11 = !DILocation(line: 6, column: 12, scope: !6)
+!12 = !DILocation(line: 5, column: 25, scope: !6)
+!13 = !DILocation(line: 8, column: 10, scope: !6)
+!14 = !DILocation(line: 8, column: 19, scope: !6)
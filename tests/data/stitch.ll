@@ -0,0 +1,29 @@
+define i32 @leaf(i32 %x) {
+entry:
+  %c = icmp sgt i32 %x, 0
+  br i1 %c, label %pos, label %neg
+pos:
+  ret i32 %x
+neg:
+  ret i32 0
+}
+
+define i32 @fact(i32 %n) {
+entry:
+  %c = icmp eq i32 %n, 0
+  br i1 %c, label %base, label %rec
+base:
+  ret i32 1
+rec:
+  %m = sub i32 %n, 1
+  %r = call i32 @fact(i32 %m)
+  %p = mul i32 %n, %r
+  ret i32 %p
+}
+
+define i32 @main(i32 %a) {
+entry:
+  %x = call i32 @leaf(i32 %a)
+  %y = call i32 @fact(i32 %x)
+  ret i32 %y
+}
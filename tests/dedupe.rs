@@ -0,0 +1,62 @@
+use learning_llvm_tools::dedupe::{function_hash, identical_functions, renumbered_body, HashContent};
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = "\
+define i32 @a(i32 %n) {
+entry:
+  %c = icmp sgt i32 %n, 0
+  br i1 %c, label %pos, label %done
+
+pos:                                              ; preds = %entry
+  %m = add i32 %n, 1, !dbg !7
+  br label %done
+
+done:                                             ; preds = %pos, %entry
+  %r = phi i32 [ %m, %pos ], [ 0, %entry ]
+  ret i32 %r
+}
+
+define i32 @b(i32) {
+  %2 = icmp sgt i32 %0, 0
+  br i1 %2, label %3, label %5
+
+3:
+  %4 = add i32 %0, 1
+  br label %5
+
+5:
+  %6 = phi i32 [ %4, %3 ], [ 0, %1 ]
+  ret i32 %6
+}
+
+define i32 @c(i32 %n) {
+entry:
+  %c = icmp sgt i32 %n, 0
+  br i1 %c, label %pos, label %done
+
+pos:
+  %m = sub i32 %n, 1
+  br label %done
+
+done:
+  %r = phi i32 [ %m, %pos ], [ 0, %entry ]
+  ret i32 %r
+}
+";
+
+#[test]
+fn renaming_values_and_blocks_keeps_the_hash() {
+    let module = parse_ll_str(IR);
+    let [a, b, c] = [0, 1, 2].map(|i| &module.functions[i]);
+    assert_eq!(renumbered_body(a), renumbered_body(b));
+    assert_eq!(function_hash(a, HashContent::Instructions), function_hash(b, HashContent::Instructions));
+    assert_eq!(function_hash(a, HashContent::Structure), function_hash(c, HashContent::Structure));
+    assert_ne!(function_hash(a, HashContent::Instructions), function_hash(c, HashContent::Instructions));
+}
+
+#[test]
+fn identical_functions_group_across_names() {
+    let module = parse_ll_str(IR);
+    let functions: Vec<_> = module.functions.iter().chain(module.functions.iter().take(1)).collect();
+    assert_eq!(identical_functions(&functions), [vec![0, 1, 3]]);
+}
@@ -0,0 +1,35 @@
+use learning_llvm_tools::dom::dominator_chain;
+use learning_llvm_tools::parser::parse_ll_str;
+
+#[test]
+fn dominator_chain_skips_blocks_on_only_some_paths() {
+    let module = parse_ll_str("\
+define void @f(i1 %c) {
+entry:
+  br i1 %c, label %a, label %b
+
+a:
+  br label %join
+
+b:
+  br label %join
+
+join:
+  br label %tail
+
+tail:
+  ret void
+
+dead:
+  br label %tail
+}
+");
+    let function = &module.functions[0];
+    let chain = dominator_chain(function, 0, function.block_index("tail").unwrap()).unwrap();
+    let names: Vec<&str> = chain.blocks.iter().map(|b| b.name.as_ref()).collect();
+    assert_eq!(names, ["entry", "join", "tail"]);
+    assert_eq!(chain.blocks[0].successors, ["%join"]);
+    assert_eq!(chain.blocks[1].predecessors, ["%entry"]);
+    assert!(chain.blocks[2].successors.is_empty());
+    assert!(dominator_chain(function, 0, function.block_index("dead").unwrap()).is_none());
+}
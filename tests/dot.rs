@@ -0,0 +1,119 @@
+use learning_llvm_tools::dot::dump_dot;
+use learning_llvm_tools::parser::parse_ll_str;
+use learning_llvm_tools::render::{EdgeBundling, RenderOptions};
+
+const IR: &str = "\
+define void @f(i32 %x) {
+entry:
+  switch i32 %x, label %exit [
+    i32 0, label %a
+    i32 1, label %b
+  ]
+
+a:
+  br label %exit
+
+b:
+  br label %exit
+
+exit:
+  ret void
+}
+";
+
+fn dot(edge_bundling: Option<EdgeBundling>) -> String {
+    let module = parse_ll_str(IR);
+    let mut output = vec![];
+    let options = RenderOptions { abbr: true, edge_bundling, ..Default::default() };
+    dump_dot(&mut output, &[&module.functions[0]], &options).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn edge_bundling_shares_ports_at_fan_in_and_fan_out() {
+    assert!(!dot(None).contains("same"));
+    let concentrated = dot(Some(EdgeBundling::Concentrate));
+    assert!(concentrated.contains("  concentrate=true;\n"));
+    assert!(concentrated.contains("\"f%entry\" -> \"f%a\" [label=\"%a\", sametail=\"out_entry\"];"));
+    assert!(concentrated.contains("\"f%a\" -> \"f%exit\" [label=\"%exit\", samehead=\"in_exit\"];"));
+    let ortho = dot(Some(EdgeBundling::Ortho));
+    assert!(ortho.contains("  splines=ortho;\n"));
+    assert!(ortho.contains("\"f%b\" -> \"f%exit\" [xlabel=\"%exit\", samehead=\"in_exit\"];"));
+}
+
+#[test]
+fn dot_attrs_carry_block_analyses() {
+    let module = parse_ll_str("\
+define void @g(i1 %c) {
+entry:
+  br label %loop
+
+loop:
+  %i = phi i32 [ 0, %entry ], [ %j, %loop ]
+  %j = add i32 %i, 1
+  br i1 %c, label %loop, label %exit
+
+exit:
+  ret void
+
+dead:
+  unreachable
+}
+");
+    let mut output = vec![];
+    let options = RenderOptions { abbr: true, dot_attrs: true, ..Default::default() };
+    dump_dot(&mut output, &[&module.functions[0]], &options).unwrap();
+    let dot = String::from_utf8(output).unwrap();
+    assert!(dot.contains("blockname=\"loop\", insts=3, term=\"condbr\", loopdepth=1, reachable=true]"));
+    assert!(dot.contains("blockname=\"exit\", insts=1, term=\"ret\", loopdepth=0, reachable=true]"));
+    assert!(dot.contains("\"g%loop\" -> \"g%loop\" [label=\"↻ %loop\"];"));
+    assert!(dot.contains("blockname=\"dead\", insts=1, term=\"unreachable\", loopdepth=0, reachable=false]"));
+}
+
+#[test]
+fn combined_module_keeps_node_ids_apart() {
+    let mut module = parse_ll_str(IR);
+    module.merge(parse_ll_str(IR));
+    module.merge(parse_ll_str("define void @g() {\nentry:\n  ret void\n}\n"));
+    let functions: Vec<_> = module.functions.iter().collect();
+    let mut output = vec![];
+    dump_dot(&mut output, &functions, &RenderOptions { abbr: true, ..Default::default() }).unwrap();
+    let dot = String::from_utf8(output).unwrap();
+    assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+    assert_eq!(dot.matches("subgraph").count(), 3);
+    assert!(dot.contains("subgraph \"cluster_f\""));
+    assert!(dot.contains("subgraph \"cluster_f#1\""));
+    assert!(dot.contains("\"f#1%a\" -> \"f#1%exit\""));
+    assert!(dot.contains("\"g%entry\" [label="));
+    let nodes: Vec<&str> = dot.lines().filter(|l| l.contains('%') && !l.contains("->")).collect();
+    let mut ids: Vec<&str> = nodes.iter().map(|l| l.trim().split(" [").next().unwrap()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), 9);
+}
+
+#[test]
+fn quoted_block_names_are_escaped() {
+    let mut module = parse_ll_str(IR);
+    let function = &mut module.functions[0];
+    for block in &mut function.blocks {
+        if block.name == "exit" {
+            block.name = "\"my exit\"".into();
+        }
+        for successor in &mut block.successors {
+            if successor == "%exit" {
+                *successor = "%\"my exit\"".into();
+            }
+        }
+    }
+    let mut output = vec![];
+    let options = RenderOptions { abbr: true, edge_bundling: Some(EdgeBundling::Concentrate), max_edge_label: Some(6), ..Default::default() };
+    dump_dot(&mut output, &[&module.functions[0]], &options).unwrap();
+    let dot = String::from_utf8(output).unwrap();
+    assert!(dot.contains("\"f%\\\"my exit\\\"\" [label=\"%\\\"my exit\\\":\\l\""), "{dot}");
+    assert!(dot.contains("\"f%a\" -> \"f%\\\"my exit\\\"\" [label=\"%\\\"my …\", comment=\"%\\\"my exit\\\"\", samehead=\"in_\\\"my exit\\\"\"];"), "{dot}");
+    // every line has its quotes paired
+    for line in dot.lines() {
+        assert_eq!(line.replace("\\\"", "").matches('"').count() % 2, 0, "{line}");
+    }
+}
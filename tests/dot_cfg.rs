@@ -0,0 +1,56 @@
+use learning_llvm_tools::cfg::cfg_json;
+use learning_llvm_tools::dot_cfg::parse_dot_cfg;
+use learning_llvm_tools::ir::Function;
+
+// opt 14 colors the nodes by block frequency; opt 10 wrote plain records
+const LLVM10_MAX: &str = include_str!("data/llvm10/max.dot");
+const LLVM14_MAX: &str = include_str!("data/llvm14/max.dot");
+const LLVM14_MAX_ONLY: &str = include_str!("data/llvm14/max-only.dot");
+const LLVM14_AGG: &str = include_str!("data/llvm14/agg.dot");
+
+#[test]
+fn records_become_blocks_with_instruction_lines() {
+    let module = parse_dot_cfg(LLVM14_MAX);
+    assert_eq!(module.functions.len(), 1);
+    let function = &module.functions[0];
+    assert_eq!(function.name, "max");
+    let names: Vec<&str> = function.blocks.iter().map(|b| b.name.as_ref()).collect();
+    assert_eq!(names, ["entry", "then", "else", "exit2", "exit"]);
+    assert_eq!(function.blocks[0].instructions, ["  %cmp = icmp sgt i32 %a, %b", "  br i1 %cmp, label %then, label %else"]);
+    // opt wraps lines at 80 columns with a `...` continuation
+    assert_eq!(function.blocks[3].instructions[0], "  %averyveryverylongname.with.lots.of.characters.in.it = add nsw i32 %a, 123456789");
+    assert_eq!(function.blocks[2].instructions.len(), 4);
+}
+
+#[test]
+fn edges_follow_the_record_ports() {
+    let function = &parse_dot_cfg(LLVM14_MAX).functions[0];
+    assert_eq!(function.blocks[0].successors, ["%then", "%else"]);
+    assert_eq!(function.blocks[2].successors, ["%exit", "%then", "%exit2"]);
+    assert!(function.blocks[4].successors.is_empty());
+    assert_eq!(function.blocks[2].terminator().map(|t| t.as_str()), Some("switch"));
+}
+
+#[test]
+fn llvm_versions_agree() {
+    let old = parse_dot_cfg(LLVM10_MAX);
+    let new = parse_dot_cfg(LLVM14_MAX);
+    assert_eq!(cfg_json(&old.functions[0], 0), cfg_json(&new.functions[0], 0));
+}
+
+#[test]
+fn cfg_only_files_have_names_and_edges() {
+    let only = &parse_dot_cfg(LLVM14_MAX_ONLY).functions[0];
+    let full = &parse_dot_cfg(LLVM14_MAX).functions[0];
+    assert!(only.blocks.iter().all(|b| b.instructions.is_empty()));
+    let shape = |f: &Function| f.blocks.iter().map(|b| (b.name.to_string(), b.successors.join(","))).collect::<Vec<_>>();
+    assert_eq!(shape(only), shape(full));
+}
+
+#[test]
+fn record_escapes_are_removed() {
+    let function = &parse_dot_cfg(LLVM14_AGG).functions[0];
+    assert_eq!(function.blocks[0].name, "0");
+    assert_eq!(function.blocks[0].instructions[0], "  %s = insertvalue { i32, i32 } undef, i32 %a, 0");
+    assert_eq!(function.blocks[2].instructions[1], "  ret { i32, i32 } { i32 1, i32 2 }");
+}
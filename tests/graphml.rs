@@ -0,0 +1,121 @@
+use std::collections::{HashMap, HashSet};
+use regex::Regex;
+use learning_llvm_tools::graphml::dump_graphml;
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = "\
+define i32 @max(i32 %a, i32 %b) {
+entry:
+  %cmp = icmp sgt i32 %a, %b
+  br i1 %cmp, label %then, label %else
+
+then:                                             ; preds = %entry
+  br label %exit
+
+else:                                             ; preds = %entry
+  switch i32 %a, label %exit [
+    i32 0, label %then
+  ]
+
+exit:                                             ; preds = %else, %then
+  %r = phi i32 [ %a, %then ], [ %b, %else ]
+  ret i32 %r
+}
+
+define void @odd.name() {
+  ret void
+}
+";
+
+fn graphml() -> String {
+    let module = parse_ll_str(IR);
+    let functions: Vec<_> = module.functions.iter().collect();
+    let mut output = vec![];
+    dump_graphml(&mut output, &functions).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Checks the constraints of the GraphML 1.0 schema that the output can violate: nesting
+/// (graphml > key* graph*, graph > data* node* edge*, node/edge > data*), NMTOKEN ids unique
+/// per kind, `data` referring to a declared `key` for its element, and edges between existing nodes.
+#[test]
+fn output_follows_the_graphml_schema() {
+    let text = graphml();
+    assert!(text.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    let tag_re = Regex::new(r#"<(/?)([\w:]+)((?:\s+[\w.:]+="[^"<]*")*)\s*(/?)>"#).unwrap();
+    let attr_re = Regex::new(r#"([\w.:]+)="([^"]*)""#).unwrap();
+    let nmtoken_re = Regex::new(r"^[\w.:-]+$").unwrap();
+    let allowed: HashMap<&str, &[&str]> = HashMap::from([
+        ("graphml", &["key", "graph"][..]),
+        ("graph", &["data", "node", "edge"][..]),
+        ("node", &["data"][..]),
+        ("edge", &["data"][..]),
+        ("key", &[][..]),
+        ("data", &[][..]),
+    ]);
+
+    let mut stack: Vec<&str> = vec![];
+    let mut keys: HashMap<String, String> = HashMap::new();
+    let mut graphs = HashSet::new();
+    let mut nodes = HashSet::new();
+    let mut edges = vec![];
+    let mut graph_started = false;
+    let body = text.split_once("?>").unwrap().1;
+    for caps in tag_re.captures_iter(body) {
+        let name = caps.get(2).unwrap().as_str();
+        if &caps[1] == "/" {
+            assert_eq!(stack.pop(), Some(name), "unbalanced </{name}>");
+            continue;
+        }
+        let attrs: HashMap<&str, &str> = attr_re.captures_iter(caps.get(3).unwrap().as_str())
+            .map(|a| (a.get(1).unwrap().as_str(), a.get(2).unwrap().as_str()))
+            .collect();
+        match stack.last() {
+            None => assert_eq!(name, "graphml"),
+            Some(parent) => assert!(allowed[parent].contains(&name), "<{name}> inside <{parent}>"),
+        }
+        match name {
+            "key" => {
+                assert!(!graph_started, "keys must precede the graphs");
+                assert!(nmtoken_re.is_match(attrs["id"]));
+                assert!(matches!(attrs["attr.type"], "string" | "int" | "long" | "double" | "boolean"));
+                assert!(keys.insert(attrs["id"].to_string(), attrs["for"].to_string()).is_none(), "duplicate key");
+            }
+            "graph" => {
+                graph_started = true;
+                assert!(nmtoken_re.is_match(attrs["id"]));
+                assert!(graphs.insert(attrs["id"].to_string()));
+                assert_eq!(attrs["edgedefault"], "directed");
+            }
+            "node" => {
+                assert!(nmtoken_re.is_match(attrs["id"]), "invalid node id {}", attrs["id"]);
+                assert!(nodes.insert(attrs["id"].to_string()), "duplicate node id");
+            }
+            "edge" => edges.push((attrs["source"].to_string(), attrs["target"].to_string())),
+            "data" => {
+                let owner = *stack.last().unwrap();
+                assert_eq!(keys.get(attrs["key"]).map(String::as_str), Some(owner), "data key {} on <{owner}>", attrs["key"]);
+            }
+            _ => {}
+        }
+        if caps.get(4).unwrap().as_str() != "/" {
+            stack.push(name);
+        }
+    }
+    assert!(stack.is_empty());
+    assert_eq!(graphs.len(), 2);
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(edges.len(), 5);
+    for (source, target) in &edges {
+        assert!(nodes.contains(source) && nodes.contains(target));
+    }
+}
+
+#[test]
+fn edges_carry_the_terminator_role() {
+    let text = graphml();
+    let kinds: Vec<&str> = text.lines()
+        .filter_map(|l| l.trim().strip_prefix("<data key=\"kind\">")?.strip_suffix("</data>"))
+        .collect();
+    assert_eq!(kinds, ["true", "false", "jump", "default", "case"]);
+}
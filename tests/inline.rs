@@ -0,0 +1,26 @@
+use learning_llvm_tools::inline::stitch_calls;
+use learning_llvm_tools::parser::parse_ll_str;
+
+const IR: &str = include_str!("data/stitch.ll");
+
+#[test]
+fn calls_become_edges_into_callee_copies() {
+    let module = parse_ll_str(IR);
+    let stitched = stitch_calls(&module, "main", 1).unwrap();
+    assert_eq!((stitched.expanded, stitched.recursion_cut), (2, 1));
+    let function = &stitched.function;
+    let names: Vec<&str> = function.blocks.iter().map(|b| b.name.as_ref()).collect();
+    assert_eq!(names, ["entry", "leaf.1.entry", "leaf.1.pos", "leaf.1.neg", "entry.ret1", "fact.2.entry", "fact.2.base", "fact.2.rec", "fact.2.rec.ret1", "entry.ret2"]);
+    assert_eq!(function.blocks[0].successors, ["%leaf.1.entry"]);
+    assert_eq!(function.blocks[4].predecessors, ["%leaf.1.pos", "%leaf.1.neg"]);
+    // at depth 1 the recursive call in the copy of @fact is cut with an edge back to its entry
+    assert!(function.blocks[7].successors.contains(&"%fact.2.entry".into()));
+}
+
+#[test]
+fn recursion_is_bounded_by_depth() {
+    let module = parse_ll_str(IR);
+    let stitched = stitch_calls(&module, "fact", 3).unwrap();
+    assert_eq!((stitched.expanded, stitched.recursion_cut), (3, 1));
+    assert!(stitch_calls(&module, "nope", 1).is_err());
+}
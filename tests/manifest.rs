@@ -0,0 +1,48 @@
+use std::fs::{self, File};
+use std::path::Path;
+use serde_json::json;
+use learning_llvm_tools::manifest::{manifest_paths, update_manifest};
+use learning_llvm_tools::pass_dump::{index_path, split_dumps, split_manifest_entries, write_index, DumpInfo};
+
+fn dump(pass: &str, function: &str) -> String {
+    format!("*** IR Dump After {pass} on {function} ***\ndefine void @{function}() {{\n  ret void\n}}\n")
+}
+
+/// Splits `log` into `dir` and writes the index, like `passes` without `--clean`.
+fn split(log: &str, dir: &Path) -> Vec<DumpInfo> {
+    let dumps = split_dumps(log.as_bytes(), dir, "log", |splitter| splitter).unwrap();
+    write_index(&mut File::create(index_path(dir, "log")).unwrap(), &dumps).unwrap();
+    dumps
+}
+
+#[test]
+fn prune_stale_deletes_what_the_previous_run_wrote_and_no_longer_does() {
+    let dir = std::env::temp_dir().join(format!("manifest_prune_{}", std::process::id()));
+    let manifest = dir.join("manifest.json");
+    let update = |dumps: &[DumpInfo], prune: bool| {
+        let entries = split_manifest_entries("in.ll", dumps, None, &index_path(&dir, "log"), None);
+        update_manifest(&manifest, &entries, json!({}), prune).unwrap()
+    };
+
+    let first = split(&[dump("A", "f"), dump("B", "g"), dump("C", "f")].concat(), &dir);
+    assert!(update(&first, true).is_empty(), "nothing to prune without a previous manifest");
+    assert_eq!(manifest_paths(&manifest).unwrap().len(), 5);
+    fs::write(dir.join("notes.txt"), "not generated").unwrap();
+
+    let second = split(&dump("A", "f"), &dir);
+    let stale = [dir.join("log_2.ll"), dir.join("log_3.ll")];
+    assert!(stale.iter().all(|path| path.exists()));
+    // without --prune-stale the files stay
+    assert!(update(&second, false).is_empty());
+    assert!(stale.iter().all(|path| path.exists()));
+
+    // back to the manifest of the first run, which lists the stale files
+    update(&first, false);
+    let mut pruned = update(&second, true);
+    pruned.sort();
+    assert_eq!(pruned, stale);
+    assert!(stale.iter().all(|path| !path.exists()));
+    assert!(dir.join("notes.txt").exists() && dir.join("log_1.ll").exists() && index_path(&dir, "log").exists());
+    assert_eq!(manifest_paths(&manifest).unwrap(), [dir.join("log_0.ll"), dir.join("log_1.ll"), index_path(&dir, "log")]);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,20 @@
+use learning_llvm_tools::analysis::FunctionMetrics;
+use learning_llvm_tools::metrics::{deltas_json, metrics_deltas};
+
+fn metrics(blocks: usize, instructions: usize) -> FunctionMetrics {
+    FunctionMetrics { blocks, instructions, edges: blocks - 1, complexity: 1, ..Default::default() }
+}
+
+#[test]
+fn deltas_match_by_name_largest_instruction_change_first() {
+    let old = [("same", metrics(2, 5)), ("shrunk", metrics(4, 20)), ("gone", metrics(1, 3))];
+    let new = [("same", metrics(2, 5)), ("shrunk", metrics(3, 12)), ("grown", metrics(2, 9)), ("new", metrics(1, 1))];
+    let deltas = metrics_deltas(&old, &new);
+    let order: Vec<(&str, &str)> = deltas.iter().map(|d| (d.function.as_str(), d.status())).collect();
+    assert_eq!(order, [("grown", "added"), ("shrunk", "changed"), ("gone", "removed"), ("new", "added"), ("same", "unchanged")]);
+    assert_eq!(deltas[1].delta(|m| m.instructions), -8);
+    assert_eq!(deltas[2].delta(|m| m.blocks), -1);
+    let json = deltas_json("a.ll", "b.ll", &deltas);
+    assert_eq!(json["functions"][1]["instructions"]["delta"], -8);
+    assert_eq!(json["functions"][0]["blocks"]["old"], serde_json::Value::Null);
+}
@@ -0,0 +1,48 @@
+use learning_llvm_tools::ir::Function;
+use learning_llvm_tools::objdump::parse_objdump;
+
+// `llvm-objdump -d -r --symbolize-operands` of the same IR compiled by llc 14 for both targets
+const X86_64: &str = include_str!("data/objdump/x86_64.txt");
+const AARCH64: &str = include_str!("data/objdump/aarch64.txt");
+
+fn edges(function: &Function) -> Vec<(String, String)> {
+    function.blocks.iter()
+        .flat_map(|b| b.successors.iter().map(move |s| (b.name.to_string(), s.trim_start_matches('%').to_string())))
+        .collect()
+}
+
+#[test]
+fn x86_blocks_split_at_branches_and_targets() {
+    let module = parse_objdump(X86_64);
+    let names: Vec<&str> = module.functions.iter().map(|f| f.name.as_ref()).collect();
+    assert_eq!(names, ["loop", "ind"]);
+    let function = &module.functions[0];
+    let blocks: Vec<&str> = function.blocks.iter().map(|b| b.name.as_ref()).collect();
+    assert_eq!(blocks, ["0x0", "0x7", "L2", "0x1d", "L0", "L3"]);
+    let expected = [("0x0", "L0"), ("0x0", "0x7"), ("0x7", "L2"), ("L2", "L2"), ("L2", "0x1d"), ("0x1d", "L3"), ("L0", "L3")];
+    assert_eq!(edges(function), expected.map(|(a, b)| (a.to_string(), b.to_string())));
+}
+
+#[test]
+fn calls_are_annotated_not_followed() {
+    let function = &parse_objdump(X86_64).functions[0];
+    let call = function.blocks[2].instructions.iter().find(|i| i.contains("callq")).unwrap();
+    assert!(call.ends_with("; call g"));
+    assert_eq!(function.blocks[2].successors, ["%L2", "%0x1d"]);
+}
+
+#[test]
+fn indirect_jumps_are_dead_ends() {
+    let function = &parse_objdump(X86_64).functions[1];
+    assert!(function.blocks[0].successors.is_empty());
+    assert!(function.blocks[0].instructions.last().unwrap().contains("dead end"));
+    // the trailing alignment padding is dropped
+    assert!(function.blocks.last().unwrap().instructions.last().unwrap().ends_with("retq"));
+}
+
+#[test]
+fn aarch64_branches_by_address() {
+    let function = &parse_objdump(AARCH64).functions[0];
+    let expected = [("0x0", "0x30"), ("0x0", "0x10"), ("0x10", "0x18"), ("0x18", "0x18"), ("0x18", "0x2c"), ("0x2c", "0x34"), ("0x30", "0x34")];
+    assert_eq!(edges(function), expected.map(|(a, b)| (a.to_string(), b.to_string())));
+}
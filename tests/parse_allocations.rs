@@ -0,0 +1,62 @@
+//! Counts the heap allocations of parsing, with a counting global allocator. Kept in a test
+//! binary of its own, holding a single test, so that nothing else allocates while it counts.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use learning_llvm_tools::parser::{parse_ll_file, parse_ll_str};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations<T>(run: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    std::hint::black_box(run());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn parsing_a_str_allocates_no_string_per_line() {
+    let mut text = String::new();
+    for n in 0..200 {
+        text.push_str(&format!("\
+define i32 @f{n}(i32 %n, i1 %c) {{
+entry:
+  br i1 %c, label %then, label %exit
+
+then:                                             ; preds = %entry
+  %a = add i32 %n, 1
+  %b = mul i32 %a, 3
+  %d = sub i32 %b, %n
+  br label %exit
+
+exit:                                             ; preds = %then, %entry
+  %r = phi i32 [ %d, %then ], [ 0, %entry ]
+  ret i32 %r
+}}
+
+"));
+    }
+    let lines = text.lines().count();
+    // compiles the parser's regexes, once per process
+    parse_ll_str(&text);
+    let borrowed = allocations(|| parse_ll_str(&text));
+    let owned = allocations(|| parse_ll_file(&mut text.as_bytes()).unwrap());
+    // what is left are the vectors holding the blocks, their instructions and edges
+    assert!(borrowed < lines, "{borrowed} allocations for {lines} lines");
+    assert!(owned > 3 * borrowed, "{owned} allocations reading, {borrowed} borrowing");
+}
@@ -0,0 +1,121 @@
+use learning_llvm_tools::parser::{parse_ll_file, parse_ll_str, parse_problems};
+
+const IR: &str = "\
+define i32 @first(i32 %a) {
+entry:
+  %s = insertvalue { i32, i32 } undef, i32 %a, 0
+  ret i32 %a
+  }
+
+define { i32, i32 } @second() {
+entry:
+  br label %exit
+
+exit:
+  ret { i32, i32 } { i32 1, i32 2 } }
+define void @third() {
+  ret void }
+";
+
+#[test]
+fn indented_or_inline_closing_braces_end_the_function() {
+    let module = parse_ll_str(IR);
+    let names: Vec<&str> = module.functions.iter().map(|f| f.name.as_ref()).collect();
+    assert_eq!(names, ["first", "second", "third"]);
+
+    let first = &module.functions[0];
+    assert_eq!(first.blocks.len(), 1);
+    assert_eq!(first.blocks[0].instructions.len(), 2);
+
+    let second = &module.functions[1];
+    assert_eq!(second.blocks.len(), 2);
+    // the braces of the constant are not the end of the function
+    assert_eq!(second.blocks[1].instructions, ["  ret { i32, i32 } { i32 1, i32 2 }"]);
+    assert_eq!(module.functions[2].blocks[0].instructions, ["  ret void"]);
+}
+
+#[test]
+fn owned_and_borrowed_parsing_agree() {
+    let owned = parse_ll_file(&mut IR.as_bytes()).unwrap();
+    let borrowed = parse_ll_str(IR);
+    for (a, b) in owned.functions.iter().zip(&borrowed.functions) {
+        assert_eq!(a.to_string(), b.to_string());
+    }
+}
+
+#[test]
+fn unnamed_arguments_get_value_numbers() {
+    let module = parse_ll_str("define void @f(i32 %a, { i32, i32 }, ptr noundef, ...) {\n  ret void\n}\n");
+    assert_eq!(module.functions[0].arguments(), ["a", "0", "1"]);
+}
+
+#[test]
+fn parse_problems_point_at_their_lines() {
+    let text = "\
+define i32 @\"quoted\"() {
+  ret i32 0
+}
+
+define void @f(i1 %c) {
+entry:
+  br i1 %c, label %a, label %missing
+
+a:
+  frobnicate
+  ret void
+}
+
+define void @g() {
+  ret void
+";
+    let problems = parse_problems(text);
+    let lines: Vec<usize> = problems.iter().map(|p| p.line).collect();
+    assert_eq!(lines, [1, 7, 10, 14]);
+    assert_eq!(problems[1].message, "branch to %missing, which is not a block of @f");
+    assert_eq!(problems[2].message, "not an instruction in @f: frobnicate");
+    assert_eq!(problems[3].message, "@g has no closing brace");
+    assert!(parse_problems(IR).is_empty());
+}
+
+#[test]
+fn source_filename_names_the_module() {
+    let module = parse_ll_str("; ModuleID = 'cfg.c'\nsource_filename = \"lib/cfg.c\"\ntarget triple = \"x86_64-pc-linux-gnu\"\n");
+    assert_eq!(module.source_filename.as_deref(), Some("lib/cfg.c"));
+    assert_eq!(module.target_triple.as_deref(), Some("x86_64-pc-linux-gnu"));
+    assert!(parse_ll_str(IR).source_filename.is_none());
+}
+
+#[test]
+fn in_memory_input_is_borrowed_not_copied() {
+    use std::borrow::Cow;
+    let input = String::from("\
+; Function Attrs: nounwind
+define i32 @f(i1 %c) #0 {
+entry:
+  br i1 %c, label %then, label %exit
+
+then:                                             ; preds = %entry
+  br label %exit
+
+exit:                                             ; preds = %then, %entry
+  %r = phi i32 [ 1, %then ], [ 0, %entry ]
+  ret i32 %r
+}
+");
+    let range = input.as_bytes().as_ptr_range();
+    let borrowed = |text: &Cow<str>| matches!(text, Cow::Borrowed(s) if range.contains(&s.as_ptr()));
+    let module = parse_ll_str(&input);
+    let function = &module.functions[0];
+    assert!(borrowed(&function.name) && borrowed(&function.define) && borrowed(function.attrs.as_ref().unwrap()));
+    for block in &function.blocks {
+        assert!(borrowed(&block.name), "%{} is copied", block.name);
+        for text in block.instructions.iter().chain(&block.predecessors).chain(&block.successors) {
+            assert!(borrowed(text), "{text} in %{} is copied", block.name);
+        }
+    }
+    assert_eq!(function.blocks[2].predecessors.len(), 2);
+    assert_eq!(function.blocks[0].successors.len(), 2);
+
+    let owned = parse_ll_file(&mut input.as_bytes()).unwrap();
+    assert!(matches!(owned.functions[0].blocks[2].instructions[0], Cow::Owned(_)));
+}
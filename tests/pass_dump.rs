@@ -0,0 +1,195 @@
+use std::fs;
+use std::io::BufReader;
+use learning_llvm_tools::pass_dump::{assign_times, continue_split, function_changes, parse_timing_report, split_dumps, write_index, PassTime, PassTiming, Splitter, LINE_CHUNK};
+
+#[test]
+fn long_lines_are_written_through_and_hashed_alike() {
+    let dir = std::env::temp_dir().join(format!("pass_dump_long_lines_{}", std::process::id()));
+    let long = format!("@g = global [{} x i8] c\"{}é\"", 3 * LINE_CHUNK, "x".repeat(3 * LINE_CHUNK));
+    let log = format!("*** IR Dump After A on [module] ***\n{long}\r\n*** IR Dump After B on [module] ***\n{long}\n");
+    // a small buffer, so that the long lines arrive in many pieces
+    let reader = BufReader::with_capacity(1000, log.as_bytes());
+    let dumps = split_dumps(reader, &dir, "log", |splitter| splitter).unwrap();
+    assert_eq!(dumps.len(), 3);
+    assert_eq!(dumps[1].longest_line, long.len());
+    assert_eq!(dumps[1].hash, dumps[2].hash);
+    assert!(!dumps[2].changed);
+    assert_eq!(fs::read_to_string(&dumps[2].file).unwrap(), format!("*** IR Dump After B on [module] ***\n{long}\n"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+const LOG: &str = "\
+*** IR Dump After A on f ***
+define i32 @f(i32 %x) {
+entry:
+  %y = add i32 %x, 0
+  ret i32 %y
+}
+*** IR Dump After B on g ***
+define void @g() {
+  ret void
+}
+*** IR Dump After C on f ***
+define i32 @f(i32 %x) {
+entry:
+  %y = add i32 %x, 0
+  ret i32 %y
+}
+*** IR Dump After D on f ***
+define i32 @f(i32 %x) {
+entry:
+  ret i32 %x
+}
+";
+
+#[test]
+fn function_changes_skip_other_functions_and_unchanged_dumps() {
+    let changes = function_changes(LOG.as_bytes(), "f").unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!((changes[0].index, changes[0].header.pass.as_str()), (4, "D"));
+    assert!(changes[0].before.contains("%y = add"));
+    assert!(!changes[0].after.contains("%y = add"));
+}
+
+#[test]
+fn resumed_split_matches_a_split_of_the_whole_log() {
+    let base = std::env::temp_dir().join(format!("pass_dump_resume_{}", std::process::id()));
+    let (resumed_dir, whole_dir) = (base.join("resumed"), base.join("whole"));
+    let grown = format!("{LOG}  ; still being written\n*** IR Dump After E on g ***\ndefine void @g() {{\n  ret void\n}}\n");
+
+    let first = split_dumps(LOG.as_bytes(), &resumed_dir, "log", |s| s.ring(Some(2), false)).unwrap();
+    let last = first.last().unwrap();
+    assert_eq!(&LOG[last.offset as usize..last.offset as usize + 10], "*** IR Dum");
+    let splitter = Splitter::resume(&resumed_dir, "log", first[..first.len() - 1].to_vec(), None).ring(Some(2), false);
+    let resumed = continue_split(&grown.as_bytes()[last.offset as usize..], splitter, last.offset).unwrap();
+    let whole = split_dumps(grown.as_bytes(), &whole_dir, "log", |s| s.ring(Some(2), false)).unwrap();
+
+    let summary = |dumps: &[learning_llvm_tools::pass_dump::DumpInfo]| dumps.iter()
+        .map(|d| (d.index, d.offset, d.lines, d.hash, d.changed, d.pruned))
+        .collect::<Vec<_>>();
+    assert_eq!(summary(&resumed), summary(&whole));
+    // A, B and D; C and E repeat the previous dump of their function
+    assert_eq!(resumed.iter().filter(|d| d.changed).count(), 3);
+    for (a, b) in resumed.iter().zip(&whole).filter(|(d, _)| !d.pruned) {
+        assert_eq!(fs::read_to_string(&a.file).unwrap(), fs::read_to_string(&b.file).unwrap());
+    }
+    fs::remove_dir_all(&base).unwrap();
+}
+
+/// A `-print-after-all -time-passes -time-passes-per-run` log: InstCombinePass runs twice,
+/// VerifierPass prints no dump, and the analysis timer group that follows is not about passes.
+const TIMED_LOG: &str = "\
+*** IR Dump After InstCombinePass on f ***
+define void @f() {
+  ret void
+}
+*** IR Dump After SimplifyCFGPass on f ***
+define void @f() {
+  ret void
+}
+*** IR Dump After InstCombinePass on g ***
+define void @g() {
+  ret void
+}
+*** IR Dump After LICMPass on g ***
+define void @g() {
+  ret void
+}
+===-------------------------------------------------------------------------===
+                      ... Pass execution timing report ...
+===-------------------------------------------------------------------------===
+  Total Execution Time: 0.0150 seconds (0.0152 wall clock)
+
+   ---User Time---   --System Time--   --User+System--   ---Wall Time---  --- Name ---
+   0.0060 ( 46.2%)   0.0010 ( 50.0%)   0.0070 ( 46.7%)   0.0071 ( 46.7%)  InstCombinePass #1
+   0.0030 ( 23.1%)   0.0005 ( 25.0%)   0.0035 ( 23.3%)   0.0036 ( 23.7%)  InstCombinePass #2
+   0.0020 ( 15.4%)   0.0003 ( 15.0%)   0.0023 ( 15.3%)   0.0024 ( 15.8%)  SimplifyCFGPass #1
+   0.0020 ( 15.4%)   0.0002 ( 10.0%)   0.0022 ( 14.7%)   0.0021 ( 13.8%)  VerifierPass #1
+   0.0130 (100.0%)   0.0020 (100.0%)   0.0150 (100.0%)   0.0152 (100.0%)  Total
+
+===-------------------------------------------------------------------------===
+                        Analysis execution timing report
+===-------------------------------------------------------------------------===
+  Total Execution Time: 0.0010 seconds (0.0010 wall clock)
+
+   ---User Time---   --User+System--   ---Wall Time---  --- Name ---
+   0.0010 (100.0%)   0.0010 (100.0%)   0.0010 (100.0%)  DominatorTreeAnalysis
+   0.0010 (100.0%)   0.0010 (100.0%)   0.0010 (100.0%)  Total
+";
+
+#[test]
+fn timing_report_rows_are_read_from_the_wall_time_column() {
+    let lines: Vec<String> = TIMED_LOG.lines().map(String::from).collect();
+    let timings = parse_timing_report(&lines);
+    let rows: Vec<(&str, Option<usize>, f64)> = timings.iter().map(|t| (t.name.as_str(), t.run, t.wall)).collect();
+    assert_eq!(rows, [
+        ("InstCombinePass", Some(1), 0.0071),
+        ("InstCombinePass", Some(2), 0.0036),
+        ("SimplifyCFGPass", Some(1), 0.0024),
+        ("VerifierPass", Some(1), 0.0021),
+    ]);
+}
+
+#[test]
+fn repeated_runs_get_their_own_time_and_unmatched_rows_are_ignored() {
+    let dir = std::env::temp_dir().join(format!("pass_dump_timed_{}", std::process::id()));
+    let dumps = split_dumps(TIMED_LOG.as_bytes(), &dir, "log", |splitter| splitter).unwrap();
+    let times: Vec<Option<PassTime>> = dumps.iter().map(|d| d.time).collect();
+    // the report is not part of the last dump
+    assert_eq!(dumps[4].lines, 4);
+    assert_eq!(times, [None, Some(PassTime::Run(0.0071)), Some(PassTime::Run(0.0024)), Some(PassTime::Run(0.0036)), None]);
+
+    let mut index = vec![];
+    write_index(&mut index, &dumps).unwrap();
+    let index = String::from_utf8(index).unwrap();
+    assert!(index.contains("| InstCombinePass | 2 | 2 | 0.0107 |"), "{index}");
+    assert!(!index.contains("VerifierPass"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn without_per_run_times_repeated_passes_share_the_total() {
+    let timings = [
+        PassTiming { name: "InstCombinePass".to_string(), run: None, wall: 0.5 },
+        PassTiming { name: "SimplifyCFGPass".to_string(), run: None, wall: 0.25 },
+    ];
+    let dir = std::env::temp_dir().join(format!("pass_dump_totals_{}", std::process::id()));
+    let log: String = TIMED_LOG.lines().take_while(|l| !l.starts_with("===-")).map(|l| format!("{l}\n")).collect();
+    let mut dumps = split_dumps(log.as_bytes(), &dir, "log", |splitter| splitter).unwrap();
+    assign_times(&mut dumps, &timings);
+    assert_eq!(dumps[1].time, Some(PassTime::Total { seconds: 0.5, runs: 2 }));
+    assert_eq!(dumps[2].time, Some(PassTime::Run(0.25)));
+    assert_eq!(dumps[3].time, dumps[1].time);
+    assert_eq!(dumps[4].time, None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// The split files left in `dir`, sorted.
+fn files_on_disk(dir: &std::path::Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn ring_keeps_the_most_recent_dumps_and_optionally_the_first() {
+    for keep_first in [false, true] {
+        let dir = std::env::temp_dir().join(format!("pass_dump_ring_{keep_first}_{}", std::process::id()));
+        let dumps = split_dumps(LOG.as_bytes(), &dir, "log", |splitter| splitter.ring(Some(2), keep_first)).unwrap();
+        let pruned: Vec<usize> = dumps.iter().filter(|d| d.pruned).map(|d| d.index).collect();
+        if keep_first {
+            assert_eq!(pruned, [2]);
+            assert_eq!(files_on_disk(&dir), ["log_0.ll", "log_1.ll", "log_3.ll", "log_4.ll"]);
+        } else {
+            assert_eq!(pruned, [1, 2]);
+            assert_eq!(files_on_disk(&dir), ["log_0.ll", "log_3.ll", "log_4.ll"]);
+        }
+        // the index still lists every dump
+        let mut index = vec![];
+        write_index(&mut index, &dumps).unwrap();
+        let index = String::from_utf8(index).unwrap();
+        assert_eq!(index.matches("(pruned)").count(), pruned.len());
+        assert!(index.contains("| 2 | B | g | 4 | yes | ~~log_2.ll~~ (pruned) |"), "{index}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
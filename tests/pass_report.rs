@@ -0,0 +1,26 @@
+use std::io::BufReader;
+use learning_llvm_tools::pass_dump::split_dumps;
+use learning_llvm_tools::pass_report::write_html_report;
+
+#[test]
+fn pass_names_targets_and_ir_are_escaped() {
+    let dir = std::env::temp_dir().join(format!("pass_report_escaping_{}", std::process::id()));
+    let log = "\
+*** IR Dump After Wrap<Pass> on \"a&b\" ***
+@s = constant [9 x i8] c\"<script>\"
+*** IR Dump After Wrap<Pass> on \"a&b\" ***
+@s = constant [9 x i8] c\"</script>\"
+";
+    let dumps = split_dumps(BufReader::new(log.as_bytes()), &dir, "log", |splitter| splitter).unwrap();
+    let mut html = vec![];
+    write_html_report(&mut html, "<title>", &dumps).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let html = String::from_utf8(html).unwrap();
+
+    assert!(html.contains("<title>&lt;title&gt;</title>"));
+    assert!(html.contains("Wrap&lt;Pass&gt; <small>&quot;a&amp;b&quot;</small>"), "{html}");
+    assert!(html.contains("c&quot;&lt;script&gt;&quot;"));
+    assert!(html.contains("<span class=\"add\">+ "));
+    assert!(!html.contains("<script>\"") && !html.contains("</script>\""));
+    assert!(!html.contains("<Pass>") && !html.contains("a&b"));
+}
@@ -0,0 +1,9 @@
+use learning_llvm_tools::progress::Progress;
+
+#[test]
+fn disabled_progress_is_never_drawn() {
+    let mut progress = Progress::new("parsing", None, false);
+    progress.inc();
+    assert!(!progress.is_drawn());
+    progress.finish();
+}
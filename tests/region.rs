@@ -0,0 +1,64 @@
+use learning_llvm_tools::cfg::Cfg;
+use learning_llvm_tools::parser::parse_ll_str;
+use learning_llvm_tools::region::{collapse_regions, region_tree};
+
+const IR: &str = "\
+define void @f(i1 %c, i1 %d) {
+entry:
+  br i1 %c, label %then, label %else
+
+then:
+  br i1 %d, label %t1, label %t2
+
+t1:
+  br label %tjoin
+
+t2:
+  br label %tjoin
+
+tjoin:
+  br label %join
+
+else:
+  br label %join
+
+join:
+  br label %loop
+
+loop:
+  br i1 %d, label %loop, label %exit
+
+exit:
+  ret void
+}
+";
+
+#[test]
+fn regions_nest() {
+    let module = parse_ll_str(IR);
+    let function = &module.functions[0];
+    let regions = region_tree(&Cfg::new(function), 0);
+    let name = |b: usize| function.blocks[b].name.to_string();
+    let summary: Vec<(String, Option<String>, usize, usize)> = regions.iter()
+        .map(|r| (name(r.entry), r.exit.map(name), r.blocks.len(), r.depth))
+        .collect();
+    assert_eq!(summary, [
+        ("entry".to_string(), Some("join".to_string()), 6, 0),
+        ("then".to_string(), Some("tjoin".to_string()), 3, 1),
+        // the self-loop alone is a single block, so its region starts at the preheader
+        ("join".to_string(), Some("exit".to_string()), 2, 0),
+    ]);
+    assert_eq!(regions[1].parent, Some(0));
+}
+
+#[test]
+fn collapsed_regions_branch_to_their_exit() {
+    let module = parse_ll_str(IR);
+    let (function, count) = collapse_regions(&module.functions[0], 1, 0);
+    assert_eq!(count, 1);
+    let names: Vec<&str> = function.blocks.iter().map(|b| b.name.as_ref()).collect();
+    assert_eq!(names, ["entry", "then", "tjoin", "else", "join", "loop", "exit"]);
+    assert_eq!(function.blocks[1].instructions, ["  ; region %then -> %tjoin: 3 blocks"]);
+    assert_eq!(function.blocks[1].successors, ["%tjoin"]);
+    assert_eq!(function.blocks[2].predecessors, ["%then"]);
+}
@@ -0,0 +1,34 @@
+use std::io::Cursor;
+use learning_llvm_tools::parser::parse_ll_str;
+use learning_llvm_tools::remarks::{debug_lines, parse_remarks, place_remarks};
+
+// opt 14 -O2 -pass-remarks-output of remarks.ll
+const IR: &str = include_str!("data/remarks/remarks.ll");
+const REMARKS: &str = include_str!("data/remarks/remarks.yaml");
+
+#[test]
+fn remark_documents_are_parsed() {
+    let remarks = parse_remarks(REMARKS);
+    assert_eq!(remarks.len(), 6);
+    let inlined = remarks.iter().find(|r| r.kind == "Passed").unwrap();
+    assert_eq!((inlined.pass.as_str(), inlined.name.as_str(), inlined.function.as_str()), ("inline", "Inlined", "sum"));
+    assert_eq!((inlined.line, inlined.column), (Some(8), Some(19)));
+    assert!(inlined.message.starts_with("'twice' inlined into 'sum' with (cost="));
+    let missed = &remarks[0];
+    assert_eq!(missed.message, "external will not be inlined into sum because its definition is unavailable");
+}
+
+#[test]
+fn remarks_land_on_the_blocks_of_their_lines() {
+    let module = parse_ll_str(IR);
+    let functions: Vec<_> = module.functions.iter().collect();
+    let lines = debug_lines(Cursor::new(IR)).unwrap();
+    let remarks = parse_remarks(REMARKS);
+    let placement = place_remarks(&remarks, &functions, &[lines]);
+    let blocks: Vec<(&str, usize)> = placement.blocks.iter().map(|((_, block), r)| (block.as_str(), r.len())).collect();
+    // the loop remarks are at line 5 column 25, the loop latch, not the line 5 column 7 comparison of the entry
+    assert_eq!(blocks, [("exit", 3), ("loop", 2)]);
+    // the SLP remark has no DebugLoc
+    assert_eq!(placement.unlocated.len(), 1);
+    assert_eq!(remarks[placement.unlocated[0]].pass, "slp-vectorizer");
+}
@@ -0,0 +1,149 @@
+use learning_llvm_tools::analysis::value_definitions;
+use learning_llvm_tools::parser::parse_ll_str;
+use learning_llvm_tools::cfg::Cfg;
+use learning_llvm_tools::render::{auto_direction, dump_cfg, function_anchors, resolve_values, truncate_label, values_as_types, MermaidKind, RenderOptions};
+
+const IR: &str = "\
+define i32 @f(i32 %a, i32) {
+entry:
+  %x = add i32 %a, %0
+  br label %bb1
+
+bb1:                                              ; preds = %entry
+  %y = phi i32 [ %x, %entry ]
+  %z = load i32, ptr @g
+  ret i32 %y
+}
+";
+
+#[test]
+fn uses_point_to_their_definitions() {
+    let module = parse_ll_str(IR);
+    let definitions = value_definitions(&module.functions[0]);
+    let block = &module.functions[0].blocks[1];
+    assert_eq!(resolve_values(&module.functions[0].blocks[0].instructions[0], &definitions),
+        "  %x = add i32 %a, %0 ; %a = argument, %0 = argument");
+    // block labels are not values
+    assert_eq!(resolve_values(&block.instructions[0], &definitions), "  %y = phi i32 [ %x, %entry ] ; %x = add in %entry");
+    assert_eq!(resolve_values(&block.instructions[1], &definitions), "  %z = load i32, ptr @g ; @g = global");
+    assert_eq!(resolve_values(&block.instructions[2], &definitions), "  ret i32 %y ; %y = phi in %bb1");
+}
+
+#[test]
+fn values_give_way_to_their_types() {
+    let module = parse_ll_str(IR);
+    let definitions = value_definitions(&module.functions[0]);
+    let types = |block: usize, inst: usize| values_as_types(&module.functions[0].blocks[block].instructions[inst], &definitions);
+    assert_eq!(types(0, 0), "  add i32");
+    assert_eq!(types(0, 1), "  br label %bb1");
+    assert_eq!(types(1, 0), "  phi i32 [ %entry ]");
+    assert_eq!(types(1, 1), "  load i32, ptr @g");
+    assert_eq!(types(1, 2), "  ret i32");
+    assert_eq!(values_as_types("  store i32 %q, ptr %a, align 4", &definitions), "  store i32 %q, ptr, align 4");
+}
+
+const SWITCH: &str = "\
+define void @s(i32 %x) {
+entry:
+  switch i32 %x, label %done [
+    i32 0, label %a
+    i32 1, label %b
+    i32 2, label %c
+  ]
+
+a:
+  br label %done
+
+b:
+  br label %done
+
+c:
+  br label %done
+
+done:
+  ret void
+}
+";
+
+#[test]
+fn wide_cfgs_go_left_to_right() {
+    let module = parse_ll_str(SWITCH);
+    assert_eq!(auto_direction(&Cfg::new(&module.functions[0]), 0), "LR");
+    let module = parse_ll_str(IR);
+    assert_eq!(auto_direction(&Cfg::new(&module.functions[0]), 0), "TD");
+}
+
+const BRANCH: &str = "\
+define i32 @max(i32 %a, i32 %b) {
+entry:
+  %c = icmp sgt i32 %a, %b
+  br i1 %c, label %left, label %right
+
+left:
+  ret i32 %a
+
+right:
+  ret i32 %b
+}
+";
+
+fn mermaid(kind: MermaidKind) -> String {
+    let module = parse_ll_str(BRANCH);
+    let mut output = vec![];
+    dump_cfg(&mut output, &module.functions[0], &RenderOptions { abbr: true, mermaid_kind: kind, ..Default::default() });
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn branches_render_as_flowchart_or_state_diagram() {
+    let flowchart = mermaid(MermaidKind::Flowchart);
+    assert!(flowchart.starts_with("```mermaid\nflowchart TD\n"));
+    assert!(flowchart.contains("\t%entry -->|%left| %left\n"));
+    let state = mermaid(MermaidKind::State);
+    assert!(state.starts_with("```mermaid\nstateDiagram-v2\n"));
+    for line in ["[*] --> b0", "b0 : %entry", "b0 --> b1 : %c", "b0 --> b2 : !%c", "b1 --> [*]", "b2 --> [*]"] {
+        assert!(state.contains(&format!("    {line}\n")), "missing {line} in {state}");
+    }
+}
+
+#[test]
+fn label_sizes_are_measured_before_truncation() {
+    let module = parse_ll_str(IR);
+    let full = dump_cfg(&mut vec![], &module.functions[0], &RenderOptions::default());
+    for kind in [MermaidKind::Flowchart, MermaidKind::State] {
+        let options = RenderOptions { max_lines: Some(1), mermaid_kind: kind, ..Default::default() };
+        assert_eq!(dump_cfg(&mut vec![], &module.functions[0], &options), full);
+    }
+    assert_eq!(full[1], ("%bb1".to_string(), module.functions[0].blocks[1].instructions.join("\n").chars().count()));
+}
+
+#[test]
+fn degree_badges_count_predecessors_and_successors() {
+    let module = parse_ll_str(BRANCH);
+    let mut output = vec![];
+    let options = RenderOptions { abbr: true, predecessor_count: true, successor_count: true, ..Default::default() };
+    dump_cfg(&mut output, &module.functions[0], &options);
+    let flowchart = String::from_utf8(output).unwrap();
+    assert!(flowchart.contains("%entry[\"%entry\n[0→2]\"]"));
+    assert!(flowchart.contains("%left[\"%left\n[1→0]\"]"));
+}
+
+#[test]
+fn long_edge_labels_are_cut_keeping_the_full_text() {
+    assert_eq!(truncate_label("%short", Some(10)), None);
+    assert_eq!(truncate_label("%a_rather_long_block", Some(8)).as_deref(), Some("%a_rath…"));
+    assert_eq!(truncate_label("%a_rather_long_block", None), None);
+    let module = parse_ll_str("define void @f() {\nentry:\n  br label %a_rather_long_block\n\na_rather_long_block:\n  ret void\n}\n");
+    let mut output = vec![];
+    dump_cfg(&mut output, &module.functions[0], &RenderOptions { abbr: true, max_edge_label: Some(8), ..Default::default() });
+    let mermaid = String::from_utf8(output).unwrap();
+    assert!(mermaid.contains("%% edge label: %a_rather_long_block\n\t%entry -->|%a_rath…| %a_rather_long_block\n"));
+}
+
+#[test]
+fn anchors_are_valid_ids_and_unique() {
+    let mut module = parse_ll_str("define void @a.b() {\n  ret void\n}\n\ndefine void @a_b() {\n  ret void\n}\n");
+    module.merge(parse_ll_str("define void @a.b() {\n  ret void\n}\n"));
+    let functions: Vec<_> = module.functions.iter().collect();
+    assert_eq!(function_anchors(&functions), ["fn-a-b", "fn-a_b", "fn-a-b-1"]);
+}
@@ -0,0 +1,20 @@
+use learning_llvm_tools::split_output::{namespaces, nested_file_names, split_file_names};
+
+#[test]
+fn file_names_are_sanitized_and_unique() {
+    let names = split_file_names(&["main", "a b", "a:b", "index", ".hidden", "_Z3foov"], "md");
+    assert_eq!(names, ["main.md", "a_b.md", "a_b-1.md", "index-1.md", "_.hidden.md", "_Z3foov.md"]);
+}
+
+#[test]
+fn nested_names_mirror_namespaces() {
+    assert_eq!(namespaces("_ZN4llvm3cfg4walkEv"), ["llvm", "cfg"]);
+    assert_eq!(namespaces("_ZNKSt6vector4sizeEv"), ["std", "vector"]);
+    assert_eq!(namespaces("_ZN3foo3BarC2Ev"), ["foo", "Bar"]);
+    assert_eq!(namespaces("_ZN3foo3mapIiEEvT_"), ["foo"]);
+    assert_eq!(namespaces("_ZN4core3fmt5write17h0123456789abcdefE"), ["core", "fmt"]);
+    assert_eq!(namespaces("ns::f(std::string)"), ["ns"]);
+    assert!(namespaces("main").is_empty());
+    let names = nested_file_names(&["_ZN1a1fEv", "a::f", "main"], "md");
+    assert_eq!(names, ["a/_ZN1a1fEv.md", "a/a__f.md", "main.md"]);
+}
@@ -0,0 +1,84 @@
+use learning_llvm_tools::text_diff::{diff_lines, DiffOp};
+
+/// Checks that `ops` turns `old` into `new`, returning the number of edits.
+fn edits(old: &[&str], new: &[&str], ops: &[DiffOp]) -> usize {
+    let before: Vec<&str> = ops.iter().filter_map(|op| match op { DiffOp::Equal(l) | DiffOp::Delete(l) => Some(*l), _ => None }).collect();
+    let after: Vec<&str> = ops.iter().filter_map(|op| match op { DiffOp::Equal(l) | DiffOp::Insert(l) => Some(*l), _ => None }).collect();
+    assert_eq!(before, old);
+    assert_eq!(after, new);
+    ops.iter().filter(|op| !matches!(op, DiffOp::Equal(_))).count()
+}
+
+/// The length of the longest common subsequence, by dynamic programming.
+fn lcs(old: &[&str], new: &[&str]) -> usize {
+    let mut row = vec![0; new.len() + 1];
+    for a in old {
+        let mut diagonal = 0;
+        for (j, b) in new.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if a == b { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    row[new.len()]
+}
+
+#[test]
+fn identical_inputs_are_all_equal() {
+    let lines = ["a", "b", "c"];
+    assert_eq!(diff_lines(&lines, &lines), vec![DiffOp::Equal("a"), DiffOp::Equal("b"), DiffOp::Equal("c")]);
+    assert_eq!(diff_lines(&[], &[]), vec![]);
+}
+
+#[test]
+fn insertions_only() {
+    assert_eq!(diff_lines(&["a", "c"], &["a", "b", "c", "d"]),
+        vec![DiffOp::Equal("a"), DiffOp::Insert("b"), DiffOp::Equal("c"), DiffOp::Insert("d")]);
+    assert_eq!(diff_lines(&[], &["a"]), vec![DiffOp::Insert("a")]);
+}
+
+#[test]
+fn deletions_only() {
+    assert_eq!(diff_lines(&["a", "b", "c", "d"], &["b", "d"]),
+        vec![DiffOp::Delete("a"), DiffOp::Equal("b"), DiffOp::Delete("c"), DiffOp::Equal("d")]);
+    assert_eq!(diff_lines(&["a"], &[]), vec![DiffOp::Delete("a")]);
+}
+
+#[test]
+fn mixed_edits_are_shortest() {
+    let (old, new) = (["a", "b", "c", "a", "b", "b", "a"], ["c", "b", "a", "b", "a", "c"]);
+    let ops = diff_lines(&old, &new);
+    assert_eq!(edits(&old, &new, &ops), 5);
+
+    // pseudo-random lines from a small alphabet, checked against the longest common subsequence
+    let mut seed = 12345u32;
+    let mut next = || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        ["x", "y", "z"][(seed >> 16) as usize % 3]
+    };
+    for length in 0..40 {
+        let old: Vec<&str> = (0..length).map(|_| next()).collect();
+        let new: Vec<&str> = (0..40 - length).map(|_| next()).collect();
+        let ops = diff_lines(&old, &new);
+        assert_eq!(edits(&old, &new, &ops), old.len() + new.len() - 2 * lcs(&old, &new), "{old:?} {new:?}");
+    }
+}
+
+#[test]
+fn too_many_edits_replace_everything() {
+    // 20002 edits when the shared line is kept, beyond the limit of 10000
+    let old_lines: Vec<String> = std::iter::once("x".to_string()).chain((0..10_001).map(|i| format!("a{i}"))).collect();
+    let new_lines: Vec<String> = (0..10_001).map(|i| format!("b{i}")).chain(std::iter::once("x".to_string())).collect();
+    let old: Vec<&str> = old_lines.iter().map(String::as_str).collect();
+    let new: Vec<&str> = new_lines.iter().map(String::as_str).collect();
+    let ops = diff_lines(&old, &new);
+    assert_eq!(edits(&old, &new, &ops), old.len() + new.len());
+    assert!(ops[..old.len()].iter().all(|op| matches!(op, DiffOp::Delete(_))));
+
+    // a common prefix and suffix are kept even then
+    let old: Vec<&str> = ["head"].into_iter().chain(old).chain(["tail"]).collect();
+    let new: Vec<&str> = ["head"].into_iter().chain(new).chain(["tail"]).collect();
+    let ops = diff_lines(&old, &new);
+    assert_eq!((ops[0], ops[ops.len() - 1]), (DiffOp::Equal("head"), DiffOp::Equal("tail")));
+    assert_eq!(edits(&old, &new, &ops), old.len() + new.len() - 4);
+}
@@ -0,0 +1,60 @@
+use learning_llvm_tools::parser::parse_ll_str;
+use learning_llvm_tools::verify::{phi_incoming_blocks, verify, ViolationKind};
+
+/// A diamond whose join block has the phi `phi`.
+fn diamond(phi: &str) -> String {
+    format!("\
+define i32 @f(i1 %c) {{
+entry:
+  br i1 %c, label %then, label %else
+
+then:
+  br label %join
+
+else:
+  br label %join
+
+other:
+  br label %join
+
+join:
+  {phi}
+  ret i32 %x
+}}
+")
+}
+
+fn phi_violations(phi: &str) -> Vec<ViolationKind> {
+    let text = diamond(phi);
+    let module = parse_ll_str(&text);
+    verify(&module.functions[0]).into_iter()
+        .map(|v| {
+            assert_eq!(v.block, "join");
+            v.kind
+        })
+        .collect()
+}
+
+#[test]
+fn phi_with_an_incoming_value_per_predecessor_is_valid() {
+    // %other is unreachable, but still a predecessor of %join
+    assert_eq!(phi_violations("%x = phi i32 [ 1, %then ], [ 2, %else ], [ 3, %other ]"), vec![]);
+    assert_eq!(phi_incoming_blocks("%x = phi i32 [ 1, %then ], [ %y, %else ]"), vec!["then", "else"]);
+}
+
+#[test]
+fn incoming_block_that_is_not_a_predecessor() {
+    assert_eq!(phi_violations("%x = phi i32 [ 1, %then ], [ 2, %else ], [ 3, %other ], [ 4, %entry ]"),
+        vec![ViolationKind::PhiIncomingMismatch { missing: vec![], extra: vec!["%entry".to_string()] }]);
+}
+
+#[test]
+fn predecessor_without_an_incoming_value() {
+    let violations = phi_violations("%x = phi i32 [ 1, %then ], [ 3, %other ]");
+    assert_eq!(violations, vec![ViolationKind::PhiIncomingMismatch { missing: vec!["%else".to_string()], extra: vec![] }]);
+
+    let text = diamond("%x = phi i32 [ 1, %then ], [ 4, %entry ]");
+    let module = parse_ll_str(&text);
+    let messages: Vec<String> = verify(&module.functions[0]).iter().map(|v| v.to_string()).collect();
+    assert_eq!(messages, ["%join: phi missing incoming for %else, %other; incoming from non-predecessors %entry: `%x = phi i32 [ 1, %then ], [ 4, %entry ]`"]);
+}